@@ -0,0 +1,125 @@
+//! Node.js bindings of `newline_normalizer`, through napi-rs: the normalizers for strings
+//! and `Buffer`s, each also as an async function that runs on the libuv worker pool.
+//!
+//! Strings are copied into UTF-8 on the way in and back out on the way out. `Buffer`s are
+//! read where they are, so large documents held as bytes cost one copy, the result.
+
+#![deny(clippy::all)]
+
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, Error, Result, Status, Task};
+use napi_derive::napi;
+
+use newline_normalizer::LineEnding;
+
+/// Returns `text` with every CRLF and lone CR turned into an LF.
+#[napi]
+pub fn to_unix(text: String) -> String {
+    LineEnding::Lf.normalize(&text).into_owned()
+}
+
+/// Returns `text` with every LF and lone CR turned into a CRLF.
+#[napi]
+pub fn to_dos(text: String) -> String {
+    LineEnding::CrLf.normalize(&text).into_owned()
+}
+
+/// Returns `text` normalized to `ending`, `"lf"` or `"crlf"`; throws for any other.
+#[napi]
+pub fn normalize(text: String, ending: String) -> Result<String> {
+    Ok(parse(&ending)?.normalize(&text).into_owned())
+}
+
+/// Returns the bytes of `data` normalized to `ending`, `"lf"` or `"crlf"`, in a new
+/// `Buffer`. Bytes that are not UTF-8 are passed through.
+#[napi]
+pub fn normalize_buffer(data: Buffer, ending: String) -> Result<Buffer> {
+    Ok(normalize_bytes(&data, parse(&ending)?).into())
+}
+
+/// Like `normalize()`, on a worker thread; resolves to the normalized string.
+#[napi(ts_return_type = "Promise<string>")]
+pub fn normalize_async(text: String, ending: String) -> Result<AsyncTask<NormalizeText>> {
+    Ok(AsyncTask::new(NormalizeText { text, ending: parse(&ending)? }))
+}
+
+/// Like `normalizeBuffer()`, on a worker thread; resolves to a new `Buffer`.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn normalize_buffer_async(data: Buffer, ending: String) -> Result<AsyncTask<NormalizeBuffer>> {
+    Ok(AsyncTask::new(NormalizeBuffer { data, ending: parse(&ending)? }))
+}
+
+pub struct NormalizeText {
+    text: String,
+    ending: LineEnding,
+}
+
+impl Task for NormalizeText {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<String> {
+        Ok(self.ending.normalize(&self.text).into_owned())
+    }
+
+    fn resolve(&mut self, _env: Env, output: String) -> Result<String> {
+        Ok(output)
+    }
+}
+
+pub struct NormalizeBuffer {
+    data: Buffer,
+    ending: LineEnding,
+}
+
+impl Task for NormalizeBuffer {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Vec<u8>> {
+        Ok(normalize_bytes(&self.data, self.ending))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+fn normalize_bytes(mut data: &[u8], ending: LineEnding) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    // Reading from a slice into a `Vec` cannot fail.
+    newline_normalizer::normalize_copy(&mut data, &mut out, ending).expect("in-memory copy");
+    out
+}
+
+fn parse(ending: &str) -> Result<LineEnding> {
+    ending_named(ending).ok_or_else(|| Error::new(Status::InvalidArg, "line ending must be \"lf\" or \"crlf\""))
+}
+
+fn ending_named(ending: &str) -> Option<LineEnding> {
+    match ending {
+        "lf" => Some(LineEnding::Lf),
+        "crlf" => Some(LineEnding::CrLf),
+        _ => None,
+    }
+}
+
+// The exported functions need a Node.js host, so the tests keep to what has none.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_and_names() {
+        assert_eq!(normalize_bytes(b"a\r\nb\r\xFF", LineEnding::Lf), b"a\nb\n\xFF");
+        assert_eq!(normalize_bytes(b"a\nb", LineEnding::CrLf), b"a\r\nb");
+        assert_eq!(ending_named("lf"), Some(LineEnding::Lf));
+        assert_eq!(ending_named("LF"), None);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(to_unix("a\r\nb\rc".into()), "a\nb\nc");
+        assert_eq!(to_dos("a\nb\r\n".into()), "a\r\nb\r\n");
+    }
+}
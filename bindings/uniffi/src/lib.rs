@@ -0,0 +1,152 @@
+//! Swift and Kotlin bindings of `newline_normalizer`, through UniFFI: the normalizers, the
+//! detector, and a stream normalizer for text arriving in chunks.
+//!
+//! The types mirror the crate's own, in the shapes UniFFI can carry: counts are `u64`, and
+//! the stream normalizer is an object shared between threads.
+
+use std::sync::Mutex;
+
+uniffi::setup_scaffolding!();
+
+/// A line ending the normalizers can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl From<LineEnding> for newline_normalizer::LineEnding {
+    fn from(ending: LineEnding) -> Self {
+        match ending {
+            LineEnding::Lf => newline_normalizer::LineEnding::Lf,
+            LineEnding::CrLf => newline_normalizer::LineEnding::CrLf,
+        }
+    }
+}
+
+/// A byte order mark, and with it the encoding the input most likely uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// The line endings found in a text, from [`analyze`].
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Analysis {
+    pub bom: Option<Bom>,
+    pub lf: u64,
+    pub crlf: u64,
+    pub cr: u64,
+    /// More than one kind of line ending is present.
+    pub mixed: bool,
+}
+
+impl From<newline_normalizer::Analysis> for Analysis {
+    fn from(analysis: newline_normalizer::Analysis) -> Self {
+        let bom = analysis.bom.map(|bom| match bom {
+            newline_normalizer::Bom::Utf8 => Bom::Utf8,
+            newline_normalizer::Bom::Utf16Le => Bom::Utf16Le,
+            newline_normalizer::Bom::Utf16Be => Bom::Utf16Be,
+        });
+        Analysis { bom, lf: analysis.lf as u64, crlf: analysis.crlf as u64, cr: analysis.cr as u64, mixed: analysis.is_mixed() }
+    }
+}
+
+/// Returns `text` with every CRLF and lone CR turned into an LF.
+#[uniffi::export]
+pub fn to_unix(text: &str) -> String {
+    normalize(text, LineEnding::Lf)
+}
+
+/// Returns `text` with every LF and lone CR turned into a CRLF.
+#[uniffi::export]
+pub fn to_dos(text: &str) -> String {
+    normalize(text, LineEnding::CrLf)
+}
+
+/// Returns `text` with every line ending turned into `ending`.
+#[uniffi::export]
+pub fn normalize(text: &str, ending: LineEnding) -> String {
+    newline_normalizer::LineEnding::from(ending).normalize(text).into_owned()
+}
+
+/// Counts the line endings of `text`.
+#[uniffi::export]
+pub fn analyze(text: &str) -> Analysis {
+    newline_normalizer::analyze(text).into()
+}
+
+/// Counts the line endings of encoded text; with a UTF-16 byte order mark, in 16-bit units.
+#[uniffi::export]
+pub fn analyze_bytes(data: &[u8]) -> Analysis {
+    newline_normalizer::analyze(data).into()
+}
+
+/// The only kind of line ending in `text`, or `None` if it has none or several.
+#[uniffi::export]
+pub fn detect(text: &str) -> Option<LineEnding> {
+    match analyze(text) {
+        Analysis { lf: 1.., crlf: 0, cr: 0, .. } => Some(LineEnding::Lf),
+        Analysis { lf: 0, crlf: 1.., cr: 0, .. } => Some(LineEnding::CrLf),
+        _ => None,
+    }
+}
+
+/// Normalizes text arriving in chunks, keeping a CRLF split between two chunks one newline.
+/// Call `finish()` once the input ends and append what it returns.
+#[derive(Debug, uniffi::Object)]
+pub struct StreamNormalizer {
+    inner: Mutex<newline_normalizer::StreamNormalizer>,
+}
+
+#[uniffi::export]
+impl StreamNormalizer {
+    #[uniffi::constructor]
+    pub fn new(ending: LineEnding) -> Self {
+        StreamNormalizer { inner: Mutex::new(newline_normalizer::StreamNormalizer::new(ending.into())) }
+    }
+
+    /// Returns the normalized form of the next chunk.
+    pub fn push(&self, chunk: &str) -> String {
+        self.lock().push(chunk).into_owned()
+    }
+
+    /// Returns what is left once the input ends.
+    pub fn finish(&self) -> String {
+        self.lock().finish().to_owned()
+    }
+}
+
+impl StreamNormalizer {
+    fn lock(&self) -> std::sync::MutexGuard<'_, newline_normalizer::StreamNormalizer> {
+        // A panic while normalizing leaves no broken state behind.
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_and_detects() {
+        assert_eq!(to_unix("a\r\nb\rc"), "a\nb\nc");
+        assert_eq!(to_dos("a\nb"), "a\r\nb");
+        assert_eq!(analyze("a\r\nb\n"), Analysis { bom: None, lf: 1, crlf: 1, cr: 0, mixed: true });
+        assert_eq!(analyze_bytes(b"\xEF\xBB\xBFa\r\n").bom, Some(Bom::Utf8));
+        assert_eq!(detect("a\r\nb\r\n"), Some(LineEnding::CrLf));
+        assert_eq!(detect("a\r\nb\n"), None);
+        assert_eq!(detect("a"), None);
+    }
+
+    #[test]
+    fn streams_carry_a_split_crlf() {
+        let stream = StreamNormalizer::new(LineEnding::Lf);
+        let out = stream.push("one\r") + &stream.push("\ntwo\r") + &stream.finish();
+        assert_eq!(out, "one\ntwo\n");
+    }
+}
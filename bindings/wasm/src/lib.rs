@@ -0,0 +1,4 @@
+//! The npm package of `newline_normalizer`: its `wasm` bindings, built into WebAssembly by
+//! `wasm-pack build`.
+
+pub use newline_normalizer::wasm::*;
@@ -0,0 +1,40 @@
+#ifndef NEWLINE_NORMALIZER_H
+#define NEWLINE_NORMALIZER_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Writes the Unix form of the `len` bytes at `input`, every CRLF and lone CR turned into
+// an LF, to `out`, and returns its length, which is at most `len`.
+//
+// # Safety
+//
+// `input` must point to `len` readable bytes, and `out` to `out_cap` writable ones; either
+// may be null when its length is 0. The two may overlap, so `nn_to_unix(text, len, text,
+// len)` converts in place.
+size_t nn_to_unix(const char *input, size_t len, char *out, size_t out_cap);
+
+// Writes the DOS form of the `len` bytes at `input`, every LF and lone CR turned into a
+// CRLF, to `out`, and returns its length, which is at most [`nn_to_dos_max_len`] of `len`.
+//
+// # Safety
+//
+// As for [`nn_to_unix`].
+size_t nn_to_dos(const char *input, size_t len, char *out, size_t out_cap);
+
+// The longest [`nn_to_dos`] can make `len` bytes, for sizing a buffer up front.
+size_t nn_to_dos_max_len(size_t len);
+
+// The version of the library, as a NUL-terminated string such as `"0.1.6"`.
+const char *nn_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NEWLINE_NORMALIZER_H */
@@ -0,0 +1,346 @@
+//! Single-pass newline analysis.
+//!
+//! Counts every kind of line ending in the input and, on request, profiles line lengths
+//! in the same scan. A leading byte order mark is reported and, for UTF-16, determines
+//! how line endings are read.
+
+/// Line endings found in a piece of text.
+///
+/// - A CR immediately followed by LF counts as a single CRLF.
+/// - `line_lengths` is only populated by [`analyze_with_line_lengths`].
+/// - With a UTF-16 BOM, line endings are counted in 16-bit code units of that byte order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// Byte order mark at the start of the input, if any.
+    pub bom: Option<Bom>,
+    /// Number of bare LF (`\n`) line endings.
+    pub lf: usize,
+    /// Number of CRLF (`\r\n`) line endings.
+    pub crlf: usize,
+    /// Number of lone CR (`\r`) line endings.
+    pub cr: usize,
+    /// Line length statistics, if they were requested.
+    pub line_lengths: Option<LineLengths>,
+}
+
+impl Analysis {
+    /// Total number of line endings of any kind.
+    pub fn newlines(&self) -> usize {
+        self.lf + self.crlf + self.cr
+    }
+
+    /// Returns `true` if more than one kind of line ending is present.
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr].iter().filter(|&&count| count > 0).count() > 1
+    }
+
+    /// The more common of LF and CRLF, LF on a tie, or `None` if there is neither. Lone
+    /// CRs are not counted, as no normalizer produces them.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::{analyze, LineEnding};
+    ///
+    /// assert_eq!(analyze("a\r\nb\r\nc\n").dominant(), Some(LineEnding::CrLf));
+    /// assert_eq!(analyze("a\rb").dominant(), None);
+    /// ```
+    pub fn dominant(&self) -> Option<crate::LineEnding> {
+        match (self.lf, self.crlf) {
+            (0, 0) => None,
+            (lf, crlf) if crlf > lf => Some(crate::LineEnding::CrLf),
+            _ => Some(crate::LineEnding::Lf),
+        }
+    }
+}
+
+/// Byte order mark, and with it the encoding the input most likely uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bom {
+    /// `EF BB BF`
+    Utf8,
+    /// `FF FE`
+    Utf16Le,
+    /// `FE FF`
+    Utf16Be,
+}
+
+impl Bom {
+    /// Detects a byte order mark at the start of `bytes`.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::Bom;
+    ///
+    /// assert_eq!(Bom::detect(b"\xEF\xBB\xBFtext"), Some(Bom::Utf8));
+    /// assert_eq!(Bom::detect(b"text"), None);
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Option<Bom> {
+        if bytes.starts_with(Bom::Utf8.as_bytes()) {
+            Some(Bom::Utf8)
+        } else if bytes.starts_with(Bom::Utf16Le.as_bytes()) {
+            Some(Bom::Utf16Le)
+        } else if bytes.starts_with(Bom::Utf16Be.as_bytes()) {
+            Some(Bom::Utf16Be)
+        } else {
+            None
+        }
+    }
+
+    /// The encoded byte order mark.
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Bom::Utf8 => b"\xEF\xBB\xBF",
+            Bom::Utf16Le => b"\xFF\xFE",
+            Bom::Utf16Be => b"\xFE\xFF",
+        }
+    }
+
+    /// Name of the encoding the byte order mark stands for, e.g. `"UTF-16LE"`.
+    pub fn encoding(self) -> &'static str {
+        match self {
+            Bom::Utf8 => "UTF-8",
+            Bom::Utf16Le => "UTF-16LE",
+            Bom::Utf16Be => "UTF-16BE",
+        }
+    }
+}
+
+/// Line length statistics, measured in bytes and excluding the line terminator.
+///
+/// A trailing line without a terminator is counted; an empty input has no lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineLengths {
+    /// Number of lines.
+    pub lines: usize,
+    /// Sum of all line lengths.
+    pub total: usize,
+    /// Length of the longest line.
+    pub max: usize,
+    /// Byte offset where the longest line starts. The first one wins on ties.
+    pub longest_line_offset: usize,
+}
+
+impl LineLengths {
+    /// Mean line length, or `0.0` for an input without lines.
+    pub fn mean(&self) -> f64 {
+        if self.lines == 0 {
+            return 0.0;
+        }
+        self.total as f64 / self.lines as f64
+    }
+
+    fn record(&mut self, start: usize, end: usize) {
+        let len = end - start;
+        if len > self.max {
+            self.max = len;
+            self.longest_line_offset = start;
+        }
+        self.lines += 1;
+        self.total += len;
+    }
+}
+
+/// Counts LF, CRLF and lone CR line endings and detects a byte order mark.
+///
+/// Accepts anything byte-like, so both `&str` and raw file contents can be analyzed.
+///
+/// Example:
+/// ```
+/// let analysis = newline_normalizer::analyze("one\r\ntwo\nthree\r");
+/// assert_eq!((analysis.lf, analysis.crlf, analysis.cr), (1, 1, 1));
+/// assert!(analysis.is_mixed());
+/// ```
+pub fn analyze<T: AsRef<[u8]> + ?Sized>(text: &T) -> Analysis {
+    scan(text.as_ref(), None)
+}
+
+/// Like [`analyze`], but also collects [`LineLengths`] during the same scan.
+///
+/// Example:
+/// ```
+/// let analysis = newline_normalizer::analyze_with_line_lengths("ab\r\nabcd\nabc");
+/// let lengths = analysis.line_lengths.unwrap();
+/// assert_eq!(lengths.lines, 3);
+/// assert_eq!(lengths.max, 4);
+/// assert_eq!(lengths.longest_line_offset, 4);
+/// assert_eq!(lengths.mean(), 3.0);
+/// ```
+pub fn analyze_with_line_lengths<T: AsRef<[u8]> + ?Sized>(text: &T) -> Analysis {
+    scan(text.as_ref(), Some(LineLengths::default()))
+}
+
+fn scan(slice: &[u8], lengths: Option<LineLengths>) -> Analysis {
+    let bom = Bom::detect(slice);
+    let start = bom.map_or(0, |bom| bom.as_bytes().len());
+    let mut analysis = match bom {
+        Some(Bom::Utf16Le) => scan_utf16(slice, start, u16::from_le_bytes, lengths),
+        Some(Bom::Utf16Be) => scan_utf16(slice, start, u16::from_be_bytes, lengths),
+        Some(Bom::Utf8) | None => scan_bytes(slice, start, lengths),
+    };
+    analysis.bom = bom;
+    analysis
+}
+
+fn scan_bytes(slice: &[u8], start: usize, mut lengths: Option<LineLengths>) -> Analysis {
+    let mut analysis = Analysis::default();
+    let mut iter = crate::search::memchr2_iter(b'\n', b'\r', &slice[start..]).map(|pos| pos + start);
+    let mut line_start = start;
+
+    while let Some(pos) = iter.next() {
+        let next = if slice[pos] == b'\n' {
+            analysis.lf += 1;
+            pos + 1
+        } else if slice.get(pos + 1) == Some(&b'\n') {
+            analysis.crlf += 1;
+            iter.next();
+            pos + 2
+        } else {
+            analysis.cr += 1;
+            pos + 1
+        };
+
+        if let Some(lengths) = lengths.as_mut() {
+            lengths.record(line_start, pos);
+        }
+        line_start = next;
+    }
+
+    if let Some(lengths) = lengths.as_mut() {
+        if line_start < slice.len() {
+            lengths.record(line_start, slice.len());
+        }
+    }
+
+    analysis.line_lengths = lengths;
+    analysis
+}
+
+fn scan_utf16(
+    slice: &[u8],
+    start: usize,
+    decode: fn([u8; 2]) -> u16,
+    mut lengths: Option<LineLengths>,
+) -> Analysis {
+    let mut analysis = Analysis::default();
+    let unit_at = |pos: usize| slice.get(pos..pos + 2).map(|unit| decode([unit[0], unit[1]]));
+    let mut line_start = start;
+    let mut pos = start;
+
+    while let Some(unit) = unit_at(pos) {
+        let next = match unit {
+            0x0A => {
+                analysis.lf += 1;
+                pos + 2
+            }
+            0x0D if unit_at(pos + 2) == Some(0x0A) => {
+                analysis.crlf += 1;
+                pos + 4
+            }
+            0x0D => {
+                analysis.cr += 1;
+                pos + 2
+            }
+            _ => {
+                pos += 2;
+                continue;
+            }
+        };
+
+        if let Some(lengths) = lengths.as_mut() {
+            lengths.record(line_start, pos);
+        }
+        line_start = next;
+        pos = next;
+    }
+
+    if let Some(lengths) = lengths.as_mut() {
+        if line_start < slice.len() {
+            lengths.record(line_start, slice.len());
+        }
+    }
+
+    analysis.line_lengths = lengths;
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_each_kind_of_newline() {
+        let analysis = analyze("a\r\nb\nc\rd\r\n");
+        assert_eq!((analysis.lf, analysis.crlf, analysis.cr), (1, 2, 1));
+        assert_eq!(analysis.newlines(), 4);
+        assert!(analysis.is_mixed());
+        assert_eq!(analysis.line_lengths, None);
+    }
+
+    #[test]
+    fn cr_cr_lf_is_one_cr_and_one_crlf() {
+        let analysis = analyze("a\r\r\nb");
+        assert_eq!((analysis.lf, analysis.crlf, analysis.cr), (0, 1, 1));
+    }
+
+    #[test]
+    fn dominant_ending_ignores_lone_crs() {
+        assert_eq!(analyze("a\nb\r\n").dominant(), Some(crate::LineEnding::Lf));
+        assert_eq!(analyze("a\rb\rc\r\n").dominant(), Some(crate::LineEnding::CrLf));
+        assert_eq!(analyze("").dominant(), None);
+    }
+
+    #[test]
+    fn empty_input_has_no_lines() {
+        let analysis = analyze_with_line_lengths("");
+        assert_eq!(analysis.line_lengths, Some(LineLengths::default()));
+        assert_eq!(analysis.line_lengths.unwrap().mean(), 0.0);
+        assert!(!analysis.is_mixed());
+    }
+
+    #[test]
+    fn trailing_newline_does_not_add_a_line() {
+        let lengths = analyze_with_line_lengths("abc\n\n").line_lengths.unwrap();
+        assert_eq!(lengths.lines, 2);
+        assert_eq!(lengths.total, 3);
+    }
+
+    #[test]
+    fn finds_longest_unterminated_last_line() {
+        let lengths = analyze_with_line_lengths("a\r\nbb\rcccc").line_lengths.unwrap();
+        assert_eq!(lengths.max, 4);
+        assert_eq!(lengths.longest_line_offset, 6);
+    }
+
+    #[test]
+    fn reports_utf8_bom_and_skips_it() {
+        let analysis = analyze_with_line_lengths("\u{FEFF}ab\r\n");
+        assert_eq!(analysis.bom, Some(Bom::Utf8));
+        assert_eq!(analysis.crlf, 1);
+        let lengths = analysis.line_lengths.unwrap();
+        assert_eq!((lengths.max, lengths.longest_line_offset), (2, 3));
+    }
+
+    #[test]
+    fn counts_utf16le_code_units() {
+        // BOM, "a", CRLF, U+0A0D (contains both newline bytes), LF
+        let input = b"\xFF\xFEa\0\r\0\n\0\x0D\x0A\n\0";
+        let analysis = analyze_with_line_lengths(&input[..]);
+        assert_eq!(analysis.bom, Some(Bom::Utf16Le));
+        assert_eq!((analysis.lf, analysis.crlf, analysis.cr), (1, 1, 0));
+        assert_eq!(analysis.line_lengths.unwrap().lines, 2);
+    }
+
+    #[test]
+    fn counts_utf16be_code_units() {
+        let input = b"\xFE\xFF\0a\0\r\0b\0\r\0\n";
+        let analysis = analyze(&input[..]);
+        assert_eq!(analysis.bom, Some(Bom::Utf16Be));
+        assert_eq!((analysis.lf, analysis.crlf, analysis.cr), (0, 1, 1));
+    }
+
+    #[test]
+    fn accepts_bytes() {
+        let analysis = analyze(&b"\xff\r\n\xfe"[..]);
+        assert_eq!(analysis.crlf, 1);
+    }
+}
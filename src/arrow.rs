@@ -0,0 +1,106 @@
+//! Whole Arrow string columns at once, enabled by the `arrow` feature.
+//!
+//! [`normalize_array`] walks the values of a `StringArray` or `LargeStringArray` and builds
+//! a single new values buffer with rebuilt offsets, instead of allocating a string per cell.
+//! Rows before the first one that changes are copied in one go, and a column that needs no
+//! change comes back as a clone sharing its buffers. [`normalize_column`] takes the
+//! `ArrayRef` columns of a `RecordBatch`.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+pub use ::arrow_array::{Array, ArrayRef, GenericStringArray, LargeStringArray, OffsetSizeTrait, StringArray};
+use ::arrow_array::cast::AsArray;
+use ::arrow_buffer::{Buffer, OffsetBuffer, ScalarBuffer};
+pub use ::arrow_schema::ArrowError;
+use ::arrow_schema::DataType;
+
+use crate::LineEnding;
+
+/// Returns `array` with every value normalized to `ending`, nulls kept as they are.
+///
+/// # Errors
+///
+/// [`ArrowError::OffsetOverflowError`] if converting to CRLF makes the values outgrow the
+/// offsets, past 2 GiB for a `StringArray`.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::arrow::{normalize_array, Array, StringArray};
+/// use newline_normalizer::LineEnding;
+///
+/// let column = StringArray::from(vec![Some("a\r\nb"), None, Some("c\n")]);
+/// let normalized = normalize_array(&column, LineEnding::Lf)?;
+/// assert_eq!(normalized, StringArray::from(vec![Some("a\nb"), None, Some("c\n")]));
+///
+/// let again = normalize_array(&normalized, LineEnding::Lf)?;
+/// assert_eq!(again.values().as_ptr(), normalized.values().as_ptr());
+/// # Ok::<(), newline_normalizer::arrow::ArrowError>(())
+/// ```
+pub fn normalize_array<O: OffsetSizeTrait>(array: &GenericStringArray<O>, ending: LineEnding) -> Result<GenericStringArray<O>, ArrowError> {
+    let offsets = array.value_offsets();
+    let all = array.values().as_slice();
+    let row = |i: usize| &all[offsets[i].as_usize()..offsets[i + 1].as_usize()];
+
+    let mut normalized = Vec::new();
+    let Some(first) = (0..array.len()).find(|&i| ending.normalize_bytes_into(row(i), &mut normalized)) else {
+        return Ok(array.clone());
+    };
+
+    let start = offsets[0].as_usize();
+    let mut values = Vec::with_capacity(all.len() - start + all.len() / 16);
+    values.extend_from_slice(&all[start..offsets[first].as_usize()]);
+    let mut new_offsets = Vec::with_capacity(offsets.len());
+    new_offsets.extend(offsets[..=first].iter().map(|&offset| offset - offsets[0]));
+    values.append(&mut normalized);
+    new_offsets.push(offset(values.len())?);
+    for i in first + 1..array.len() {
+        if !ending.normalize_bytes_into(row(i), &mut values) {
+            values.extend_from_slice(row(i));
+        }
+        new_offsets.push(offset(values.len())?);
+    }
+
+    let offsets = OffsetBuffer::new(ScalarBuffer::from(new_offsets));
+    // Only ASCII newline bytes of valid UTF-8 values were rewritten, so the values stay valid
+    // UTF-8 and every offset still falls on a character boundary.
+    Ok(unsafe { GenericStringArray::new_unchecked(offsets, Buffer::from_vec(values), array.nulls().cloned()) })
+}
+
+/// Like [`normalize_array`], for a column of any type: `Utf8` and `LargeUtf8` columns are
+/// normalized, others come back as they are.
+pub fn normalize_column(column: &ArrayRef, ending: LineEnding) -> Result<ArrayRef, ArrowError> {
+    Ok(match column.data_type() {
+        DataType::Utf8 => Arc::new(normalize_array(column.as_string::<i32>(), ending)?),
+        DataType::LargeUtf8 => Arc::new(normalize_array(column.as_string::<i64>(), ending)?),
+        _ => Arc::clone(column),
+    })
+}
+
+fn offset<O: OffsetSizeTrait>(len: usize) -> Result<O, ArrowError> {
+    O::from_usize(len).ok_or(ArrowError::OffsetOverflowError(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilds_offsets_of_sliced_arrays() {
+        let column = StringArray::from(vec![Some("skip\r"), Some("a"), None, Some("b\nc"), Some(""), Some("\r\r\n")]).slice(1, 5);
+        let normalized = normalize_array(&column, LineEnding::CrLf).unwrap();
+        assert_eq!(normalized, StringArray::from(vec![Some("a"), None, Some("b\r\nc"), Some(""), Some("\r\n\r\n")]));
+        assert_eq!(normalized.value_offsets()[0], 0);
+        assert_eq!(normalized.values().len(), 9);
+    }
+
+    #[test]
+    fn columns_of_any_type() {
+        let large: ArrayRef = Arc::new(LargeStringArray::from(vec!["x\r\n"]));
+        let normalized = normalize_column(&large, LineEnding::Lf).unwrap();
+        assert_eq!(normalized.as_string::<i64>().value(0), "x\n");
+
+        let ints: ArrayRef = Arc::new(::arrow_array::Int32Array::from(vec![1, 2]));
+        assert!(Arc::ptr_eq(&normalize_column(&ints, LineEnding::Lf).unwrap(), &ints));
+    }
+}
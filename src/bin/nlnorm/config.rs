@@ -0,0 +1,193 @@
+//! Project defaults from an `nlnorm.toml` file, or the `[tool.nlnorm]` table of a
+//! `pyproject.toml`, found in the current directory or the closest parent that has one:
+//!
+//! ```toml
+//! # Processed when no paths are given; relative to this file.
+//! paths = ["src", "scripts"]
+//! # For `convert`, `watch` and `hook`, and the default of `fix`.
+//! to = "lf"
+//! # As `--glob`, and `--glob '!...'` for the excludes.
+//! globs = ["**/*.rs", "**/*.md"]
+//! exclude = ["vendor/**"]
+//! # keep, strip or add.
+//! bom = "strip"
+//! # As `--map`; entries given on the command line come after these.
+//! map = ["*.bat=crlf", "*.sh=lf"]
+//! ```
+//!
+//! Options given on the command line win. `--no-config` ignores the file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use newline_normalizer::BomPolicy;
+use serde::Deserialize;
+
+use crate::map::Mapping;
+use crate::{Ending, Options};
+
+const FILE: &str = "nlnorm.toml";
+const PYPROJECT: &str = "pyproject.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+    to: Option<Ending>,
+    #[serde(default)]
+    globs: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    bom: Option<Bom>,
+    #[serde(default)]
+    map: Vec<String>,
+    /// The directory of the file, relative to the current one.
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Bom {
+    Keep,
+    Strip,
+    Add,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pyproject {
+    tool: Option<Tool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tool {
+    nlnorm: Option<Config>,
+}
+
+impl Config {
+    /// Looks for a config in `cwd`, an absolute path, and then in its parents. A
+    /// `pyproject.toml` without a `[tool.nlnorm]` table does not count.
+    pub(crate) fn discover(cwd: &Path) -> Result<Option<Config>, String> {
+        let mut dir = PathBuf::new();
+        for ancestor in cwd.ancestors() {
+            let found = match read(&ancestor.join(FILE))? {
+                Some(text) => Some(toml::from_str::<Config>(&text).map_err(|e| format!("{}: {}", ancestor.join(FILE).display(), e.to_string().trim_end()))?),
+                None => match read(&ancestor.join(PYPROJECT))? {
+                    Some(text) => toml::from_str::<Pyproject>(&text)
+                        .map_err(|e| format!("{}: {}", ancestor.join(PYPROJECT).display(), e.to_string().trim_end()))?
+                        .tool
+                        .and_then(|tool| tool.nlnorm),
+                    None => None,
+                },
+            };
+            if let Some(config) = found {
+                return Ok(Some(Config { dir, ..config }));
+            }
+            dir.push("..");
+        }
+        Ok(None)
+    }
+
+    /// Fills in what the command line left out: the line ending `to`, where the command
+    /// takes one, and `options`.
+    pub(crate) fn apply(&self, to: Option<&mut Option<Ending>>, options: &mut Options) -> Result<(), String> {
+        if let (Some(to), Some(ending)) = (to, self.to) {
+            to.get_or_insert(ending);
+        }
+        if options.files.is_empty() {
+            options.files = self.paths.iter().map(|path| self.dir.join(path)).collect();
+        }
+        if options.globs.is_empty() {
+            options.globs = self.globs.clone();
+        }
+        options.globs.extend(self.exclude.iter().map(|glob| format!("!{glob}")));
+        if !options.strip_bom && !options.add_bom {
+            options.default_bom = match self.bom {
+                Some(Bom::Strip) => BomPolicy::Strip,
+                Some(Bom::Add) => BomPolicy::Add,
+                Some(Bom::Keep) | None => BomPolicy::Keep,
+            };
+        }
+        let mut maps = self.map.iter().map(|map| map.parse::<Mapping>().map_err(|e| format!("map: {e}"))).collect::<Result<Vec<_>, _>>()?;
+        maps.append(&mut options.maps);
+        options.maps = maps;
+        Ok(())
+    }
+}
+
+/// The content of the file at `path`, if there is one.
+fn read(path: &Path) -> Result<Option<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {e}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn options(args: &[&str]) -> Options {
+        let (_, options) = crate::Cli::try_parse_from(args).unwrap().command.split();
+        options
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let config: Config = toml::from_str(
+            r#"
+            paths = ["src"]
+            to = "crlf"
+            globs = ["**/*.rs"]
+            exclude = ["vendor/**"]
+            bom = "strip"
+            map = ["*.sh=lf"]
+            "#,
+        )
+        .unwrap();
+        let config = Config { dir: PathBuf::from(".."), ..config };
+
+        let mut defaults = options(&["nlnorm", "unix"]);
+        let mut to = None;
+        config.apply(Some(&mut to), &mut defaults).unwrap();
+        assert_eq!(to, Some(Ending::Crlf));
+        assert_eq!(defaults.files, [PathBuf::from("../src")]);
+        assert_eq!(defaults.globs, ["**/*.rs", "!vendor/**"]);
+        assert_eq!(defaults.bom(), BomPolicy::Strip);
+        assert_eq!(defaults.maps.len(), 1);
+
+        let mut given = options(&["nlnorm", "unix", "--glob", "*.md", "--add-bom", "--map", "*.bat=crlf", "a.txt"]);
+        let mut to = Some(Ending::Lf);
+        config.apply(Some(&mut to), &mut given).unwrap();
+        assert_eq!(to, Some(Ending::Lf));
+        assert_eq!(given.files, [PathBuf::from("a.txt")]);
+        assert_eq!(given.globs, ["*.md", "!vendor/**"]);
+        assert_eq!(given.bom(), BomPolicy::Add);
+        assert_eq!(crate::map::lookup(&given.maps, Path::new("x.sh")), Some(crate::newline::Newline::Lf));
+        assert!(toml::from_str::<Config>("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn discovered_upward() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let nested = dir.join("project/sub/deeper");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.join("project/sub/pyproject.toml"), "[project]\nname = \"other\"\n").unwrap();
+        fs::write(dir.join("project/pyproject.toml"), "[tool.nlnorm]\nto = \"cr\"\n").unwrap();
+
+        let config = Config::discover(&nested).unwrap().unwrap();
+        assert_eq!((config.to, config.dir), (Some(Ending::Cr), PathBuf::from("../..")));
+
+        fs::write(nested.join(FILE), "to = \"native\"\n").unwrap();
+        let config = Config::discover(&nested).unwrap().unwrap();
+        assert_eq!((config.to, config.dir), (Some(Ending::Native), PathBuf::new()));
+
+        fs::write(nested.join(FILE), "to = \"mac\"\n").unwrap();
+        assert!(Config::discover(&nested).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
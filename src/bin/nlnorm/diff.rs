@@ -0,0 +1,230 @@
+//! Unified diffs of what normalizing a file would change.
+//!
+//! Normalizing never adds or drops a line break, it only rewrites them, so the lines of the
+//! old and new text pair up one to one and no diff algorithm is needed; a byte order mark
+//! added or removed only changes the first line. Carriage returns are shown as `^M`, like
+//! `cat -v` does, so that the changed line endings are visible.
+
+use std::io::{self, Write};
+use std::ops::Range;
+
+use newline_normalizer::BomPolicy;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// Writes a unified diff turning `old` into its form with every line break replaced by
+/// `target`, with its byte order mark handled as `bom` says, labelled with `name`.
+///
+/// Writes nothing if `old` is already normalized.
+pub(crate) fn write_diff<W: Write + ?Sized>(
+    out: &mut W,
+    name: &str,
+    old: &[u8],
+    target: &[u8],
+    bom: BomPolicy,
+) -> io::Result<()> {
+    let lines = Line::split(old);
+    if lines.is_empty() {
+        // An empty file can only change by getting a byte order mark.
+        let new = bom.apply(old);
+        if !new.is_empty() {
+            writeln!(out, "--- {name}")?;
+            writeln!(out, "+++ {name}")?;
+            writeln!(out, "@@ -0,0 +1,1 @@")?;
+            write_rows(out, b'+', &new, target)?;
+        }
+        return Ok(());
+    }
+    let mut changed: Vec<bool> = lines.iter().map(|line| line.is_changed(old, target)).collect();
+    let first = bom.apply(&old[lines[0].text.clone()]);
+    changed[0] |= *first != old[lines[0].text.clone()];
+    // The old side of a change is shown in rows ending in LF, so a line after a changed one
+    // ending in a lone CR shares its row and has to be shown as changed too.
+    for i in 1..lines.len() {
+        changed[i] |= changed[i - 1] && &old[lines[i - 1].ending.clone()] == b"\r";
+    }
+
+    let mut hunks = hunks(&changed).into_iter().peekable();
+    if hunks.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(out, "--- {name}")?;
+    writeln!(out, "+++ {name}")?;
+    for hunk in hunks {
+        let old_start = 1 + old[..lines[hunk.start].text.start].iter().filter(|&&b| b == b'\n').count();
+        // The old rows are counted as they are written, so the body comes before its header.
+        let mut body = Vec::new();
+        let mut old_rows = 0;
+        let mut i = hunk.start;
+        while i < hunk.end {
+            if !changed[i] {
+                old_rows += write_rows(&mut body, b' ', &old[lines[i].text.start..lines[i].ending.end], target)?;
+                i += 1;
+                continue;
+            }
+            let run = i..(i..hunk.end).find(|&j| !changed[j]).unwrap_or(hunk.end);
+            old_rows += write_rows(&mut body, b'-', &old[lines[run.start].text.start..lines[run.end - 1].ending.end], b"\n")?;
+            let mut new = Vec::new();
+            for (j, line) in lines[run.clone()].iter().enumerate() {
+                if run.start + j == 0 {
+                    new.extend_from_slice(&first);
+                } else {
+                    new.extend_from_slice(&old[line.text.clone()]);
+                }
+                if !line.ending.is_empty() {
+                    new.extend_from_slice(target);
+                }
+            }
+            write_rows(&mut body, b'+', &new, target)?;
+            i = run.end;
+        }
+        writeln!(out, "@@ -{},{} +{},{} @@", old_start, old_rows, hunk.start + 1, hunk.len())?;
+        out.write_all(&body)?;
+    }
+    Ok(())
+}
+
+/// One line of the old text: its content, then its line break, if any.
+struct Line {
+    text: Range<usize>,
+    ending: Range<usize>,
+}
+
+impl Line {
+    fn split(old: &[u8]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while start < old.len() {
+            let Some(found) = old[start..].iter().position(|&b| b == b'\n' || b == b'\r').map(|found| found + start) else {
+                lines.push(Line { text: start..old.len(), ending: old.len()..old.len() });
+                break;
+            };
+            let len = if old[found..].starts_with(b"\r\n") { 2 } else { 1 };
+            lines.push(Line { text: start..found, ending: found..found + len });
+            start = found + len;
+        }
+        lines
+    }
+
+    fn is_changed(&self, old: &[u8], target: &[u8]) -> bool {
+        !self.ending.is_empty() && &old[self.ending.clone()] != target
+    }
+}
+
+/// Groups the changed lines with their context into hunks, merging hunks whose context overlaps.
+fn hunks(changed: &[bool]) -> Vec<Range<usize>> {
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for (i, _) in changed.iter().enumerate().filter(|(_, &changed)| changed) {
+        let hunk = i.saturating_sub(CONTEXT)..(i + 1 + CONTEXT).min(changed.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= hunk.start => last.end = hunk.end,
+            _ => hunks.push(hunk),
+        }
+    }
+    hunks
+}
+
+/// Writes `text` as rows starting with `sign`, one for each line ending in `ending`, and
+/// returns how many it wrote.
+fn write_rows<W: Write + ?Sized>(out: &mut W, sign: u8, text: &[u8], ending: &[u8]) -> io::Result<usize> {
+    let mut rows = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let len = rest.windows(ending.len()).position(|window| window == ending).map_or(rest.len(), |at| at + ending.len());
+        let (row, tail) = rest.split_at(len);
+        out.write_all(&[sign])?;
+        write_visible(out, row.strip_suffix(b"\n").unwrap_or(row))?;
+        out.write_all(b"\n")?;
+        rows += 1;
+        rest = tail;
+    }
+    if !text.ends_with(ending) {
+        out.write_all(b"\\ No newline at end of file\n")?;
+    }
+    Ok(rows)
+}
+
+/// Writes `text` with every CR shown as `^M`.
+fn write_visible<W: Write + ?Sized>(out: &mut W, text: &[u8]) -> io::Result<()> {
+    for (i, part) in text.split(|&b| b == b'\r').enumerate() {
+        if i > 0 {
+            out.write_all(b"^M")?;
+        }
+        out.write_all(part)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use newline_normalizer::LineEnding;
+
+    fn diff(old: &str, ending: LineEnding) -> String {
+        diff_with_bom(old, ending, BomPolicy::Keep)
+    }
+
+    fn diff_with_bom(old: &str, ending: LineEnding, bom: BomPolicy) -> String {
+        let mut out = Vec::new();
+        write_diff(&mut out, "file.txt", old.as_bytes(), ending.as_bytes(), bom).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn changed_lines_get_context() {
+        let old = "1\n2\n3\n4\n5\r\n6\n7\n8\n9\n10\n11\n12\n13\r\n";
+        assert_eq!(
+            diff(old, LineEnding::Lf),
+            "--- file.txt\n+++ file.txt\n\
+             @@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5^M\n+5\n 6\n 7\n 8\n\
+             @@ -10,4 +10,4 @@\n 10\n 11\n 12\n-13^M\n+13\n"
+        );
+        let merged = diff(&old.replace("13\r\n", "13\n").replace("9\n", "9\r\n"), LineEnding::Lf);
+        assert!(merged.contains("@@ -2,11 +2,11 @@\n"), "{merged}");
+        assert_eq!(diff("a\nb\n", LineEnding::Lf), "");
+    }
+
+    #[test]
+    fn lone_carriage_returns_share_a_row() {
+        assert_eq!(
+            diff("a\rb\r\nc", LineEnding::CrLf),
+            "--- file.txt\n+++ file.txt\n@@ -1,2 +1,3 @@\n-a^Mb^M\n+a^M\n+b^M\n c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            diff("x\r", LineEnding::Lf),
+            "--- file.txt\n+++ file.txt\n@@ -1,1 +1,1 @@\n-x^M\n\\ No newline at end of file\n+x\n"
+        );
+    }
+
+    #[test]
+    fn byte_order_marks_change_the_first_line() {
+        assert_eq!(
+            diff_with_bom("\u{feff}a\nb\n", LineEnding::Lf, BomPolicy::Strip),
+            "--- file.txt\n+++ file.txt\n@@ -1,2 +1,2 @@\n-\u{feff}a\n+a\n b\n"
+        );
+        assert_eq!(diff_with_bom("\u{feff}a\n", LineEnding::Lf, BomPolicy::Add), "");
+        assert_eq!(
+            diff_with_bom("", LineEnding::Lf, BomPolicy::Add),
+            "--- file.txt\n+++ file.txt\n@@ -0,0 +1,1 @@\n+\u{feff}\n\\ No newline at end of file\n"
+        );
+        assert_eq!(diff_with_bom("", LineEnding::Lf, BomPolicy::Strip), "");
+    }
+
+    #[test]
+    fn carriage_return_targets_end_their_rows() {
+        let diff = |old: &str| {
+            let mut out = Vec::new();
+            write_diff(&mut out, "file.txt", old.as_bytes(), b"\r", BomPolicy::Keep).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            diff("a\nb\n"),
+            "--- file.txt\n+++ file.txt\n@@ -1,2 +1,2 @@\n-a\n-b\n+a^M\n+b^M\n"
+        );
+        assert_eq!(
+            diff("a\rb\n1\r2\r3\r4\r5"),
+            "--- file.txt\n+++ file.txt\n@@ -1,5 +1,5 @@\n a^M\n-b\n+b^M\n 1^M\n 2^M\n 3^M\n"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! A git filter driver, for the `filter.<driver>` settings of `git config`.
+//!
+//! `--clean` and `--smudge` convert standard input once per file. `--process` speaks git's
+//! long-running filter protocol instead, so that one process serves a whole checkout:
+//!
+//! ```text
+//! [filter "nlnorm"]
+//!     process = nlnorm git-filter --process
+//!     required
+//! ```
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use clap::Args;
+use newline_normalizer::{filter_stdio, BomPolicy, LineEnding, Machine, Status};
+
+use crate::newline::{copy, Newline};
+use crate::{Ending, EXIT_ERROR};
+
+/// Largest payload of one pkt-line.
+const MAX_DATA: usize = 65516;
+
+#[derive(Debug, Args)]
+pub(crate) struct GitFilter {
+    #[command(flatten)]
+    direction: Direction,
+
+    /// Line ending that smudging checks files out with.
+    #[arg(long, value_enum, default_value = "crlf")]
+    smudge_to: Ending,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct Direction {
+    /// Converts standard input to LF, for `filter.<driver>.clean`.
+    #[arg(long)]
+    clean: bool,
+
+    /// Converts standard input to the `--smudge-to` ending, for `filter.<driver>.smudge`.
+    #[arg(long)]
+    smudge: bool,
+
+    /// Serves git's long-running filter protocol, for `filter.<driver>.process`.
+    #[arg(long)]
+    process: bool,
+}
+
+pub(crate) fn run(filter: &GitFilter) -> ExitCode {
+    let smudge = filter.smudge_to.into();
+    let result = match &filter.direction {
+        Direction { process: true, .. } => serve(&mut io::stdin().lock(), &mut io::stdout().lock(), smudge),
+        Direction { clean: true, .. } => filter_stdio(LineEnding::Lf).map(drop),
+        _ if smudge == Newline::Cr => copy(io::stdin().lock(), &mut io::stdout().lock(), smudge, BomPolicy::Keep).map(drop),
+        _ => filter_stdio(smudge.base()).map(drop),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("nlnorm: {e}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+/// Serves clean and smudge requests until git closes `input`.
+fn serve<R: Read + ?Sized, W: Write + ?Sized>(input: &mut R, output: &mut W, smudge: Newline) -> io::Result<()> {
+    let hello = read_list(input)?.unwrap_or_default();
+    if hello.first().map(String::as_str) != Some("git-filter-client") || !hello.iter().any(|line| line == "version=2") {
+        return Err(invalid("expected a git filter client speaking version 2"));
+    }
+    write_list(output, ["git-filter-server", "version=2"])?;
+    // Git waits for this answer before it offers its capabilities.
+    output.flush()?;
+    let offered = read_list(input)?.unwrap_or_default();
+    let capabilities = ["capability=clean", "capability=smudge"].into_iter().filter(|capability| offered.iter().any(|line| line == capability));
+    write_list(output, capabilities)?;
+    output.flush()?;
+
+    let mut block = vec![0; MAX_DATA];
+    while let Some(request) = read_list(input)? {
+        let ending = match request.iter().find_map(|line| line.strip_prefix("command=")) {
+            Some("clean") => Newline::Lf,
+            Some("smudge") => smudge,
+            _ => return Err(invalid("expected a clean or smudge command")),
+        };
+        write_list(output, ["status=success"])?;
+
+        let mut machine = Machine::new(ending.base());
+        while let Some(data) = read_packet(input)?.ok_or_else(|| invalid("content ended early"))? {
+            let mut data = &data[..];
+            loop {
+                let feed = machine.feed(data, &mut block);
+                if feed.written > 0 {
+                    if ending == Newline::Cr {
+                        block[..feed.written].iter_mut().filter(|byte| **byte == b'\n').for_each(|byte| *byte = b'\r');
+                    }
+                    write_packet(output, &block[..feed.written])?;
+                }
+                data = &data[feed.consumed..];
+                if feed.status == Status::NeedsInput {
+                    break;
+                }
+            }
+        }
+        // Ends the content, then keeps the status sent before it.
+        output.write_all(b"00000000")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Reads one pkt-line: `Some(None)` for a flush packet, `None` at the end of input.
+fn read_packet<R: Read + ?Sized>(input: &mut R) -> io::Result<Option<Option<Vec<u8>>>> {
+    let mut header = [0; 4];
+    let mut filled = 0;
+    while filled < header.len() {
+        match input.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let len = std::str::from_utf8(&header)
+        .ok()
+        .and_then(|header| usize::from_str_radix(header, 16).ok())
+        .ok_or_else(|| invalid("malformed pkt-line length"))?;
+    match len {
+        0 => Ok(Some(None)),
+        1..=4 => Err(invalid("unexpected special pkt-line")),
+        _ => {
+            let mut data = vec![0; len - 4];
+            input.read_exact(&mut data)?;
+            Ok(Some(Some(data)))
+        }
+    }
+}
+
+/// Reads text packets up to a flush packet, or `None` at the end of input.
+fn read_list<R: Read + ?Sized>(input: &mut R) -> io::Result<Option<Vec<String>>> {
+    let mut lines = Vec::new();
+    loop {
+        match read_packet(input)? {
+            Some(Some(data)) => {
+                let line = String::from_utf8(data).map_err(|_| invalid("pkt-line text is not UTF-8"))?;
+                lines.push(line.strip_suffix('\n').unwrap_or(&line).to_string());
+            }
+            Some(None) => return Ok(Some(lines)),
+            None if lines.is_empty() => return Ok(None),
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+fn write_packet<W: Write + ?Sized>(output: &mut W, data: &[u8]) -> io::Result<()> {
+    debug_assert!(!data.is_empty() && data.len() <= MAX_DATA);
+    write!(output, "{:04x}", data.len() + 4)?;
+    output.write_all(data)
+}
+
+/// Writes text packets followed by a flush packet.
+fn write_list<'a, W: Write + ?Sized>(output: &mut W, lines: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+    for line in lines {
+        write_packet(output, format!("{line}\n").as_bytes())?;
+    }
+    output.write_all(b"0000")
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("git filter protocol: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What git would send: the handshake, then one request per `(command, content)`.
+    fn session(requests: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut input = Vec::new();
+        write_list(&mut input, ["git-filter-client", "version=2"]).unwrap();
+        write_list(&mut input, ["capability=clean", "capability=smudge", "capability=delay"]).unwrap();
+        for (command, content) in requests {
+            write_list(&mut input, [&*format!("command={command}"), "pathname=a.txt"]).unwrap();
+            for chunk in content.chunks(MAX_DATA) {
+                write_packet(&mut input, chunk).unwrap();
+            }
+            input.extend_from_slice(b"0000");
+        }
+        input
+    }
+
+    #[test]
+    fn serves_clean_and_smudge_requests() {
+        let big = "line\n".repeat(20_000);
+        let input = session(&[("clean", b"a\r\nb\rc"), ("smudge", b"x\ny"), ("smudge", b""), ("smudge", big.as_bytes())]);
+        let mut output = Vec::new();
+        serve(&mut &input[..], &mut output, Newline::CrLf).unwrap();
+
+        let mut output = &output[..];
+        assert_eq!(read_list(&mut output).unwrap().unwrap(), ["git-filter-server", "version=2"]);
+        assert_eq!(read_list(&mut output).unwrap().unwrap(), ["capability=clean", "capability=smudge"]);
+        let mut contents = Vec::new();
+        while let Some(status) = read_list(&mut output).unwrap() {
+            assert_eq!(status, ["status=success"]);
+            let mut content = Vec::new();
+            while let Some(data) = read_packet(&mut output).unwrap().unwrap() {
+                assert!(data.len() <= MAX_DATA);
+                content.extend_from_slice(&data);
+            }
+            assert_eq!(read_list(&mut output).unwrap().unwrap(), Vec::<String>::new());
+            contents.push(content);
+        }
+        assert_eq!(contents, [&b"a\nb\nc"[..], b"x\r\ny", b"", big.replace('\n', "\r\n").as_bytes()]);
+    }
+
+    #[test]
+    fn rejects_other_clients() {
+        let mut input = Vec::new();
+        write_list(&mut input, ["git-remote-client", "version=2"]).unwrap();
+        assert!(serve(&mut &input[..], &mut Vec::new(), Newline::CrLf).is_err());
+        assert!(serve(&mut &b"00"[..], &mut Vec::new(), Newline::CrLf).is_err());
+    }
+}
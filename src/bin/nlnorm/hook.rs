@@ -0,0 +1,167 @@
+//! `nlnorm hook`: checking or fixing the files of a commit, from a git pre-commit hook or the
+//! pre-commit framework.
+//!
+//! As a plain hook, `.git/hooks/pre-commit` runs `nlnorm hook --staged`. The pre-commit
+//! framework passes the files itself, see `.pre-commit-hooks.yaml`.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use clap::Args;
+
+use crate::newline::Newline;
+use crate::{collect, process_all, Ending, Found, Options, Target, EXIT_CHANGES, EXIT_ERROR};
+
+#[derive(Debug, Args)]
+pub(crate) struct Hook {
+    /// Takes the files added, copied, modified or renamed in the index instead of paths.
+    #[arg(long, conflicts_with = "files")]
+    staged: bool,
+
+    /// Rewrites the files that need it. With `--staged`, also stages them again and lets the
+    /// commit go ahead; otherwise exits with 1 as the pre-commit framework expects.
+    #[arg(long, conflicts_with_all = ["check", "dry_run", "list"])]
+    fix: bool,
+
+    /// Line ending to convert to. Without it, each file gets the `end_of_line` that
+    /// `.editorconfig` sets for it, or LF.
+    #[arg(long, value_enum)]
+    to: Option<Ending>,
+
+    #[command(flatten)]
+    options: Options,
+}
+
+impl Hook {
+    /// What a config file can fill in.
+    pub(crate) fn configurable(&mut self) -> (Option<&mut Option<Ending>>, &mut Options) {
+        (Some(&mut self.to), &mut self.options)
+    }
+}
+
+/// Checks the files, or fixes them with `--fix`, and exits with 1 if the commit should stop.
+///
+/// Files are read from the working tree. With `--staged --fix`, a file that also has changes
+/// that are not staged is left alone and reported, as staging it again would commit those too.
+pub(crate) fn run(hook: Hook) -> ExitCode {
+    let Hook { staged, fix, to, mut options } = hook;
+    let target = match to {
+        Some(ending) => Target::Fixed(ending.into()),
+        None => Target::EditorConfig { default: Newline::Lf },
+    };
+    options.check = !fix && !options.dry_run;
+    let mut unstaged = HashSet::new();
+    if staged {
+        let listed = git_files(Path::new("."), &["diff", "--cached", "--diff-filter=ACMR"]).and_then(|files| {
+            if fix {
+                unstaged = git_files(Path::new("."), &["diff"])?.into_iter().collect();
+            }
+            Ok(files)
+        });
+        match listed {
+            Ok(files) => options.files = files,
+            Err(e) => {
+                eprintln!("nlnorm: cannot list the staged files: {e}");
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+    // Nothing to commit is nothing to check; unlike the other commands, no standard input.
+    if options.files.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    let walker = match options.walker() {
+        Ok(walker) => walker,
+        Err(e) => {
+            eprintln!("nlnorm: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let mut reporter = options.reporter(true);
+    let (files, partial): (Vec<Found>, Vec<Found>) = collect(target, &options, &walker, &options.files, None)
+        .into_iter()
+        .partition(|file| file.as_ref().map_or(true, |job| !unstaged.contains(&job.path)));
+    for job in partial.iter().flatten() {
+        reporter.error(Some(&job.path), "has unstaged changes, stage or stash them first");
+    }
+    process_all(&files, &options, &mut reporter);
+    // The files changed had nothing unstaged, so staging them again adds just the conversion.
+    if staged && fix && reporter.changes() {
+        let fixed = files.iter().flatten().filter(|job| job.skip.is_none()).map(|job| job.path.as_os_str());
+        if let Err(e) = git(Path::new("."), ["add", "--"].into_iter().map(OsStr::new).chain(fixed)) {
+            reporter.error(None, &format!("cannot stage the fixed files: {e}"));
+        }
+    }
+    reporter.summary();
+
+    if reporter.failed() {
+        ExitCode::from(EXIT_ERROR)
+    } else if reporter.changes() && !(staged && fix) {
+        if staged && !options.dry_run {
+            eprintln!("nlnorm: `nlnorm hook --staged --fix` converts and stages these files");
+        }
+        ExitCode::from(EXIT_CHANGES)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The files that `git <args> --name-only` lists when run in `dir`, as paths from `dir`.
+fn git_files(dir: &Path, args: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let top = git(dir, ["rev-parse", "--show-cdup"])?;
+    let top = PathBuf::from(String::from_utf8_lossy(&top).trim_end_matches('\n'));
+    let listed = git(dir, args.iter().chain(&["--name-only", "-z"]))?;
+    Ok(listed.split(|&byte| byte == 0).filter(|name| !name.is_empty()).map(|name| top.join(path_from_bytes(name))).collect())
+}
+
+/// Runs git in `dir` and returns its standard output.
+fn git<S: AsRef<OsStr>>(dir: &Path, args: impl IntoIterator<Item = S>) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").current_dir(dir).args(args).output()?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr);
+        Err(io::Error::new(io::ErrorKind::Other, message.trim().to_owned()))
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(name))
+}
+
+/// Git stores names as UTF-8 where paths are not bytes.
+#[cfg(not(unix))]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lists_staged_and_unstaged_files() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-hook-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        git(&dir, ["init", "-q"]).unwrap();
+        for (name, content) in [("a.txt", "a\r\n"), ("sub/b.txt", "b\r\n"), ("sub/new.txt", "c\r\n")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        git(&dir, ["add", "a.txt", "sub/b.txt"]).unwrap();
+        fs::write(dir.join("sub/b.txt"), "b\r\nmore\r\n").unwrap();
+
+        let staged = ["diff", "--cached", "--diff-filter=ACMR"];
+        assert_eq!(git_files(&dir, &staged).unwrap(), [PathBuf::from("a.txt"), PathBuf::from("sub/b.txt")]);
+        assert_eq!(git_files(&dir.join("sub"), &staged).unwrap(), [PathBuf::from("../a.txt"), PathBuf::from("../sub/b.txt")]);
+        assert_eq!(git_files(&dir, &["diff"]).unwrap(), [PathBuf::from("sub/b.txt")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! `--interactive`: asking before converting each file, as `git add -p` asks about each hunk.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::report::{Outcome, Reporter};
+use crate::{process, Found, Job, Mode, Options};
+
+const HELP: &str = "\
+y - convert this file
+n - leave this file alone
+a - convert this file and the rest of its directory
+d - leave this file and the rest of its directory alone
+q - quit; convert no more files
+? - print help
+";
+
+/// What to do with the file asked about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Yes,
+    No,
+    All,
+    Done,
+    Quit,
+}
+
+/// Checks the files one at a time and asks on standard error about each that would change,
+/// reading the answers from `answers`. Only the files agreed to are converted.
+///
+/// Files in a directory answered with `a` or `d`, at any depth, are not asked about again.
+/// Running out of answers quits, leaving the other files alone.
+pub(crate) fn process_all(files: &[Found], options: &Options, reporter: &mut Reporter, answers: &mut dyn BufRead) {
+    // Directories answered for, most recent last: whether their files are converted.
+    let mut decided: Vec<(PathBuf, bool)> = Vec::new();
+    for file in files {
+        let (path, ending) = match file {
+            Err(e) => {
+                reporter.error(None, e);
+                continue;
+            }
+            Ok(Job { path, skip: Some(skip), .. }) => {
+                reporter.file(path, Ok(skip.clone()));
+                continue;
+            }
+            Ok(Job { path, ending, skip: None }) => (path, *ending),
+        };
+        if path == Path::new("-") {
+            reporter.error(Some(path), "cannot ask about standard input, which gives the answers");
+            continue;
+        }
+        let report = match process(path, ending, Mode::DryRun, options, &mut io::sink()) {
+            Ok(Outcome::Normalized(report)) if report.changed => report,
+            other => {
+                reporter.file(path, other);
+                continue;
+            }
+        };
+        let directory = match path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        let convert = match decided.iter().rev().find(|(dir, _)| within(path, dir)) {
+            Some(&(_, convert)) => convert,
+            None => {
+                let question = format!("Convert {} to {} ({} line endings)?", path.display(), ending.name(), report.stats.converted);
+                let answer = ask(&question, answers);
+                match answer {
+                    Answer::Yes => true,
+                    Answer::No => false,
+                    Answer::All | Answer::Done => {
+                        let convert = answer == Answer::All;
+                        decided.push((directory.to_path_buf(), convert));
+                        convert
+                    }
+                    Answer::Quit => break,
+                }
+            }
+        };
+        if convert {
+            reporter.file(path, process(path, ending, Mode::Write, options, &mut io::stdout().lock()));
+        } else {
+            reporter.file(path, Ok(Outcome::Declined));
+        }
+    }
+}
+
+/// Whether `path` is in `directory` at any depth. A relative path with no directory is in
+/// `.`, as are the other relative paths not leaving it; an absolute path never is.
+fn within(path: &Path, directory: &Path) -> bool {
+    if directory == Path::new(".") {
+        path.is_relative() && !path.starts_with("..")
+    } else {
+        path.starts_with(directory)
+    }
+}
+
+/// Asks `question` until it gets an answer it knows, printing the help for the others.
+fn ask(question: &str, answers: &mut dyn BufRead) -> Answer {
+    loop {
+        eprint!("{question} [y,n,a,d,q,?] ");
+        let _ = io::stderr().flush();
+        let mut line = String::new();
+        match answers.read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                eprintln!();
+                return Answer::Quit;
+            }
+            Ok(_) => {}
+        }
+        match line.trim() {
+            "y" => return Answer::Yes,
+            "n" => return Answer::No,
+            "a" => return Answer::All,
+            "d" => return Answer::Done,
+            "q" => return Answer::Quit,
+            _ => eprint!("{HELP}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newline::Newline;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn converts_the_files_agreed_to() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-interactive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let names = ["a.txt", "b.txt", "sub/c.txt", "sub/d.txt", "e.txt", "f.txt"];
+        for name in names {
+            fs::write(dir.join(name), if name == "b.txt" { "b\n" } else { "x\r\n" }).unwrap();
+        }
+        let files: Vec<Found> = names.iter().map(|name| Ok(Job { path: dir.join(name), ending: Newline::Lf, skip: None })).collect();
+
+        let (_, options) = crate::Cli::try_parse_from(["nlnorm", "unix", "-i", "-q", dir.to_str().unwrap()]).unwrap().command.split();
+        let mut reporter = options.reporter(false);
+        // Help for an unknown answer; nothing asked about the file already normalized or the
+        // rest of the directory left alone.
+        process_all(&files, &options, &mut reporter, &mut &b"x\ny\nd\nn\nq\n"[..]);
+        let content = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!([content("a.txt"), content("b.txt"), content("sub/c.txt"), content("sub/d.txt")], ["x\n", "b\n", "x\r\n", "x\r\n"]);
+        assert_eq!([content("e.txt"), content("f.txt")], ["x\r\n", "x\r\n"]);
+        assert!(reporter.changes() && !reporter.failed());
+
+        // All of `sub`, then all of the directory.
+        process_all(&files, &options, &mut reporter, &mut &b"a\na\n"[..]);
+        assert!(names.iter().all(|name| !content(name).contains('\r')));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_top_level_file_answers_only_for_the_current_directory() {
+        // A file with no directory, as named on the command line, next to one elsewhere.
+        let top = PathBuf::from(format!("nlnorm-interactive-top-{}.txt", std::process::id()));
+        let dir = std::env::temp_dir().join(format!("nlnorm-interactive-nested-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let nested = dir.join("c.txt");
+        for path in [&top, &nested] {
+            fs::write(path, "x\r\n").unwrap();
+        }
+        let files: Vec<Found> = [&top, &nested].iter().map(|path| Ok(Job { path: path.to_path_buf(), ending: Newline::Lf, skip: None })).collect();
+
+        let (_, options) = crate::Cli::try_parse_from(["nlnorm", "unix", "-i", "-q", "."]).unwrap().command.split();
+        let mut reporter = options.reporter(false);
+        // All of the current directory, which does not take in the nested file: it is still asked about.
+        process_all(&files, &options, &mut reporter, &mut &b"a\nn\n"[..]);
+        assert_eq!([fs::read_to_string(&top).unwrap(), fs::read_to_string(&nested).unwrap()], ["x\n", "x\r\n"]);
+        fs::remove_file(&top).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn relative_paths_are_within_the_current_directory() {
+        assert!(within(Path::new("a.txt"), Path::new(".")));
+        assert!(within(Path::new("sub/a.txt"), Path::new(".")));
+        assert!(!within(Path::new("../a.txt"), Path::new(".")));
+        assert!(!within(Path::new("/tmp/a.txt"), Path::new(".")));
+        assert!(within(Path::new("sub/deeper/a.txt"), Path::new("sub")));
+        assert!(!within(Path::new("subway/a.txt"), Path::new("sub")));
+    }
+}
@@ -0,0 +1,106 @@
+//! Processing files on several threads while reporting in order.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Runs `task` on every item on up to `jobs` threads, calling `report` in item order.
+///
+/// - `task` writes its output to the writer it is given. With one job that is standard
+///   output itself; otherwise the output is buffered and handed to `report`, which writes
+///   it out, so that the output of different items never interleaves.
+/// - `report` runs on the calling thread, as soon as all earlier items are reported.
+pub(crate) fn for_each_ordered<T, R, F, G>(items: &[T], jobs: usize, task: F, mut report: G)
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T, &mut dyn Write) -> R + Sync,
+    G: FnMut(&T, R, &[u8]),
+{
+    if jobs <= 1 || items.len() <= 1 {
+        for item in items {
+            let result = task(item, &mut io::stdout().lock());
+            report(item, result, &[]);
+        }
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let (next, task, sender) = (&next, &task, sender.clone());
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let mut output = Vec::new();
+                let result = task(item, &mut output);
+                if sender.send((i, result, output)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut done = BTreeMap::new();
+        let mut reported = 0;
+        for (i, result, output) in receiver {
+            done.insert(i, (result, output));
+            while let Some((result, output)) = done.remove(&reported) {
+                report(&items[reported], result, &output);
+                reported += 1;
+            }
+        }
+    });
+}
+
+/// Number of jobs to run when `--jobs` is not given: one per CPU.
+pub(crate) fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_are_reported_in_order() {
+        let items: Vec<u64> = (0..40).collect();
+        for jobs in [1, 3, 8] {
+            let mut reported = Vec::new();
+            for_each_ordered(
+                &items,
+                jobs,
+                |&item, _| {
+                    // Later items finish first.
+                    thread::sleep(Duration::from_micros((40 - item) * 50));
+                    item * 2
+                },
+                |&item, result, _| reported.push((item, result)),
+            );
+            assert_eq!(reported, items.iter().map(|&item| (item, item * 2)).collect::<Vec<_>>(), "{jobs}");
+        }
+    }
+
+    #[test]
+    fn parallel_output_is_buffered_per_item() {
+        let items = ["a", "b", "c", "d"];
+        let mut outputs = Vec::new();
+        for_each_ordered(
+            &items,
+            4,
+            |item, out| {
+                for _ in 0..100 {
+                    out.write_all(item.as_bytes()).unwrap();
+                }
+            },
+            |_, (), output| outputs.push(String::from_utf8(output.to_vec()).unwrap()),
+        );
+        assert_eq!(outputs, items.map(|item| item.repeat(100)));
+    }
+}
@@ -0,0 +1,118 @@
+//! Symbolic and hard links: which ones to follow, and which files turn up under several names.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::report::Outcome;
+
+/// Which symbolic links lead to files to convert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Follow {
+    /// Only the paths named: links found walking directories are skipped.
+    Named,
+    /// Every link whose target stays below the directory walked.
+    Inside,
+    /// None at all, not even the paths named.
+    Never,
+}
+
+/// Looks at the files found, in order, for the ones to skip because of links.
+#[derive(Debug)]
+pub(crate) struct Links {
+    follow: Follow,
+    /// The first path each file was found under.
+    seen: HashMap<Identity, PathBuf>,
+    /// The last root walked, and where it leads.
+    root: Option<(PathBuf, PathBuf)>,
+}
+
+/// What tells files apart, whatever their name.
+#[cfg(unix)]
+type Identity = (u64, u64);
+/// What tells files apart, whatever their name. Hard links are not told apart here.
+#[cfg(not(unix))]
+type Identity = PathBuf;
+
+impl Links {
+    pub(crate) fn new(follow: Follow) -> Self {
+        Links { follow, seen: HashMap::new(), root: None }
+    }
+
+    /// Whether `root`, a path named, is a link not to follow.
+    pub(crate) fn skip_root(&self, root: &Path) -> Option<Outcome> {
+        (self.follow == Follow::Never && is_symlink(root)).then_some(Outcome::Symlink)
+    }
+
+    /// Why `path`, found walking `root`, is not to be converted, if it is not: it leads
+    /// outside `root` with [`Follow::Inside`], or was found before under another name.
+    ///
+    /// Links found walking are only followed with [`Follow::Inside`]; otherwise the walk
+    /// never returns them.
+    pub(crate) fn skip(&mut self, root: &Path, path: &Path) -> Option<Outcome> {
+        if self.follow == Follow::Inside && path != root {
+            if self.root.as_ref().map_or(true, |(walked, _)| walked != root) {
+                self.root = Some((root.to_path_buf(), fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf())));
+            }
+            let target = self.root.as_ref().map(|(_, target)| target).expect("set above");
+            if fs::canonicalize(path).map_or(false, |path| !path.starts_with(target)) {
+                return Some(Outcome::Outside);
+            }
+        }
+        // A file that cannot be looked at fails when it is processed.
+        let identity = identity(path)?;
+        match self.seen.get(&identity) {
+            Some(first) => Some(Outcome::SameFile(first.clone())),
+            None => {
+                self.seen.insert(identity, path.to_path_buf());
+                None
+            }
+        }
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).map_or(false, |metadata| metadata.file_type().is_symlink())
+}
+
+#[cfg(unix)]
+fn identity(path: &Path) -> Option<Identity> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(path: &Path) -> Option<Identity> {
+    fs::canonicalize(path).ok()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn links_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (tree, outside) = (dir.join("tree"), dir.join("outside.txt"));
+        fs::create_dir_all(&tree).unwrap();
+        fs::write(tree.join("a.txt"), "a\r\n").unwrap();
+        fs::write(&outside, "b\r\n").unwrap();
+        fs::hard_link(tree.join("a.txt"), tree.join("hard.txt")).unwrap();
+        symlink("a.txt", tree.join("soft.txt")).unwrap();
+        symlink("../outside.txt", tree.join("out.txt")).unwrap();
+
+        let mut links = Links::new(Follow::Inside);
+        assert_eq!(links.skip(&tree, &tree.join("a.txt")), None);
+        assert_eq!(links.skip(&tree, &tree.join("hard.txt")), Some(Outcome::SameFile(tree.join("a.txt"))));
+        assert_eq!(links.skip(&tree, &tree.join("soft.txt")), Some(Outcome::SameFile(tree.join("a.txt"))));
+        assert_eq!(links.skip(&tree, &tree.join("out.txt")), Some(Outcome::Outside));
+        assert_eq!(links.skip(&tree.join("out.txt"), &tree.join("out.txt")), None);
+
+        assert_eq!(Links::new(Follow::Never).skip_root(&tree.join("soft.txt")), Some(Outcome::Symlink));
+        assert_eq!(Links::new(Follow::Named).skip_root(&tree.join("soft.txt")), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
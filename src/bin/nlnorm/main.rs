@@ -0,0 +1,957 @@
+//! `nlnorm`: normalizes the newlines of files, or of standard input, from the command line.
+
+// The `cli` feature needs a newer toolchain than the library anyway, see `Cargo.toml`.
+#![allow(clippy::incompatible_msrv)]
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use newline_normalizer::{filter_stdio, looks_binary, BomPolicy, EditorConfig, FileReport};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::diff::write_diff;
+use crate::git::GitFilter;
+use crate::hook::Hook;
+use crate::jobs::{default_jobs, for_each_ordered};
+use crate::links::{Follow, Links};
+use crate::map::{lookup, Mapping};
+use crate::metadata::restore;
+use crate::newline::{copy, normalize_file, Newline};
+use crate::report::{Format, Outcome, Reporter, Verbosity};
+use crate::sarif::{Lines, Scanner};
+use crate::walk::Walker;
+use crate::watch::watch;
+
+mod config;
+mod diff;
+mod git;
+mod hook;
+mod interactive;
+mod jobs;
+mod links;
+mod map;
+mod metadata;
+mod newline;
+mod report;
+mod sarif;
+mod walk;
+mod watch;
+
+#[derive(Debug, Parser)]
+#[command(name = "nlnorm", version, about = "Normalizes newlines to LF or CRLF")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Converts newlines to LF.
+    Unix(Options),
+    /// Converts newlines to CRLF.
+    Dos(Options),
+    /// Converts newlines to the `--to` line ending, such as the native one of the platform.
+    Convert {
+        /// Line ending to convert to. Required unless `nlnorm.toml` sets `to`.
+        #[arg(long, value_enum)]
+        to: Option<Ending>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Converts each file to the `end_of_line` that `.editorconfig` files set for it.
+    Fix {
+        /// Line ending for the files that `.editorconfig` sets none for, and for standard input.
+        /// Defaults to the `to` of `nlnorm.toml`, or LF.
+        #[arg(long, value_enum)]
+        default: Option<Ending>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Converts files once, then again whenever they are created or modified, until interrupted.
+    /// Watches the current directory if no paths are given.
+    Watch {
+        /// Line ending to convert to. Defaults to the `to` of `nlnorm.toml`, or LF.
+        #[arg(long, value_enum)]
+        to: Option<Ending>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Works as a git filter driver: converts to LF on clean and, optionally, to CRLF on smudge.
+    GitFilter(GitFilter),
+    /// Checks the files of a commit, or with `--fix` converts them, for pre-commit hooks.
+    /// Exits with 1 if the commit should stop.
+    Hook(Hook),
+}
+
+impl Command {
+    fn split(self) -> (Target, Options) {
+        match self {
+            Command::Unix(options) => (Target::Fixed(Newline::Lf), options),
+            Command::Dos(options) => (Target::Fixed(Newline::CrLf), options),
+            Command::Convert { to, options } => (Target::Fixed(to.expect("main checks for --to").into()), options),
+            Command::Fix { default, options } => (Target::EditorConfig { default: default.unwrap_or(Ending::Lf).into() }, options),
+            Command::Watch { .. } | Command::GitFilter(_) | Command::Hook(_) => unreachable!("main runs these commands itself"),
+        }
+    }
+
+    /// Applies the config file found for the current directory, unless `--no-config` is given.
+    fn configure(&mut self) -> Result<(), String> {
+        let (to, options) = match self {
+            Command::Unix(options) | Command::Dos(options) => (None, options),
+            Command::Convert { to, options } | Command::Watch { to, options } | Command::Fix { default: to, options } => (Some(to), options),
+            Command::Hook(hook) => hook.configurable(),
+            Command::GitFilter(_) => return Ok(()),
+        };
+        if options.no_config {
+            return Ok(());
+        }
+        let cwd = std::env::current_dir().map_err(|e| format!("cannot find the current directory: {e}"))?;
+        match Config::discover(&cwd)? {
+            Some(config) => config.apply(to, options),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A line ending named on the command line or in `nlnorm.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Ending {
+    /// The one of the platform nlnorm runs on: CRLF on Windows, LF elsewhere.
+    Native,
+    Lf,
+    Crlf,
+    /// A CR alone, as classic Mac OS used.
+    Cr,
+}
+
+impl From<Ending> for Newline {
+    fn from(ending: Ending) -> Newline {
+        match ending {
+            Ending::Native => Newline::native(),
+            Ending::Lf => Newline::Lf,
+            Ending::Crlf => Newline::CrLf,
+            Ending::Cr => Newline::Cr,
+        }
+    }
+}
+
+/// Which line ending each file gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    /// The same one for every file.
+    Fixed(Newline),
+    /// The one `.editorconfig` sets, or `default` where it sets none.
+    EditorConfig { default: Newline },
+}
+
+impl Target {
+    /// The line ending for input that is not a file, such as standard input.
+    fn fallback(self) -> Newline {
+        match self {
+            Target::Fixed(ending) | Target::EditorConfig { default: ending } => ending,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct Options {
+    /// Files or directories to rewrite in place. Without any, takes the `paths` of
+    /// `nlnorm.toml`, or filters standard input to standard output, as `-` does.
+    files: Vec<PathBuf>,
+
+    /// Only visits the files in directories that match, relative to the directory given;
+    /// a leading `!` excludes matches instead. Can be repeated.
+    #[arg(long = "glob", short, value_name = "GLOB")]
+    globs: Vec<String>,
+
+    /// Picks the line ending by file name pattern, over the one the command gives: `GLOB=ENDING`
+    /// entries separated by commas, as in `--map '*.bat=crlf,*.sh=lf'`. Globs without a `/`
+    /// match file names, others the path below the directory given; later entries win.
+    #[arg(long = "map", value_name = "GLOB=ENDING,...")]
+    maps: Vec<Mapping>,
+
+    /// Also visits files that `.gitignore` and similar files exclude.
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Follows symbolic links found in directories, skipping the ones that lead outside the
+    /// directory given. Links named on the command line are always followed.
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Follows no symbolic link at all, not even the paths named.
+    #[arg(long, conflicts_with = "follow_symlinks")]
+    no_follow: bool,
+
+    /// Changes nothing; lists the files that would change and exits with 1 if there are any.
+    #[arg(long)]
+    check: bool,
+
+    /// Changes nothing; lists the files that would change.
+    #[arg(long, conflicts_with = "check")]
+    dry_run: bool,
+
+    /// Works like `--check`, printing nothing but the paths of the files that would change,
+    /// and errors, for scripts: `nlnorm unix --list -0 . | xargs -0 $EDITOR`.
+    #[arg(long, conflicts_with_all = ["dry_run", "format", "quiet", "verbose"])]
+    list: bool,
+
+    /// With `--list`, ends each path with a NUL byte instead of a newline.
+    #[arg(long = "null", short = '0', requires = "list")]
+    null: bool,
+
+    /// With `--dry-run`, prints a unified diff of the changes instead, with CRs shown as `^M`.
+    #[arg(long, requires = "dry_run")]
+    diff: bool,
+
+    /// Rewrites files in place, which is the default. With a suffix, as in `--in-place=.bak`,
+    /// first keeps a copy of every file that changes, named after it plus the suffix.
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with_all = ["check", "dry_run", "list"]
+    )]
+    in_place: Option<String>,
+
+    /// Removes the UTF-8 byte order mark from the start of each file that has one.
+    #[arg(long, conflicts_with = "add_bom")]
+    strip_bom: bool,
+
+    /// Adds a UTF-8 byte order mark to the start of each file that has no byte order mark.
+    #[arg(long)]
+    add_bom: bool,
+
+    /// Gives rewritten files back their modification time, so that build tools relying on it
+    /// do not rebuild. Permissions, and the owner where permitted, are always kept.
+    #[arg(long, conflicts_with_all = ["check", "dry_run", "list"])]
+    keep_mtime: bool,
+
+    /// Writes the normalized files to standard output one after another, changing nothing.
+    /// Suits pager preprocessors such as `LESSOPEN='|nlnorm unix --stdout %s'`.
+    #[arg(long, short = 'c', conflicts_with_all = ["check", "dry_run", "list", "in_place"])]
+    stdout: bool,
+
+    /// Asks before converting each file that would change, reading the answers from standard
+    /// input: `y` or `n`, `a` or `d` for the rest of its directory too, `q` to stop.
+    #[arg(long, short, requires = "files", conflicts_with_all = ["check", "dry_run", "list", "stdout", "format"])]
+    interactive: bool,
+
+    /// Files to process at once. Defaults to the number of CPUs; output stays in order.
+    #[arg(long, short, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Largest file to hold in memory, as in `512K`, `64M` or `2G`. Larger files are streamed
+    /// instead: through a temporary file when converting to CR or rewriting a hard-linked file,
+    /// one at a time with `--stdout` and `--diff`, and with no diff shown.
+    #[arg(long, value_name = "SIZE", default_value = "64M", value_parser = parse_size)]
+    max_in_memory: u64,
+
+    /// Also converts files that look binary, which are skipped otherwise: files with a NUL
+    /// byte near the start, and images, archives and other known binary formats.
+    #[arg(long)]
+    force: bool,
+
+    /// How to print the results.
+    #[arg(long, value_enum, default_value = "text", conflicts_with_all = ["stdout", "diff"])]
+    format: Format,
+
+    /// Ignores `nlnorm.toml` and `[tool.nlnorm]` in `pyproject.toml`.
+    #[arg(long)]
+    no_config: bool,
+
+    /// The BOM policy of `nlnorm.toml`, for when neither BOM option is given.
+    #[arg(skip)]
+    default_bom: BomPolicy,
+
+    /// Prints nothing but errors: no list of files, no summary.
+    #[arg(long, short, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also prints the newlines found in every file, and a summary even with `--stdout`.
+    #[arg(long, short)]
+    verbose: bool,
+}
+
+impl Options {
+    fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (_, true) => Verbosity::Verbose,
+            _ => Verbosity::Normal,
+        }
+    }
+
+    fn bom(&self) -> BomPolicy {
+        match (self.strip_bom, self.add_bom) {
+            (true, _) => BomPolicy::Strip,
+            (_, true) => BomPolicy::Add,
+            _ => self.default_bom,
+        }
+    }
+
+    fn walker(&self) -> Result<Walker, String> {
+        Walker::new(&self.globs, !self.no_ignore).map(|walker| walker.follow_links(self.follow_symlinks))
+    }
+
+    fn follow(&self) -> Follow {
+        match (self.follow_symlinks, self.no_follow) {
+            (true, _) => Follow::Inside,
+            (_, true) => Follow::Never,
+            _ => Follow::Named,
+        }
+    }
+
+    /// The reporter for these options; `list_changed` names the files that change in text output.
+    fn reporter(&self, list_changed: bool) -> Reporter {
+        let reporter = Reporter::new(self.format, self.verbosity(), list_changed);
+        match (self.list, self.null) {
+            (true, true) => reporter.porcelain(0),
+            (true, false) => reporter.porcelain(b'\n'),
+            _ => reporter,
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        match (self.check || self.list, self.dry_run, self.diff) {
+            _ if self.stdout => Mode::Stdout,
+            (true, ..) => Mode::Check,
+            (_, true, true) => Mode::Diff,
+            (_, true, false) => Mode::DryRun,
+            _ => Mode::Write,
+        }
+    }
+}
+
+/// What to do with each file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Write,
+    Stdout,
+    Check,
+    DryRun,
+    Diff,
+}
+
+/// Some file would change; only returned by `--check`.
+const EXIT_CHANGES: u8 = 1;
+/// Some file could not be read or written.
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let mut command = Cli::parse().command;
+    if let Err(e) = command.configure() {
+        eprintln!("nlnorm: {e}");
+        return ExitCode::from(EXIT_ERROR);
+    }
+    match command {
+        Command::Convert { to: None, .. } => {
+            eprintln!("nlnorm: convert needs --to, or a `to` in nlnorm.toml");
+            ExitCode::from(EXIT_ERROR)
+        }
+        Command::Watch { to, options } => watch(Target::Fixed(to.unwrap_or(Ending::Lf).into()), &options),
+        Command::GitFilter(filter) => git::run(&filter),
+        Command::Hook(hook) => hook::run(hook),
+        command => {
+            let (target, options) = command.split();
+            run(target, &options)
+        }
+    }
+}
+
+fn run(target: Target, options: &Options) -> ExitCode {
+    let mode = options.mode();
+    if options.files.is_empty() && matches!(mode, Mode::Write | Mode::Stdout) {
+        return filter(target.fallback(), options.bom());
+    }
+    let walker = match options.walker() {
+        Ok(walker) => walker,
+        Err(e) => {
+            eprintln!("nlnorm: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let stdin = [PathBuf::from("-")];
+    let roots = if options.files.is_empty() { &stdin[..] } else { &options.files[..] };
+    let files = collect(target, options, &walker, roots, None);
+    let mut reporter = options.reporter(matches!(mode, Mode::Check | Mode::DryRun));
+    process_all(&files, options, &mut reporter);
+    // The files written to standard output are the point of `--stdout`; a summary is asked for.
+    if mode != Mode::Stdout || options.verbose {
+        reporter.summary();
+    }
+
+    if reporter.failed() {
+        ExitCode::from(EXIT_ERROR)
+    } else if reporter.changes() && mode == Mode::Check {
+        ExitCode::from(EXIT_CHANGES)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// A file to process, or why it cannot be processed.
+type Found = Result<Job, String>;
+
+/// A file to process and the line ending it gets.
+#[derive(Debug)]
+struct Job {
+    path: PathBuf,
+    ending: Newline,
+    /// Why the file is skipped after all, found before processing any file: links.
+    skip: Option<Outcome>,
+}
+
+/// Finds the files to process below `roots`, `-` standing for standard input.
+///
+/// With `only`, keeps just the files named in it, as found below the roots, and drops any
+/// errors walking the roots.
+fn collect(target: Target, options: &Options, walker: &Walker, roots: &[PathBuf], only: Option<&HashSet<PathBuf>>) -> Vec<Found> {
+    let mut editorconfig = EditorConfig::new();
+    let mut resolve = |root: &Path, path: PathBuf| {
+        if path == Path::new("-") {
+            return Ok((path, target.fallback()));
+        }
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => Path::new(path.file_name().unwrap_or_default()),
+        };
+        if let Some(ending) = lookup(&options.maps, relative) {
+            return Ok((path, ending));
+        }
+        match target {
+            Target::Fixed(ending) => Ok((path, ending)),
+            Target::EditorConfig { default } => match editorconfig.line_ending(&path) {
+                Ok(ending) => Ok((path, ending.map_or(default, Newline::from))),
+                Err(e) => Err(format!("{}: {e}", path.display())),
+            },
+        }
+    };
+    let mut links = Links::new(options.follow());
+    let mut files = Vec::new();
+    for root in roots {
+        if root == Path::new("-") {
+            files.push(resolve(root, root.clone()).map(|(path, ending)| Job { path, ending, skip: None }));
+            continue;
+        }
+        if let Some(skip) = links.skip_root(root) {
+            files.push(Ok(Job { path: root.clone(), ending: target.fallback(), skip: Some(skip) }));
+            continue;
+        }
+        walker.files(root, &mut |file| match (file, only) {
+            (Ok(path), Some(only)) if !only.contains(&path) => {}
+            (Err(_), Some(_)) => {}
+            (file, _) => files.push(file.and_then(|path| resolve(root, path)).map(|(path, ending)| {
+                let skip = links.skip(root, &path);
+                Job { path, ending, skip }
+            })),
+        });
+    }
+    files
+}
+
+/// Processes `files`, as many at once as `--jobs` says, and reports on each in order.
+fn process_all(files: &[Found], options: &Options, reporter: &mut Reporter) {
+    if options.interactive && options.mode() == Mode::Write {
+        return interactive::process_all(files, options, reporter, &mut io::stdin().lock());
+    }
+    let mut jobs = options.jobs.map_or_else(default_jobs, NonZeroUsize::get);
+    // Several jobs hold the output of each file in memory until its turn comes; one job
+    // writes it out as it goes.
+    let large = |job: &Job| fs::metadata(&job.path).map_or(false, |metadata| metadata.len() > options.max_in_memory);
+    if matches!(options.mode(), Mode::Stdout | Mode::Diff) && files.iter().flatten().any(large) {
+        jobs = 1;
+    }
+    for_each_ordered(
+        files,
+        jobs,
+        |file, out| match file {
+            Ok(Job { skip: Some(skip), .. }) => Ok(skip.clone()),
+            Ok(Job { path, ending, skip: None }) => process(path, *ending, options.mode(), options, out),
+            Err(_) => Ok(Outcome::Normalized(FileReport::default())),
+        },
+        |file, result, output| match file {
+            Ok(Job { path, .. }) => reporter.file(path, result.and_then(|outcome| io::stdout().write_all(output).map(|()| outcome))),
+            Err(e) => reporter.error(None, e),
+        },
+    );
+}
+
+/// Normalizes the file at `path`, or standard input for `-`, as `mode` says.
+///
+/// In the modes that change nothing, the counters tell what would change. Output meant for
+/// standard output goes to `out`. Files that look binary are left alone unless `--force` is
+/// given; standard input is always converted.
+fn process(path: &Path, ending: Newline, mode: Mode, options: &Options, out: &mut dyn Write) -> io::Result<Outcome> {
+    let stdin = path == Path::new("-");
+    if !stdin && !options.force && looks_binary(path)? {
+        return Ok(Outcome::Binary);
+    }
+    let bom = options.bom();
+    // With `--format sarif`, the lines that change are noted as the input is read.
+    let mut lines = (options.format == Format::Sarif).then(|| Lines::new(ending));
+    let report = match mode {
+        Mode::Write | Mode::Stdout if stdin => copy(Scanner::new(io::stdin().lock(), lines.as_mut()), out, ending, bom)?,
+        Mode::Write => {
+            if let Some(lines) = lines.as_mut() {
+                io::copy(&mut Scanner::new(File::open(path)?, Some(lines)), &mut io::sink())?;
+            }
+            let original = fs::metadata(path)?;
+            let report = match options.in_place.as_deref() {
+                Some(suffix) if !suffix.is_empty() => rewrite_with_backup(path, ending, bom, suffix, options.max_in_memory)?,
+                _ => normalize_file(path, ending, bom, options.max_in_memory)?,
+            };
+            if report.changed {
+                restore(path, &original, options.keep_mtime)?;
+            }
+            report
+        }
+        Mode::Stdout => copy(File::open(path)?, out, ending, bom)?,
+        Mode::Check | Mode::DryRun if stdin => copy(Scanner::new(io::stdin().lock(), lines.as_mut()), &mut io::sink(), ending, bom)?,
+        Mode::Check | Mode::DryRun => copy(Scanner::new(File::open(path)?, lines.as_mut()), &mut io::sink(), ending, bom)?,
+        Mode::Diff => {
+            let name = path.display().to_string();
+            let mut input: Box<dyn Read> = if stdin { Box::new(io::stdin().lock()) } else { Box::new(File::open(path)?) };
+            let mut old = Vec::new();
+            input.by_ref().take(options.max_in_memory.saturating_add(1)).read_to_end(&mut old)?;
+            if old.len() as u64 > options.max_in_memory {
+                let report = copy((&old[..]).chain(input), &mut io::sink(), ending, bom)?;
+                if report.changed {
+                    writeln!(out, "Files {name} differ, too large to show a diff for")?;
+                }
+                report
+            } else {
+                write_diff(out, &name, &old, ending.as_bytes(), bom)?;
+                copy(&old[..], &mut io::sink(), ending, bom)?
+            }
+        }
+    };
+    Ok(match lines {
+        Some(lines) => Outcome::Scanned(report, lines),
+        None => Outcome::Normalized(report),
+    })
+}
+
+/// Like `normalize_file`, first copying the file to its name plus `suffix`.
+///
+/// The file is read through once to see whether it changes, and only then copied, under a
+/// temporary name renamed to the backup, so that a file needing no change is not copied and
+/// leaves any backup from an earlier run alone.
+fn rewrite_with_backup(path: &Path, ending: Newline, bom: BomPolicy, suffix: &str, max_in_memory: u64) -> io::Result<FileReport> {
+    let report = copy(File::open(path)?, &mut io::sink(), ending, bom)?;
+    if !report.changed {
+        return Ok(report);
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    let backup = path.with_file_name(&name);
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(&name);
+    temp_name.push(format!(".{}.nlnorm.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+    if let Err(e) = fs::copy(path, &temp).and_then(|_| fs::rename(&temp, &backup)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    normalize_file(path, ending, bom, max_in_memory)
+}
+
+/// Parses a number of bytes, with an optional `K`, `M`, `G` or `T` for powers of 1024.
+fn parse_size(size: &str) -> Result<u64, String> {
+    let digits = size.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = size[digits.len()..].to_ascii_uppercase();
+    let unit = unit.strip_suffix("IB").or_else(|| unit.strip_suffix('B')).unwrap_or(&unit);
+    let shift = match unit {
+        "" => Some(0),
+        "K" => Some(10),
+        "M" => Some(20),
+        "G" => Some(30),
+        "T" => Some(40),
+        _ => None,
+    };
+    shift
+        .zip(digits.parse::<u64>().ok())
+        .and_then(|(shift, n)| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("`{size}` is not a size, such as 512K, 64M or 2G"))
+}
+
+/// Filters standard input to standard output, stopping quietly when the reader goes away.
+fn filter(ending: Newline, bom: BomPolicy) -> ExitCode {
+    let result = match (bom, ending) {
+        (BomPolicy::Keep, Newline::Lf | Newline::CrLf) => filter_stdio(ending.base()).map(drop),
+        _ => copy(io::stdin().lock(), &mut io::stdout().lock(), ending, bom).map(drop),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("nlnorm: {e}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nlnorm-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn parse(args: &[&str]) -> (Target, Options) {
+        Cli::try_parse_from(args).unwrap().command.split()
+    }
+
+    #[test]
+    fn arguments_are_consistent() {
+        Cli::command().debug_assert();
+        let (target, options) = parse(&["nlnorm", "dos", "a.txt", "b.txt"]);
+        assert_eq!(target, Target::Fixed(Newline::CrLf));
+        assert_eq!(options.files, [PathBuf::from("a.txt"), PathBuf::from("b.txt")]);
+        assert!(Cli::try_parse_from(["nlnorm", "mac"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--follow-symlinks", "--no-follow"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "git-filter"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "git-filter", "--clean", "--smudge"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "git-filter", "--smudge", "--smudge-to", "lf"]).is_ok());
+    }
+
+    #[test]
+    fn rewrites_every_file_and_reports_failures() {
+        let dir = temp_dir("run");
+        let (first, second) = (dir.join("first.txt"), dir.join("second.txt"));
+        fs::write(&first, "a\r\nb\r").unwrap();
+        fs::write(&second, "c\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", first.to_str().unwrap(), second.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&first).unwrap(), b"a\nb\n");
+        assert_eq!(fs::read(&second).unwrap(), b"c\n");
+
+        let missing = dir.join("missing.txt");
+        let (ending, options) = parse(&["nlnorm", "dos", missing.to_str().unwrap(), second.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_ERROR));
+        assert_eq!(fs::read(&second).unwrap(), b"c\r\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_changes_nothing() {
+        let dir = temp_dir("check");
+        let (dos, unix) = (dir.join("dos.txt"), dir.join("unix.txt"));
+        fs::write(&dos, "a\r\n").unwrap();
+        fs::write(&unix, "a\n").unwrap();
+        let (dos, unix) = (dos.to_str().unwrap(), unix.to_str().unwrap());
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", unix]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", dos, unix]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+        let (ending, options) = parse(&["nlnorm", "dos", "--check", dos, "missing.txt"]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_ERROR));
+        assert_eq!(fs::read(dos).unwrap(), b"a\r\n");
+        assert_eq!(fs::read(unix).unwrap(), b"a\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn directories_are_walked() {
+        let dir = temp_dir("walk");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "a\r\n").unwrap();
+        fs::write(dir.join("notes.txt"), "b\r\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--glob", "*.rs", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("src/lib.rs")).unwrap(), b"a\n");
+        assert_eq!(fs::read(dir.join("notes.txt")).unwrap(), b"b\r\n");
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+        let (ending, options) = parse(&["nlnorm", "unix", "--glob", "a[", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_ERROR));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dry_runs_change_nothing() {
+        let dir = temp_dir("dry-run");
+        let path = dir.join("dos.txt");
+        fs::write(&path, "a\r\n").unwrap();
+
+        for flags in [&["--dry-run"][..], &["--dry-run", "--diff"]] {
+            let args = [&["nlnorm", "unix"], flags, &[path.to_str().unwrap()]].concat();
+            let (ending, options) = parse(&args);
+            assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\n");
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--diff", "a.txt"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--check", "--dry-run"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn in_place_keeps_backups_of_changed_files() {
+        let dir = temp_dir("backup");
+        let (dos, unix) = (dir.join("dos.txt"), dir.join("unix.txt"));
+        fs::write(&dos, "a\r\n").unwrap();
+        fs::write(&unix, "a\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--in-place=.bak", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&dos).unwrap(), b"a\n");
+        assert_eq!(fs::read(dir.join("dos.txt.bak")).unwrap(), b"a\r\n");
+        assert!(!dir.join("unix.txt.bak").exists());
+
+        // Running again changes nothing, so the backup of the original stays.
+        let (ending, options) = parse(&["nlnorm", "unix", "--in-place=.bak", dos.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("dos.txt.bak")).unwrap(), b"a\r\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        fs::write(&dos, "b\r\n").unwrap();
+        let (ending, options) = parse(&["nlnorm", "unix", "--in-place", dos.to_str().unwrap()]);
+        assert_eq!(options.in_place.as_deref(), Some(""));
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("dos.txt.bak")).unwrap(), b"a\r\n");
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--in-place=.bak", "--check"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stdout_mode_changes_nothing() {
+        let dir = temp_dir("stdout");
+        let path = dir.join("dos.txt");
+        fs::write(&path, "a\r\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "-c", path.to_str().unwrap()]);
+        assert_eq!(options.mode(), Mode::Stdout);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\n");
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--stdout", "--in-place"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "-q", "-v"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_are_converted_in_parallel() {
+        let dir = temp_dir("jobs");
+        for i in 0..50 {
+            fs::write(dir.join(format!("{i}.txt")), "a\r\nb\r").unwrap();
+        }
+
+        let (ending, options) = parse(&["nlnorm", "unix", "-j", "4", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        for i in 0..50 {
+            assert_eq!(fs::read(dir.join(format!("{i}.txt"))).unwrap(), b"a\nb\n");
+        }
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--jobs", "0"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sarif_notes_the_lines_read() {
+        let dir = temp_dir("sarif");
+        let path = dir.join("dos.txt");
+        fs::write(&path, "a\r\nb\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", "--format", "sarif", path.to_str().unwrap()]);
+        let outcome = process(&path, Newline::Lf, options.mode(), &options, &mut io::sink()).unwrap();
+        assert!(matches!(outcome, Outcome::Scanned(report, _) if report.changed));
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+
+        let (_, options) = parse(&["nlnorm", "unix", "--format", "sarif", path.to_str().unwrap()]);
+        assert!(matches!(process(&path, Newline::Lf, options.mode(), &options, &mut io::sink()).unwrap(), Outcome::Scanned(report, _) if report.changed));
+        assert_eq!(fs::read(&path).unwrap(), b"a\nb\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn large_files_are_not_diffed() {
+        let dir = temp_dir("large");
+        let path = dir.join("dos.txt");
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+
+        let (_, options) = parse(&["nlnorm", "unix", "--dry-run", "--diff", "--max-in-memory", "4"]);
+        let mut out = Vec::new();
+        let Outcome::Normalized(report) = process(&path, Newline::Lf, options.mode(), &options, &mut out).unwrap() else {
+            panic!("not normalized");
+        };
+        assert_eq!((report.changed, report.stats.crlf), (true, 2));
+        assert_eq!(String::from_utf8(out).unwrap(), format!("Files {} differ, too large to show a diff for\n", path.display()));
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\nb\r\n");
+
+        assert_eq!(parse(&["nlnorm", "unix"]).1.max_in_memory, 64 << 20);
+        assert_eq!(parse_size("512k"), Ok(512 << 10));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert!(parse_size("1X").is_err() && parse_size("M").is_err() && parse_size("99999999T").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fix_follows_editorconfig() {
+        let dir = temp_dir("fix");
+        fs::write(dir.join(".editorconfig"), "root = true\n[*.bat]\nend_of_line = crlf\n[*.sh]\nend_of_line = lf\n").unwrap();
+        for name in ["build.bat", "build.sh", "notes.txt"] {
+            fs::write(dir.join(name), "a\nb\r\n").unwrap();
+        }
+
+        let (target, options) = parse(&["nlnorm", "fix", "--default", "crlf", dir.to_str().unwrap()]);
+        assert_eq!(target, Target::EditorConfig { default: Newline::CrLf });
+        assert_eq!(run(target, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("build.bat")).unwrap(), b"a\r\nb\r\n");
+        assert_eq!(fs::read(dir.join("build.sh")).unwrap(), b"a\nb\n");
+        assert_eq!(fs::read(dir.join("notes.txt")).unwrap(), b"a\r\nb\r\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn list_works_like_check() {
+        let dir = temp_dir("list");
+        let path = dir.join("dos.txt");
+        fs::write(&path, "a\r\n").unwrap();
+
+        assert!(parse(&["nlnorm", "unix", "--list", "-0", "a.txt"]).1.null);
+        let (ending, options) = parse(&["nlnorm", "unix", "--list", dir.to_str().unwrap()]);
+        assert_eq!(options.mode(), Mode::Check);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\n");
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "-0", "a.txt"]).is_err());
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--list", "--format", "json", "a.txt"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn native_and_cr_targets() {
+        let (target, _) = parse(&["nlnorm", "fix", "--default", "native"]);
+        assert_eq!(target, Target::EditorConfig { default: if cfg!(windows) { Newline::CrLf } else { Newline::Lf } });
+
+        let dir = temp_dir("cr");
+        let path = dir.join("old-mac.txt");
+        fs::write(&path, "a\r\nb\n").unwrap();
+        let (target, options) = parse(&["nlnorm", "unix", "--map", "*.txt=cr", "--check", path.to_str().unwrap()]);
+        assert_eq!(run(target, &options), ExitCode::from(EXIT_CHANGES));
+        let (target, options) = parse(&["nlnorm", "convert", "--to", "cr", path.to_str().unwrap()]);
+        assert_eq!(target, Target::Fixed(Newline::Cr));
+        assert_eq!(run(target, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&path).unwrap(), b"a\rb\r");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gitignored_files_are_left_alone() {
+        let dir = temp_dir("gitignore");
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.join("build.log"), "a\r\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        let (ending, options) = parse(&["nlnorm", "unix", "--no-ignore", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("build.log")).unwrap(), b"a\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keep_mtime_and_permissions() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = temp_dir("mtime");
+        let path = dir.join("build.sh");
+        fs::write(&path, "make\r\n").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::open(&path).unwrap().set_modified(old).unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+        let permissions = fs::metadata(&path).unwrap().permissions();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--keep-mtime", path.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&path).unwrap(), b"make\n");
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+        assert_eq!(fs::metadata(&path).unwrap().permissions(), permissions);
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--check", "--keep-mtime"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn map_overrides_the_command() {
+        let dir = temp_dir("map");
+        fs::create_dir_all(dir.join("tools")).unwrap();
+        fs::write(dir.join("tools/build.bat"), "echo\n").unwrap();
+        fs::write(dir.join("tools/build.sh"), "echo\r\n").unwrap();
+        fs::write(dir.join("notes.txt"), "a\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--map", "*.{bat,cmd}=crlf,*.txt=crlf", "--map", "/*.txt=lf", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("tools/build.bat")).unwrap(), b"echo\r\n");
+        assert_eq!(fs::read(dir.join("tools/build.sh")).unwrap(), b"echo\n");
+        assert_eq!(fs::read(dir.join("notes.txt")).unwrap(), b"a\n");
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--map", "*.bat=lf", dir.join("tools/build.bat").to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(dir.join("tools/build.bat")).unwrap(), b"echo\n");
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--map", "*.bat"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn byte_order_marks() {
+        let dir = temp_dir("bom");
+        let path = dir.join("bom.txt");
+        fs::write(&path, "\u{feff}a\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", path.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", "--strip-bom", path.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+        let (ending, options) = parse(&["nlnorm", "unix", "--strip-bom", path.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&path).unwrap(), b"a\n");
+        let (ending, options) = parse(&["nlnorm", "dos", "--add-bom", path.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&path).unwrap(), "\u{feff}a\r\n".as_bytes());
+        assert!(Cli::try_parse_from(["nlnorm", "unix", "--strip-bom", "--add-bom"]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn binary_files_need_force() {
+        let dir = temp_dir("binary");
+        let (image, data, text) = (dir.join("logo.png"), dir.join("data.txt"), dir.join("text.txt"));
+        fs::write(&image, b"\x89PNG\r\n\x1a\n").unwrap();
+        fs::write(&data, b"a\0b\r\n").unwrap();
+        fs::write(&text, b"a\r\n").unwrap();
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--check", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::from(EXIT_CHANGES));
+        let (ending, options) = parse(&["nlnorm", "unix", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&image).unwrap(), b"\x89PNG\r\n\x1a\n");
+        assert_eq!(fs::read(&data).unwrap(), b"a\0b\r\n");
+        assert_eq!(fs::read(&text).unwrap(), b"a\n");
+
+        let (ending, options) = parse(&["nlnorm", "unix", "--force", dir.to_str().unwrap()]);
+        assert_eq!(run(ending, &options), ExitCode::SUCCESS);
+        assert_eq!(fs::read(&image).unwrap(), b"\x89PNG\n\x1a\n");
+        assert_eq!(fs::read(&data).unwrap(), b"a\0b\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
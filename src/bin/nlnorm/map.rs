@@ -0,0 +1,102 @@
+//! `--map`: line endings picked by file name patterns, such as `*.bat=crlf,*.sh=lf`.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use crate::newline::Newline;
+use crate::Ending;
+
+/// The entries of one `--map` value, in the order given.
+#[derive(Debug, Clone)]
+pub(crate) struct Mapping(Vec<Entry>);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    matcher: GlobMatcher,
+    /// Globs with a `/` match the path below the directory walked, others the file name.
+    anchored: bool,
+    ending: Newline,
+}
+
+impl FromStr for Mapping {
+    type Err = String;
+
+    /// Parses comma-separated `GLOB=ENDING` entries; commas inside `{a,b}` belong to the glob.
+    fn from_str(value: &str) -> Result<Self, String> {
+        split_entries(value).into_iter().filter(|entry| !entry.trim().is_empty()).map(Entry::parse).collect::<Result<_, _>>().map(Mapping)
+    }
+}
+
+impl Entry {
+    fn parse(entry: &str) -> Result<Entry, String> {
+        let (glob, ending) = entry.rsplit_once('=').ok_or_else(|| format!("`{entry}` is not GLOB=ENDING"))?;
+        let ending = <Ending as ValueEnum>::from_str(ending.trim(), true).map_err(|_| format!("`{}` is not native, lf, crlf or cr", ending.trim()))?;
+        let glob = glob.trim();
+        let anchored = glob.contains('/');
+        let matcher = GlobBuilder::new(glob.strip_prefix('/').unwrap_or(glob))
+            .literal_separator(true)
+            .build()
+            .map(|glob: Glob| glob.compile_matcher())
+            .map_err(|e| e.to_string())?;
+        Ok(Entry { matcher, anchored, ending: ending.into() })
+    }
+
+    fn matches(&self, relative: &Path) -> bool {
+        match relative.file_name() {
+            Some(name) if !self.anchored => self.matcher.is_match(name),
+            _ => self.matcher.is_match(relative),
+        }
+    }
+}
+
+/// The line ending the last matching entry of all `mappings` picks for `relative`, the path
+/// of a file below the directory walked.
+pub(crate) fn lookup(mappings: &[Mapping], relative: &Path) -> Option<Newline> {
+    mappings.iter().flat_map(|mapping| &mapping.0).rev().find(|entry| entry.matches(relative)).map(|entry| entry.ending)
+}
+
+fn split_entries(value: &str) -> Vec<&str> {
+    let (mut entries, mut depth, mut start) = (Vec::new(), 0usize, 0);
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                entries.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&value[start..]);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_entries_win() {
+        let mappings = ["*=lf,*.{bat,cmd}=CRLF".parse::<Mapping>().unwrap(), "scripts/**/*.sh=crlf,/*.sln=crlf,*.mac=cr".parse().unwrap()];
+        let ending = |path: &str| lookup(&mappings, Path::new(path));
+        assert_eq!(ending("build.bat"), Some(Newline::CrLf));
+        assert_eq!(ending("notes.mac"), Some(Newline::Cr));
+        assert_eq!(ending("tools/run.cmd"), Some(Newline::CrLf));
+        assert_eq!(ending("tools/run.sh"), Some(Newline::Lf));
+        assert_eq!(ending("scripts/ci/run.sh"), Some(Newline::CrLf));
+        assert_eq!(ending("app.sln"), Some(Newline::CrLf));
+        assert_eq!(ending("sub/app.sln"), Some(Newline::Lf));
+        assert_eq!(lookup(&[], Path::new("a.txt")), None);
+    }
+
+    #[test]
+    fn malformed_entries() {
+        assert!("*.bat".parse::<Mapping>().is_err());
+        assert!("*.bat=mac".parse::<Mapping>().is_err());
+        assert!("[=lf".parse::<Mapping>().is_err());
+        assert_eq!("*.a=lf,,".parse::<Mapping>().unwrap().0.len(), 1);
+    }
+}
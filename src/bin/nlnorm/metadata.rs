@@ -0,0 +1,56 @@
+//! Giving rewritten files back what replacing them loses.
+//!
+//! `normalize_file` replaces a file with a new one, which keeps the permissions but belongs
+//! to whoever runs `nlnorm` and is modified now. Extended attributes are not copied.
+
+use std::fs::{self, File, FileTimes, Metadata};
+use std::io;
+use std::path::Path;
+
+/// Restores the owner of the file at `path` to that of `original` where permitted, and its
+/// access and modification times with `keep_mtime`.
+pub(crate) fn restore(path: &Path, original: &Metadata, keep_mtime: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{chown, MetadataExt};
+
+        match chown(path, Some(original.uid()), Some(original.gid())) {
+            // Only root can give a file away, but the group can stay if the user is in it.
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                let _ = chown(path, None, Some(original.gid()));
+            }
+            result => result?,
+        }
+        // Changing the owner clears the set-user-ID and set-group-ID bits.
+        fs::set_permissions(path, original.permissions())?;
+    }
+    if keep_mtime {
+        let times = FileTimes::new().set_accessed(original.accessed()?).set_modified(original.modified()?);
+        File::open(path)?.set_times(times)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn times_come_back_on_request() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-metadata-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt");
+        fs::write(&path, "a").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::open(&path).unwrap().set_times(FileTimes::new().set_accessed(old).set_modified(old)).unwrap();
+        let original = fs::metadata(&path).unwrap();
+
+        fs::write(&path, "b").unwrap();
+        restore(&path, &original, false).unwrap();
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+        restore(&path, &original, true).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+//! The line endings `nlnorm` converts to: the library's LF and CRLF, and the lone CR of
+//! classic Mac OS, which is converted to LF first and then has every LF replaced.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use newline_normalizer::{BomPolicy, BomReader, FileReport, LineEnding, Stats};
+
+/// A line ending a file gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Newline {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl Newline {
+    /// CRLF on Windows, LF elsewhere.
+    pub(crate) fn native() -> Newline {
+        if cfg!(windows) {
+            Newline::CrLf
+        } else {
+            Newline::Lf
+        }
+    }
+
+    pub(crate) fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Newline::Lf => b"\n",
+            Newline::CrLf => b"\r\n",
+            Newline::Cr => b"\r",
+        }
+    }
+
+    /// The name of the line ending, for people.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Newline::Lf => "LF",
+            Newline::CrLf => "CRLF",
+            Newline::Cr => "CR",
+        }
+    }
+
+    /// The line ending the library converts to on the way.
+    pub(crate) fn base(self) -> LineEnding {
+        match self {
+            Newline::Lf | Newline::Cr => LineEnding::Lf,
+            Newline::CrLf => LineEnding::CrLf,
+        }
+    }
+}
+
+impl From<LineEnding> for Newline {
+    fn from(ending: LineEnding) -> Newline {
+        match ending {
+            LineEnding::Lf => Newline::Lf,
+            LineEnding::CrLf => Newline::CrLf,
+        }
+    }
+}
+
+/// Normalizes `input` into `out`, handling its byte order mark as `bom` says.
+pub(crate) fn copy<R: Read>(input: R, out: &mut dyn Write, newline: Newline, bom: BomPolicy) -> io::Result<FileReport> {
+    let mut input = BomReader::new(input, bom);
+    let stats = match newline {
+        Newline::Cr => to_cr(newline_normalizer::normalize_copy(&mut input, &mut CrWriter(out), LineEnding::Lf)?),
+        _ => newline_normalizer::normalize_copy(&mut input, out, newline.base())?,
+    };
+    Ok(FileReport { stats, changed: stats.changed() || input.changed() })
+}
+
+/// Like [`newline_normalizer::normalize_file_with_bom`], for any [`Newline`], and keeping
+/// links: the target of a symbolic link is rewritten, and the link stays.
+///
+/// A file with several hard links is rewritten in place, as replacing it would leave the
+/// other links with the old content. Converting to CR, and rewriting in place, hold the
+/// file in memory up to `max_in_memory` bytes; larger files go through a temporary file,
+/// which either replaces the file like the library does or is copied back into it.
+pub(crate) fn normalize_file(path: &Path, newline: Newline, bom: BomPolicy, max_in_memory: u64) -> io::Result<FileReport> {
+    let target = if fs::symlink_metadata(path)?.file_type().is_symlink() { fs::canonicalize(path)? } else { path.to_path_buf() };
+    let source = File::open(&target)?;
+    let metadata = source.metadata()?;
+    let linked = hard_links(&metadata) > 1;
+    if newline != Newline::Cr && !linked {
+        drop(source);
+        return newline_normalizer::normalize_file_with_bom(&target, newline.base(), bom);
+    }
+    if metadata.len() > max_in_memory {
+        return through_temp_file(source, &target, &metadata, linked, newline, bom);
+    }
+    let mut converted = Vec::new();
+    let report = copy(source, &mut converted, newline, bom)?;
+    if !report.changed {
+        return Ok(report);
+    }
+    if linked {
+        fs::write(&target, &converted)?;
+        return Ok(report);
+    }
+    let temp = temp_path(&target);
+    let result = fs::write(&temp, &converted).and_then(|()| fs::set_permissions(&temp, metadata.permissions())).and_then(|()| fs::rename(&temp, &target));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map(|()| report)
+}
+
+/// Converts `source`, the file at `target`, into a temporary file next to it, then renames
+/// that over `target`, or copies it back into `target` if the file is `linked`.
+fn through_temp_file(source: File, target: &Path, metadata: &fs::Metadata, linked: bool, newline: Newline, bom: BomPolicy) -> io::Result<FileReport> {
+    let temp = temp_path(target);
+    let result = File::create(&temp).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        let report = copy(source, &mut out, newline, bom)?;
+        out.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        if !report.changed {
+            return Ok(report);
+        }
+        if linked {
+            io::copy(&mut File::open(&temp)?, &mut File::create(target)?)?;
+        } else {
+            fs::set_permissions(&temp, metadata.permissions())?;
+            fs::rename(&temp, target)?;
+        }
+        Ok(report)
+    });
+    // Gone already once renamed.
+    let _ = fs::remove_file(&temp);
+    result
+}
+
+/// A hidden name next to `target` for the converted file.
+fn temp_path(target: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(format!(".nlnorm-{}", std::process::id()));
+    target.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn hard_links(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+/// Hard links cannot be counted here; files are taken to have one.
+#[cfg(not(unix))]
+fn hard_links(_: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Turns the counts of a conversion to LF into those of the conversion to CR it was for.
+fn to_cr(stats: Stats) -> Stats {
+    Stats { converted: stats.lf + stats.crlf, ..stats }
+}
+
+/// Writes through to the inner writer with every LF turned into a CR.
+struct CrWriter<'a>(&'a mut dyn Write);
+
+impl Write for CrWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let replaced: Vec<u8> = buf.iter().map(|&byte| if byte == b'\n' { b'\r' } else { byte }).collect();
+        self.0.write_all(&replaced)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_cr() {
+        let mut out = Vec::new();
+        let report = copy(&b"\xEF\xBB\xBFa\r\nb\nc\rd"[..], &mut out, Newline::Cr, BomPolicy::Strip).unwrap();
+        assert_eq!(out, b"a\rb\rc\rd");
+        assert_eq!((report.stats.crlf, report.stats.lf, report.stats.cr, report.stats.converted), (1, 1, 1, 2));
+        assert!(report.changed);
+
+        let report = copy(&b"a\rb\r"[..], &mut io::sink(), Newline::Cr, BomPolicy::Keep).unwrap();
+        assert_eq!((report.stats.converted, report.changed), (0, false));
+    }
+
+    #[test]
+    fn rewrites_files_to_cr() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-newline-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("old-mac.txt");
+        fs::write(&path, "a\nb\r\n").unwrap();
+
+        assert!(normalize_file(&path, Newline::Cr, BomPolicy::Keep, u64::MAX).unwrap().changed);
+        assert_eq!(fs::read(&path).unwrap(), b"a\rb\r");
+        assert!(!normalize_file(&path, Newline::Cr, BomPolicy::Keep, u64::MAX).unwrap().changed);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_survive_rewrites() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-newline-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (file, hard, soft) = (dir.join("file.txt"), dir.join("hard.txt"), dir.join("soft.txt"));
+        fs::write(&file, "a\r\n").unwrap();
+        fs::hard_link(&file, &hard).unwrap();
+        std::os::unix::fs::symlink("file.txt", &soft).unwrap();
+
+        assert!(normalize_file(&soft, Newline::Lf, BomPolicy::Keep, u64::MAX).unwrap().changed);
+        assert!(fs::symlink_metadata(&soft).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&hard).unwrap(), b"a\n");
+        assert!(normalize_file(&hard, Newline::Cr, BomPolicy::Keep, u64::MAX).unwrap().changed);
+        assert_eq!(fs::read(&file).unwrap(), b"a\r");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn large_files_go_through_a_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-newline-large-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (file, hard, cr) = (dir.join("file.txt"), dir.join("hard.txt"), dir.join("cr.txt"));
+        fs::write(&file, "a\r\nb\r\n").unwrap();
+        fs::hard_link(&file, &hard).unwrap();
+        fs::write(&cr, "a\nb\n").unwrap();
+
+        assert!(normalize_file(&file, Newline::Lf, BomPolicy::Keep, 4).unwrap().changed);
+        assert_eq!(fs::read(&hard).unwrap(), b"a\nb\n");
+        assert!(normalize_file(&cr, Newline::Cr, BomPolicy::Keep, 0).unwrap().changed);
+        assert_eq!(fs::read(&cr).unwrap(), b"a\rb\r");
+        assert!(!normalize_file(&cr, Newline::Cr, BomPolicy::Keep, 0).unwrap().changed);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,386 @@
+//! Reporting the outcome of each file, for people or for scripts.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use newline_normalizer::{FileReport, Stats};
+use serde::Serialize;
+
+use crate::sarif::{self, Lines};
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    /// Paths of the files that change, errors on standard error.
+    #[default]
+    Text,
+    /// One JSON object per file and per error, each on its own line.
+    Json,
+    /// A SARIF log of the lines with the wrong newline, for code scanning; errors also go to
+    /// standard error.
+    Sarif,
+}
+
+/// What became of one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// The file was normalized, or would have been; the report tells what changed.
+    Normalized(FileReport),
+    /// Like `Normalized`, with the lines found that do not end with the newline wanted.
+    Scanned(FileReport, Lines),
+    /// The file looks binary and was left alone.
+    Binary,
+    /// The file is a symbolic link, which `--no-follow` leaves alone.
+    Symlink,
+    /// With `--follow-symlinks`, the file is reached through a link leading outside the
+    /// directory walked.
+    Outside,
+    /// The file was processed before under another name, a hard or symbolic link to it.
+    SameFile(PathBuf),
+    /// With `--interactive`, converting the file was declined.
+    Declined,
+}
+
+impl Outcome {
+    /// Why the file was skipped, as `--format json` says it.
+    fn kind(&self) -> &'static str {
+        match self {
+            Outcome::Normalized(_) | Outcome::Scanned(..) => "",
+            Outcome::Binary => "binary",
+            Outcome::Symlink => "symlink",
+            Outcome::Outside => "outside",
+            Outcome::SameFile(_) => "same_file",
+            Outcome::Declined => "declined",
+        }
+    }
+
+    /// Why the file was skipped, for people.
+    fn reason(&self) -> String {
+        match self {
+            Outcome::Normalized(_) | Outcome::Scanned(..) => String::new(),
+            Outcome::Binary => "looks binary (use --force to convert it)".into(),
+            Outcome::Symlink => "is a symbolic link".into(),
+            Outcome::Outside => "links outside the directory walked".into(),
+            Outcome::SameFile(first) => format!("same file as {}", first.display()),
+            Outcome::Declined => "not confirmed".into(),
+        }
+    }
+}
+
+/// How much is printed besides errors, which are always printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Verbosity {
+    /// Nothing but errors.
+    Quiet,
+    /// The files that change, the files skipped and a summary.
+    #[default]
+    Normal,
+    /// Also what was found in every file.
+    Verbose,
+}
+
+/// Prints results as they come in and adds them up for the summary and the exit code.
+#[derive(Debug)]
+pub(crate) struct Reporter {
+    format: Format,
+    verbosity: Verbosity,
+    list_changed: bool,
+    /// With `--list`, what ends each path; nothing else is printed but errors.
+    porcelain: Option<u8>,
+    totals: Totals,
+    /// With `--format sarif`, the files that change and the errors, for the log.
+    changed: Vec<(PathBuf, Option<Lines>)>,
+    errors: Vec<(Option<PathBuf>, String)>,
+}
+
+/// What a run did, for its summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+struct Totals {
+    /// Files looked at, including the ones skipped.
+    files: u64,
+    changed: u64,
+    binary: u64,
+    /// Links skipped, and files found again under another name.
+    links: u64,
+    /// Only with `--interactive`, which prints text.
+    #[serde(skip)]
+    declined: u64,
+    errors: u64,
+    converted: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl Reporter {
+    /// With `list_changed`, text output names every file that changes.
+    pub(crate) fn new(format: Format, verbosity: Verbosity, list_changed: bool) -> Self {
+        Reporter { format, verbosity, list_changed, porcelain: None, totals: Totals::default(), changed: Vec::new(), errors: Vec::new() }
+    }
+
+    /// Prints just the paths of the files that change, each followed by `terminator`.
+    pub(crate) fn porcelain(self, terminator: u8) -> Self {
+        Reporter { porcelain: Some(terminator), ..self }
+    }
+
+    /// Some file changed, or would have.
+    pub(crate) fn changes(&self) -> bool {
+        self.totals.changed > 0
+    }
+
+    /// Some file or directory could not be processed.
+    pub(crate) fn failed(&self) -> bool {
+        self.totals.errors > 0
+    }
+
+    /// Records the outcome of processing `path`.
+    pub(crate) fn file(&mut self, path: &Path, result: io::Result<Outcome>) {
+        let quiet = self.verbosity == Verbosity::Quiet;
+        match result {
+            Ok(Outcome::Normalized(report)) => self.normalized(path, report, None),
+            Ok(Outcome::Scanned(report, lines)) => self.normalized(path, report, Some(lines)),
+            Ok(skipped) => {
+                self.totals.files += 1;
+                match skipped {
+                    Outcome::Binary => self.totals.binary += 1,
+                    Outcome::Declined => self.totals.declined += 1,
+                    _ => self.totals.links += 1,
+                }
+                match self.format {
+                    // The answer was just given.
+                    _ if quiet || self.porcelain.is_some() || skipped == Outcome::Declined => {}
+                    Format::Text | Format::Sarif => eprintln!("nlnorm: {}: skipped, {}", path.display(), skipped.reason()),
+                    Format::Json => {
+                        let same_as = match &skipped {
+                            Outcome::SameFile(first) => Some(first.to_string_lossy().into_owned()),
+                            _ => None,
+                        };
+                        print_json(&Entry { skipped: Some(skipped.kind()), same_as, ..Entry::new(Some(path)) });
+                    }
+                }
+            }
+            // The reader of standard output went away; there is nobody left to tell.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(e) => self.error(Some(path), &e.to_string()),
+        }
+    }
+
+    fn normalized(&mut self, path: &Path, report: FileReport, lines: Option<Lines>) {
+        self.totals.add(&report);
+        if self.format == Format::Sarif && report.changed {
+            self.changed.push((path.to_path_buf(), lines));
+        }
+        match (self.format, self.porcelain) {
+            (_, Some(terminator)) if report.changed => print_path(path, terminator),
+            (_, Some(_)) => {}
+            _ if self.verbosity == Verbosity::Quiet => {}
+            (Format::Text | Format::Sarif, None) => {
+                if self.verbosity == Verbosity::Verbose {
+                    eprintln!("nlnorm: {}: {}", path.display(), describe(&report));
+                }
+                if self.list_changed && report.changed && self.format == Format::Text {
+                    print_line(&path.display());
+                }
+            }
+            (Format::Json, None) => print_json(&Entry::file(path, &report)),
+        }
+    }
+
+    /// Records an error that is not about one file, or about a file that was not processed.
+    pub(crate) fn error(&mut self, path: Option<&Path>, message: &str) {
+        self.totals.errors += 1;
+        if self.format == Format::Sarif {
+            self.errors.push((path.map(Path::to_path_buf), message.to_owned()));
+        }
+        match (self.format, path) {
+            (Format::Text | Format::Sarif, Some(path)) => eprintln!("nlnorm: {}: {message}", path.display()),
+            (Format::Text | Format::Sarif, None) => eprintln!("nlnorm: {message}"),
+            (Format::Json, _) => print_json(&Entry { error: Some(message), ..Entry::new(path) }),
+        }
+    }
+
+    /// Prints what the run did: a line on standard error, or a last JSON object. The SARIF
+    /// log is printed here, even with `--quiet`, followed by the line.
+    pub(crate) fn summary(&self) {
+        if self.format == Format::Sarif {
+            print_json(&sarif::log(&self.changed, &self.errors));
+        }
+        match self.format {
+            _ if self.verbosity == Verbosity::Quiet || self.porcelain.is_some() => {}
+            Format::Text | Format::Sarif => eprintln!("nlnorm: {}", self.totals),
+            Format::Json => print_json(&Summary { summary: self.totals }),
+        }
+    }
+}
+
+impl Totals {
+    fn add(&mut self, report: &FileReport) {
+        self.files += 1;
+        self.changed += u64::from(report.changed);
+        self.converted += report.stats.converted;
+        self.bytes_read += report.stats.bytes_read;
+        self.bytes_written += report.stats.bytes_written;
+    }
+}
+
+impl std::fmt::Display for Totals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} files, {} changed", self.files, self.changed)?;
+        if self.binary > 0 {
+            write!(f, ", {} skipped as binary", self.binary)?;
+        }
+        if self.links > 0 {
+            write!(f, ", {} skipped as links", self.links)?;
+        }
+        if self.declined > 0 {
+            write!(f, ", {} left alone", self.declined)?;
+        }
+        if self.errors > 0 {
+            write!(f, ", {} failed", self.errors)?;
+        }
+        write!(f, "; {} line endings converted", self.converted)?;
+        match self.bytes_read.cmp(&self.bytes_written) {
+            std::cmp::Ordering::Greater => write!(f, ", {} bytes saved", self.bytes_read - self.bytes_written),
+            std::cmp::Ordering::Less => write!(f, ", {} bytes added", self.bytes_written - self.bytes_read),
+            std::cmp::Ordering::Equal => Ok(()),
+        }
+    }
+}
+
+/// What `--verbose` says about one file.
+fn describe(report: &FileReport) -> String {
+    let stats = &report.stats;
+    let action = if report.changed { "changed" } else { "unchanged" };
+    format!("{}, {} LF, {} CRLF, {} CR, {} converted, {action}", style(stats), stats.lf, stats.crlf, stats.cr, stats.converted)
+}
+
+/// The last line of `--format json` output.
+#[derive(Debug, Serialize)]
+struct Summary {
+    summary: Totals,
+}
+
+/// One line of `--format json` output.
+#[derive(Debug, Default, Serialize)]
+struct Entry<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    /// Why the file was left alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<&'static str>,
+    /// The path a file skipped as `same_file` was processed under.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    same_as: Option<String>,
+    #[serde(flatten)]
+    counts: Option<Counts>,
+}
+
+/// What was found in a file, and what was or would be rewritten.
+#[derive(Debug, Serialize)]
+struct Counts {
+    /// `none`, `lf`, `crlf`, `cr` or `mixed`.
+    style: &'static str,
+    lf: u64,
+    crlf: u64,
+    cr: u64,
+    converted: u64,
+    changed: bool,
+}
+
+impl<'a> Entry<'a> {
+    fn new(path: Option<&Path>) -> Self {
+        Entry { path: path.map(|path| path.to_string_lossy().into_owned()), ..Entry::default() }
+    }
+
+    fn file(path: &Path, report: &FileReport) -> Self {
+        let stats = &report.stats;
+        let counts = Counts {
+            style: style(stats),
+            lf: stats.lf,
+            crlf: stats.crlf,
+            cr: stats.cr,
+            converted: stats.converted,
+            changed: report.changed,
+        };
+        Entry { counts: Some(counts), ..Entry::new(Some(path)) }
+    }
+}
+
+/// The newline style found in the input: the only kind of newline in it, or `mixed`.
+fn style(stats: &Stats) -> &'static str {
+    match (stats.lf > 0, stats.crlf > 0, stats.cr > 0) {
+        (false, false, false) => "none",
+        (true, false, false) => "lf",
+        (false, true, false) => "crlf",
+        (false, false, true) => "cr",
+        _ => "mixed",
+    }
+}
+
+fn print_json(entry: &impl Serialize) {
+    let json = serde_json::to_string(entry).expect("output serializes");
+    print_line(&json);
+}
+
+/// Prints `path` as it is, byte for byte where the platform allows, then `terminator`.
+fn print_path(path: &Path, terminator: u8) {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(&bytes).and_then(|()| stdout.write_all(&[terminator]));
+}
+
+/// Prints a line to standard output, ignoring a closed pipe as `| head` leaves behind.
+fn print_line(line: &dyn std::fmt::Display) {
+    let _ = writeln!(io::stdout().lock(), "{line}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_name_the_newlines_found() {
+        let stats = |lf, crlf, cr| Stats { lf, crlf, cr, ..Stats::default() };
+        assert_eq!(style(&stats(0, 0, 0)), "none");
+        assert_eq!(style(&stats(3, 0, 0)), "lf");
+        assert_eq!(style(&stats(0, 2, 0)), "crlf");
+        assert_eq!(style(&stats(0, 0, 1)), "cr");
+        assert_eq!(style(&stats(1, 1, 0)), "mixed");
+    }
+
+    #[test]
+    fn totals_add_up() {
+        let mut reporter = Reporter::new(Format::Text, Verbosity::Quiet, false);
+        let report = FileReport { stats: Stats { bytes_read: 10, bytes_written: 8, crlf: 2, converted: 2, ..Stats::default() }, changed: true };
+        reporter.file(Path::new("a.txt"), Ok(Outcome::Normalized(report)));
+        reporter.file(Path::new("b.txt"), Ok(Outcome::Normalized(FileReport::default())));
+        reporter.file(Path::new("c.png"), Ok(Outcome::Binary));
+        reporter.file(Path::new("link.txt"), Ok(Outcome::SameFile(PathBuf::from("a.txt"))));
+        assert!(reporter.changes() && !reporter.failed());
+        assert_eq!(reporter.totals.to_string(), "4 files, 1 changed, 1 skipped as binary, 1 skipped as links; 2 line endings converted, 2 bytes saved");
+        reporter.error(Some(Path::new("d.txt")), "denied");
+        assert!(reporter.failed());
+        assert_eq!(
+            serde_json::to_string(&Summary { summary: reporter.totals }).unwrap(),
+            r#"{"summary":{"files":4,"changed":1,"binary":1,"links":1,"errors":1,"converted":2,"bytes_read":10,"bytes_written":8}}"#
+        );
+    }
+
+    #[test]
+    fn json_entries() {
+        let report = FileReport { stats: Stats { lf: 1, crlf: 2, converted: 2, ..Stats::default() }, changed: true };
+        assert_eq!(
+            serde_json::to_string(&Entry::file(Path::new("a.txt"), &report)).unwrap(),
+            r#"{"path":"a.txt","style":"mixed","lf":1,"crlf":2,"cr":0,"converted":2,"changed":true}"#
+        );
+        let error = Entry { error: Some("denied"), ..Entry::new(None) };
+        assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"error":"denied"}"#);
+        let skipped = Entry { skipped: Some("binary"), ..Entry::new(Some(Path::new("a.png"))) };
+        assert_eq!(serde_json::to_string(&skipped).unwrap(), r#"{"path":"a.png","skipped":"binary"}"#);
+    }
+}
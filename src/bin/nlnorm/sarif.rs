@@ -0,0 +1,223 @@
+//! `--format sarif`: the lines that end with the wrong newline, as a SARIF 2.1.0 log that
+//! GitHub code scanning and other SARIF consumers show as annotations.
+//!
+//! The lines are found as the files are read, by [`Scanner`]. Unlike the other formats,
+//! the log is one document, printed once the run is over.
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::newline::Newline;
+
+/// Most lines reported for one file; the others are only counted.
+const MAX_LINES: usize = 100;
+
+/// The one rule results are reported under.
+const RULE: &str = "line-ending";
+
+/// The lines of a file that do not end with the newline wanted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lines {
+    wanted: Newline,
+    /// The line being read, from 1.
+    line: u64,
+    /// The previous byte read was a CR.
+    after_cr: bool,
+    /// The lines found, up to [`MAX_LINES`], and the newline each ends with.
+    found: Vec<(u64, Newline)>,
+    /// Further lines found.
+    omitted: u64,
+}
+
+impl Lines {
+    pub(crate) fn new(wanted: Newline) -> Self {
+        Lines { wanted, line: 1, after_cr: false, found: Vec::new(), omitted: 0 }
+    }
+
+    fn scan(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.after_cr {
+                self.after_cr = false;
+                if byte == b'\n' {
+                    self.record(Newline::CrLf);
+                    continue;
+                }
+                self.record(Newline::Cr);
+            }
+            match byte {
+                b'\r' => self.after_cr = true,
+                b'\n' => self.record(Newline::Lf),
+                _ => {}
+            }
+        }
+    }
+
+    /// Counts a CR left at the end of the input.
+    fn finish(&mut self) {
+        if self.after_cr {
+            self.after_cr = false;
+            self.record(Newline::Cr);
+        }
+    }
+
+    fn record(&mut self, found: Newline) {
+        if found != self.wanted {
+            if self.found.len() < MAX_LINES {
+                self.found.push((self.line, found));
+            } else {
+                self.omitted += 1;
+            }
+        }
+        self.line += 1;
+    }
+}
+
+/// Passes reads through, noting the line endings in [`Lines`] when it has some.
+pub(crate) struct Scanner<'a, R> {
+    inner: R,
+    lines: Option<&'a mut Lines>,
+}
+
+impl<'a, R: Read> Scanner<'a, R> {
+    pub(crate) fn new(inner: R, lines: Option<&'a mut Lines>) -> Self {
+        Scanner { inner, lines }
+    }
+}
+
+impl<R: Read> Read for Scanner<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(lines) = self.lines.as_deref_mut() {
+            if n == 0 {
+                lines.finish();
+            } else {
+                lines.scan(&buf[..n]);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// The log of a run: one result per line found in the files that changed, and the errors.
+///
+/// A file that changed with no line found, as only its byte order mark did, gets a result
+/// without a line.
+pub(crate) fn log(files: &[(PathBuf, Option<Lines>)], errors: &[(Option<PathBuf>, String)]) -> Value {
+    let mut results = Vec::new();
+    for (path, lines) in files {
+        let location = |line: Option<u64>| {
+            let mut physical = json!({ "artifactLocation": artifact(path) });
+            if let Some(line) = line {
+                physical["region"] = json!({ "startLine": line });
+            }
+            json!([{ "physicalLocation": physical }])
+        };
+        let result = |text: String, line: Option<u64>| json!({ "ruleId": RULE, "level": "warning", "message": { "text": text }, "locations": location(line) });
+        match lines {
+            Some(lines) if !lines.found.is_empty() => {
+                for &(line, found) in &lines.found {
+                    results.push(result(format!("Line ends with {}, not {}.", found.name(), lines.wanted.name()), Some(line)));
+                }
+                if lines.omitted > 0 {
+                    results.push(result(format!("{} more lines do not end with {}.", lines.omitted, lines.wanted.name()), None));
+                }
+            }
+            _ => results.push(result("File needs normalizing.".to_owned(), None)),
+        }
+    }
+    let notifications: Vec<Value> = errors
+        .iter()
+        .map(|(path, message)| {
+            let mut notification = json!({ "level": "error", "message": { "text": message } });
+            if let Some(path) = path {
+                notification["locations"] = json!([{ "physicalLocation": { "artifactLocation": artifact(path) } }]);
+            }
+            notification
+        })
+        .collect();
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "nlnorm",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": RULE,
+                        "shortDescription": { "text": "Line ending other than the one wanted" },
+                    }],
+                },
+            },
+            "invocations": [{ "executionSuccessful": errors.is_empty(), "toolExecutionNotifications": notifications }],
+            "results": results,
+        }],
+    })
+}
+
+/// Where `path` is: relative to the root of the sources scanned, unless it is absolute.
+fn artifact(path: &Path) -> Value {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let relative = text.strip_prefix("./").unwrap_or(&text);
+    if path.is_absolute() {
+        let slash = if relative.starts_with('/') { "" } else { "/" };
+        json!({ "uri": format!("file://{slash}{}", encode(relative)) })
+    } else {
+        json!({ "uri": encode(relative), "uriBaseId": "%SRCROOT%" })
+    }
+}
+
+/// Percent-encodes what cannot appear as it is in the path of a URI.
+fn encode(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => encoded.push(char::from(byte)),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_found_while_reading() {
+        let input: &[u8] = b"a\nb\r\nc\rd\ne\r";
+        let mut lines = Lines::new(Newline::Lf);
+        let mut out = Vec::new();
+        Scanner::new(input, Some(&mut lines)).read_to_end(&mut out).unwrap();
+        assert_eq!(out, input);
+        assert_eq!(lines.found, [(2, Newline::CrLf), (3, Newline::Cr), (5, Newline::Cr)]);
+
+        // A CRLF split between reads is still one newline.
+        let mut split = Lines::new(Newline::Lf);
+        split.scan(&input[..4]);
+        split.scan(&input[4..]);
+        split.finish();
+        assert_eq!(split, lines);
+
+        let mut lines = Lines::new(Newline::CrLf);
+        lines.scan(&b"\n".repeat(MAX_LINES + 5));
+        assert_eq!((lines.found.len(), lines.omitted), (MAX_LINES, 5));
+    }
+
+    #[test]
+    fn logs_results_and_errors() {
+        let mut lines = Lines::new(Newline::Lf);
+        lines.scan(b"a\r\n");
+        let files = [(PathBuf::from("./src/a b.txt"), Some(lines)), (PathBuf::from("/abs/c.txt"), None)];
+        let log = log(&files, &[(Some(PathBuf::from("d.txt")), "denied".to_owned())]);
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["results"][0]["message"]["text"], "Line ends with CRLF, not LF.");
+        assert_eq!(run["results"][0]["locations"][0]["physicalLocation"], json!({ "artifactLocation": { "uri": "src/a%20b.txt", "uriBaseId": "%SRCROOT%" }, "region": { "startLine": 1 } }));
+        assert_eq!(run["results"][1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "file:///abs/c.txt");
+        assert_eq!(run["invocations"][0]["executionSuccessful"], false);
+        assert_eq!(run["invocations"][0]["toolExecutionNotifications"][0]["message"]["text"], "denied");
+    }
+}
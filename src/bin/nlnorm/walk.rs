@@ -0,0 +1,130 @@
+//! Expands the paths on the command line into the files to normalize.
+
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+
+/// Walks directories, keeping the files that pass the `--glob` filters and are not ignored.
+#[derive(Debug)]
+pub(crate) struct Walker {
+    globs: Vec<String>,
+    ignore: bool,
+    follow_links: bool,
+}
+
+impl Walker {
+    /// Checks the globs up front, so that a typo fails the run before any file is touched.
+    ///
+    /// With `ignore`, files excluded by `.gitignore`, `.git/info/exclude`, the global git
+    /// excludes and `.ignore` files are skipped, as git itself would.
+    pub(crate) fn new(globs: &[String], ignore: bool) -> Result<Self, String> {
+        let walker = Walker { globs: globs.to_vec(), ignore, follow_links: false };
+        walker.overrides(Path::new(".")).map(|_| walker)
+    }
+
+    /// With `follow`, symbolic links below the paths named are followed; otherwise they are
+    /// skipped.
+    pub(crate) fn follow_links(self, follow: bool) -> Self {
+        Walker { follow_links: follow, ..self }
+    }
+
+    /// Calls `visit` with `root` if it is a file, or with every file below it in name order.
+    ///
+    /// - Globs filter the files found in directories, relative to `root`. Paths named on
+    ///   the command line are always visited, even if ignored.
+    /// - Hidden files are visited, but `.git` directories are never entered.
+    /// - Each directory is listed in full before its files are visited, so files created
+    ///   next to them, such as backups, are not picked up.
+    pub(crate) fn files(&self, root: &Path, visit: &mut dyn FnMut(Result<PathBuf, String>)) {
+        let overrides = match self.overrides(root) {
+            Ok(overrides) => overrides,
+            Err(e) => return visit(Err(e)),
+        };
+        let walk = WalkBuilder::new(root)
+            .standard_filters(self.ignore)
+            .hidden(false)
+            .follow_links(self.follow_links)
+            .overrides(overrides)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walk {
+            match entry {
+                Ok(entry) if entry.file_type().map_or(false, |kind| kind.is_file()) => visit(Ok(entry.into_path())),
+                Ok(_) => {}
+                Err(e) => visit(Err(e.to_string())),
+            }
+        }
+    }
+
+    fn overrides(&self, root: &Path) -> Result<Override, String> {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &self.globs {
+            overrides.add(glob).map_err(|e| e.to_string())?;
+        }
+        overrides.build().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tree(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nlnorm-walk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x\r\n").unwrap();
+        }
+        dir
+    }
+
+    fn collect(walker: &Walker, root: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        walker.files(root, &mut |file| {
+            let file = file.unwrap();
+            files.push(file.strip_prefix(root).unwrap_or(&file).to_string_lossy().replace('\\', "/"));
+        });
+        files
+    }
+
+    #[test]
+    fn globs_filter_directory_walks() {
+        let dir = tree("globs", &["a.rs", "b.txt", "src/c.rs", "target/d.rs", ".git/config", ".editorconfig"]);
+
+        let all = Walker::new(&[], false).unwrap();
+        assert_eq!(collect(&all, &dir), [".editorconfig", "a.rs", "b.txt", "src/c.rs", "target/d.rs"]);
+
+        let rust = Walker::new(&["**/*.rs".into(), "!target/**".into()], false).unwrap();
+        assert_eq!(collect(&rust, &dir), ["a.rs", "src/c.rs"]);
+        assert_eq!(collect(&rust, &dir.join("b.txt")), [""]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ignored_files_are_skipped() {
+        let dir = tree("ignore", &["a.rs", "target/b.rs", "logs/c.log", "logs/d.txt", ".git/info/exclude", "nested/e.rs"]);
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.join(".git/info/exclude"), "*.log\n").unwrap();
+        fs::write(dir.join("nested/.gitignore"), "e.rs\n").unwrap();
+
+        let walker = Walker::new(&[], true).unwrap();
+        assert_eq!(collect(&walker, &dir), [".gitignore", "a.rs", "logs/d.txt", "nested/.gitignore"]);
+        assert_eq!(collect(&walker, &dir.join("target/b.rs")), [""]);
+        assert_eq!(collect(&Walker::new(&[], false).unwrap(), &dir).len(), 7);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bad_globs_and_paths_are_errors() {
+        assert!(Walker::new(&["a[".into()], true).is_err());
+
+        let mut errors = 0;
+        Walker::new(&[], false).unwrap().files(Path::new("/nonexistent/nlnorm"), &mut |file| errors += file.is_err() as usize);
+        assert_eq!(errors, 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! `nlnorm watch`: normalizing files again whenever they are created or modified.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{collect, process_all, Options, Target, EXIT_ERROR};
+
+/// How long to wait for more events before handling a batch, as editors and copies usually
+/// touch a file several times in a row.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Normalizes everything below the roots once, then each file again as it changes, until
+/// interrupted.
+///
+/// A file that changes is processed as if it was found walking its root, so globs, ignore
+/// files and `--map` apply as without `watch`. Rewriting a file triggers an event for it
+/// too, but finds it normalized the second time.
+pub(crate) fn watch(target: Target, options: &Options) -> ExitCode {
+    let roots = if options.files.is_empty() { vec![PathBuf::from(".")] } else { options.files.clone() };
+    if roots.iter().any(|root| root == Path::new("-")) {
+        eprintln!("nlnorm: watch needs files or directories, not standard input");
+        return ExitCode::from(EXIT_ERROR);
+    }
+    let walker = match options.walker() {
+        Ok(walker) => walker,
+        Err(e) => {
+            eprintln!("nlnorm: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("nlnorm: cannot watch files: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    let mut watched = Vec::new();
+    for root in &roots {
+        let result = fs::canonicalize(root).map_err(|e| e.to_string()).and_then(|absolute| {
+            watcher.watch(&absolute, RecursiveMode::Recursive).map_err(|e| e.to_string())?;
+            Ok(absolute)
+        });
+        match result {
+            Ok(absolute) => watched.push((root.clone(), absolute)),
+            Err(e) => {
+                eprintln!("nlnorm: {}: {e}", root.display());
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    // Every file that changes is worth a line, whatever the mode.
+    let mut reporter = options.reporter(true);
+    process_all(&collect(target, options, &walker, &roots, None), options, &mut reporter);
+    while let Ok(first) = receiver.recv() {
+        let mut changed = HashSet::new();
+        let mut deadline = Instant::now();
+        let mut next = Some(first);
+        while let Some(event) = next {
+            match event {
+                Ok(event) => {
+                    let paths = changed_paths(&event, &watched);
+                    // Only changes keep the batch open; reads, such as polling a file, do not.
+                    if !paths.is_empty() {
+                        deadline = Instant::now() + SETTLE;
+                    }
+                    changed.extend(paths);
+                }
+                Err(e) => reporter.error(None, &e.to_string()),
+            }
+            let wait = deadline.saturating_duration_since(Instant::now());
+            next = if wait.is_zero() { None } else { receiver.recv_timeout(wait).ok() };
+        }
+        if changed.is_empty() {
+            continue;
+        }
+        let roots: Vec<PathBuf> =
+            watched.iter().filter(|(root, _)| changed.iter().any(|path| path.starts_with(root))).map(|(root, _)| root.clone()).collect();
+        process_all(&collect(target, options, &walker, &roots, Some(&changed)), options, &mut reporter);
+    }
+    ExitCode::from(EXIT_ERROR)
+}
+
+/// The files an event creates or modifies, as paths below the root they were found under.
+fn changed_paths(event: &Event, watched: &[(PathBuf, PathBuf)]) -> Vec<PathBuf> {
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .iter()
+        .filter_map(|path| {
+            watched.iter().find_map(|(root, absolute)| match path.strip_prefix(absolute) {
+                Ok(relative) if relative.as_os_str().is_empty() => Some(root.clone()),
+                Ok(relative) => Some(root.join(relative)),
+                Err(_) => None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use notify::event::{AccessKind, CreateKind};
+
+    #[test]
+    fn events_map_back_to_the_roots() {
+        let watched = [(PathBuf::from("assets"), PathBuf::from("/work/assets")), (PathBuf::from("a.txt"), PathBuf::from("/work/a.txt"))];
+        let event = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(PathBuf::from("/work/assets/icons/logo.svg"))
+            .add_path(PathBuf::from("/work/a.txt"))
+            .add_path(PathBuf::from("/elsewhere/b.txt"));
+        assert_eq!(changed_paths(&event, &watched), [PathBuf::from("assets/icons/logo.svg"), PathBuf::from("a.txt")]);
+        let access = Event::new(EventKind::Access(AccessKind::Any)).add_path(PathBuf::from("/work/a.txt"));
+        assert!(changed_paths(&access, &watched).is_empty());
+    }
+
+    #[test]
+    fn rewrites_files_as_they_change() {
+        let dir = std::env::temp_dir().join(format!("nlnorm-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.txt"), dir.join("new.txt"));
+        fs::write(&old, "a\r\n").unwrap();
+
+        let cli = crate::Cli::try_parse_from(["nlnorm", "watch", "--to", "lf", dir.to_str().unwrap()]).unwrap();
+        let crate::Command::Watch { to, options } = cli.command else {
+            panic!("not a watch command");
+        };
+        // Watches until the test process ends.
+        std::thread::spawn(move || watch(Target::Fixed(to.expect("given").into()), &options));
+
+        let wait_for = |path: &Path, expected: &[u8]| {
+            for _ in 0..100 {
+                if fs::read(path).map_or(false, |content| content == expected) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            false
+        };
+        assert!(wait_for(&old, b"a\n"));
+        fs::write(&new, "b\r\nc\r").unwrap();
+        assert!(wait_for(&new, b"b\nc\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
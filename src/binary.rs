@@ -0,0 +1,84 @@
+//! Telling binary files apart from text, so that normalizing leaves them alone.
+//!
+//! A CR byte in an image or an archive is data, not a line break; rewriting it corrupts the
+//! file. A file counts as binary if its extension names a binary format, or if it has a NUL
+//! byte near the start, which is the test git uses too.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from the start of a file looking for a NUL, as many as git reads.
+const SNIFF: u64 = 8000;
+
+/// Extensions of formats that are binary even when their first bytes have no NUL.
+const EXTENSIONS: &[&str] = &[
+    // Images
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tif", "tiff", "psd", "heic", "avif",
+    // Archives and compressed files
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "tar", "jar", "war", "whl", "deb", "rpm",
+    // Documents
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp",
+    // Compiled code
+    "exe", "dll", "so", "dylib", "a", "o", "obj", "lib", "rlib", "class", "pyc", "wasm", "bin",
+    // Audio and video
+    "mp3", "mp4", "m4a", "wav", "ogg", "flac", "avi", "mov", "mkv", "webm",
+    // Fonts and databases
+    "ttf", "otf", "woff", "woff2", "eot", "sqlite", "db",
+];
+
+/// Whether the file at `path` looks binary: its extension names a binary format such as
+/// `png` or `zip`, or its first 8000 bytes hold a NUL.
+///
+/// Example:
+/// ```no_run
+/// if !newline_normalizer::looks_binary("logo.png")? {
+///     newline_normalizer::normalize_file("logo.png", newline_normalizer::LineEnding::Lf)?;
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn looks_binary<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let path = path.as_ref();
+    if has_binary_extension(path) {
+        return Ok(true);
+    }
+    let mut head = Vec::new();
+    File::open(path)?.take(SNIFF).read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+fn has_binary_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| EXTENSIONS.iter().any(|known| extension.eq_ignore_ascii_case(known)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn extensions() {
+        assert!(has_binary_extension(Path::new("logo.png")));
+        assert!(has_binary_extension(Path::new("dir/PHOTO.JPG")));
+        assert!(!has_binary_extension(Path::new("main.rs")));
+        assert!(!has_binary_extension(Path::new("png")));
+        assert!(!has_binary_extension(Path::new("archive.zip.txt")));
+    }
+
+    #[test]
+    fn nul_bytes_near_the_start() {
+        let dir = std::env::temp_dir().join(format!("newline_normalizer-binary-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (text, data, late) = (dir.join("text.txt"), dir.join("data"), dir.join("late.txt"));
+        fs::write(&text, "a\r\nb\r\n").unwrap();
+        fs::write(&data, b"\x7fELF\x02\x01\x01\x00\r\n").unwrap();
+        fs::write(&late, [&[b'a'; SNIFF as usize][..], b"\0"].concat()).unwrap();
+        assert!(!looks_binary(&text).unwrap());
+        assert!(looks_binary(&data).unwrap());
+        assert!(!looks_binary(&late).unwrap());
+        assert!(looks_binary(dir.join("missing.txt")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Normalization of very large text in async code, with a yield point after every block.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::{kernel, LineEnding};
+
+/// Bytes normalized between two yield points.
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Like [`LineEnding::normalize`], yielding to the executor after every megabyte so that a
+/// multi-gigabyte input does not stall the other tasks on its thread.
+///
+/// - Works with any executor: yielding only wakes the task and returns `Pending` once.
+/// - Blocks never split a CRLF, so the result equals that of `normalize`.
+/// - Text that needs no change is returned borrowed, as with `normalize`.
+///
+/// The blocks only bound the time between yield points; they are not sized for the cache.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_in_blocks, LineEnding};
+///
+/// let text = "line\r\n".repeat(1_000_000);
+/// let unix = futures::executor::block_on(normalize_in_blocks(&text, LineEnding::Lf));
+/// assert_eq!(unix.len(), 5_000_000);
+/// ```
+pub async fn normalize_in_blocks(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    let mut out: Option<Vec<u8>> = None;
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let mut end = (start + BLOCK_SIZE).min(bytes.len());
+        if bytes[end - 1] == b'\r' && bytes.get(end) == Some(&b'\n') {
+            end += 1;
+        }
+        let block = &bytes[start..end];
+        match &mut out {
+            Some(out) => {
+                if !ending.normalize_bytes_into(block, out) {
+                    out.extend_from_slice(block);
+                }
+            }
+            // The check stops at the first newline to change, so the first changed block is
+            // then normalized straight into the output, after the blocks before it.
+            None if kernel::is_normalized(block, ending == LineEnding::CrLf) => {}
+            None => {
+                let mut first = Vec::with_capacity(bytes.len() + bytes.len() / 16);
+                first.extend_from_slice(&bytes[..start]);
+                ending.normalize_bytes_into(block, &mut first);
+                out = Some(first);
+            }
+        }
+        start = end;
+        if start < bytes.len() {
+            YieldNow(false).await;
+        }
+    }
+
+    match out {
+        // Only ASCII newline bytes were rewritten, so the result is still UTF-8.
+        Some(out) => Cow::Owned(unsafe { String::from_utf8_unchecked(out) }),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Returns `Pending` once, waking the task right away.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Polls `future` to completion, returning its output and how often it yielded.
+    fn run<F: Future>(future: F) -> (F::Output, usize) {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        let mut yields = 0;
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return (output, yields),
+                Poll::Pending => yields += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn yields_between_blocks() {
+        let text = "x".repeat(BLOCK_SIZE - 1) + "\r\n" + &"y\n".repeat(BLOCK_SIZE);
+        let (dos, yields) = run(normalize_in_blocks(&text, LineEnding::CrLf));
+        assert_eq!(dos, LineEnding::CrLf.normalize(&text));
+        assert_eq!(yields, 2);
+
+        let (unix, _) = run(normalize_in_blocks(&text, LineEnding::Lf));
+        assert_eq!(unix, LineEnding::Lf.normalize(&text));
+    }
+
+    #[test]
+    fn normalized_text_is_borrowed() {
+        let text = "line\n".repeat(BLOCK_SIZE / 2);
+        let (unix, yields) = run(normalize_in_blocks(&text, LineEnding::Lf));
+        assert!(matches!(unix, Cow::Borrowed(_)));
+        assert_eq!(yields, 2);
+        assert_eq!(run(normalize_in_blocks("", LineEnding::CrLf)), (Cow::Borrowed(""), 0));
+    }
+}
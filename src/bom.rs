@@ -0,0 +1,187 @@
+//! Adding or removing a UTF-8 byte order mark while normalizing.
+
+use alloc::borrow::Cow;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::Bom;
+
+/// What to do with a UTF-8 byte order mark at the start of the text.
+///
+/// Only the UTF-8 mark is added or removed. Text starting with a UTF-16 mark is left as it
+/// is: dropping that mark loses the byte order, and adding a UTF-8 one would be wrong.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::BomPolicy;
+///
+/// assert_eq!(BomPolicy::Strip.apply(b"\xEF\xBB\xBFtext"), &b"text"[..]);
+/// assert_eq!(BomPolicy::Add.apply(b"text"), &b"\xEF\xBB\xBFtext"[..]);
+/// assert_eq!(BomPolicy::Add.apply(b"\xEF\xBB\xBFtext"), &b"\xEF\xBB\xBFtext"[..]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BomPolicy {
+    /// Leaves the start of the text as it is.
+    #[default]
+    Keep,
+    /// Removes a UTF-8 byte order mark.
+    Strip,
+    /// Adds a UTF-8 byte order mark to text that has no byte order mark.
+    Add,
+}
+
+impl BomPolicy {
+    /// Applies the policy to `text`, borrowing it when nothing changes.
+    pub fn apply(self, text: &[u8]) -> Cow<'_, [u8]> {
+        match self.edit(text) {
+            (b"", skip) => Cow::Borrowed(&text[skip..]),
+            (prefix, _) => Cow::Owned([prefix, text].concat()),
+        }
+    }
+
+    /// The bytes to write before the text that starts with `start`, and the number of bytes
+    /// of `start` to drop. `start` holds the first three bytes, or all of a shorter text.
+    pub(crate) fn edit(self, start: &[u8]) -> (&'static [u8], usize) {
+        let utf8 = Bom::Utf8.as_bytes();
+        match (self, Bom::detect(start)) {
+            (BomPolicy::Strip, Some(Bom::Utf8)) => (b"", utf8.len()),
+            (BomPolicy::Add, None) => (utf8, 0),
+            _ => (b"", 0),
+        }
+    }
+}
+
+/// Wraps a reader and applies a [`BomPolicy`] to the start of what it reads.
+///
+/// Put it in front of any of the streaming functions, such as
+/// [`normalize_copy`](crate::normalize_copy), to handle the byte order mark on the way.
+///
+/// Example:
+/// ```
+/// use std::io::Read;
+/// use newline_normalizer::{BomPolicy, BomReader};
+///
+/// let mut reader = BomReader::new(&b"\xEF\xBB\xBFtext"[..], BomPolicy::Strip);
+/// let mut out = String::new();
+/// reader.read_to_string(&mut out).unwrap();
+/// assert_eq!(out, "text");
+/// assert!(reader.changed());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct BomReader<R> {
+    inner: R,
+    policy: BomPolicy,
+    /// The first bytes of the input, read ahead to look for a byte order mark.
+    head: [u8; 3],
+    head_len: usize,
+    head_pos: usize,
+    prefix: &'static [u8],
+    decided: bool,
+    changed: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> BomReader<R> {
+    /// Creates a reader applying `policy`.
+    pub fn new(inner: R, policy: BomPolicy) -> Self {
+        BomReader { inner, policy, head: [0; 3], head_len: 0, head_pos: 0, prefix: b"", decided: false, changed: false }
+    }
+
+    fn decide(&mut self) -> io::Result<()> {
+        while self.head_len < self.head.len() {
+            match self.inner.read(&mut self.head[self.head_len..]) {
+                Ok(0) => break,
+                Ok(read) => self.head_len += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        (self.prefix, self.head_pos) = self.policy.edit(&self.head[..self.head_len]);
+        self.changed = !self.prefix.is_empty() || self.head_pos > 0;
+        self.decided = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> BomReader<R> {
+    /// Returns `true` once a byte order mark was added or removed.
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for BomReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.decided {
+            self.decide()?;
+        }
+        let pending = if self.prefix.is_empty() { &self.head[self.head_pos..self.head_len] } else { self.prefix };
+        if pending.is_empty() {
+            return self.inner.read(buf);
+        }
+        let len = pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&pending[..len]);
+        if self.prefix.is_empty() {
+            self.head_pos += len;
+        } else {
+            self.prefix = &self.prefix[len..];
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        let (bom, utf16) = (&b"\xEF\xBB\xBFa"[..], &b"\xFF\xFEa\0"[..]);
+        assert_eq!(BomPolicy::Keep.apply(bom), bom);
+        assert_eq!(BomPolicy::Strip.apply(bom), &b"a"[..]);
+        assert_eq!(BomPolicy::Strip.apply(utf16), utf16);
+        assert_eq!(BomPolicy::Add.apply(utf16), utf16);
+        assert_eq!(BomPolicy::Add.apply(b""), &b"\xEF\xBB\xBF"[..]);
+        assert!(matches!(BomPolicy::Strip.apply(bom), Cow::Borrowed(_)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_matches_apply_in_any_read_size() {
+        /// Yields one byte per read.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.0.len().min(1).min(buf.len());
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        for input in [&b""[..], b"a", b"\xEF\xBB", b"\xEF\xBB\xBF", b"\xEF\xBB\xBFline\r\n", b"plain text"] {
+            for policy in [BomPolicy::Keep, BomPolicy::Strip, BomPolicy::Add] {
+                let mut reader = BomReader::new(Trickle(input), policy);
+                let mut out = Vec::new();
+                reader.read_to_end(&mut out).unwrap();
+                let expected = policy.apply(input);
+                assert_eq!(out, &*expected, "{input:?} {policy:?}");
+                assert_eq!(reader.changed(), *expected != *input, "{input:?} {policy:?}");
+            }
+        }
+    }
+}
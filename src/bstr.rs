@@ -0,0 +1,86 @@
+//! Byte strings from [`bstr`](::bstr), enabled by the `bstr` feature.
+//!
+//! [`BStr`] is only conventionally UTF-8, so its normalized forms are byte strings too: the
+//! traits here mirror [`crate::ToUnixNewlines`] and [`crate::ToDosNewlines`] for it and for
+//! [`BString`]. Invalid UTF-8 is passed through untouched.
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+pub use ::bstr::{BStr, BString};
+
+use crate::LineEnding;
+
+/// Converts any mix of CRLF and CR newlines in a byte string to LF.
+///
+/// Example:
+/// ```
+/// use bstr::ByteSlice;
+/// use newline_normalizer::bstr::ToUnixNewlines;
+///
+/// let text = b"caf\xE9\r\nok\r".as_bstr();
+/// assert_eq!(text.to_unix_newlines(), b"caf\xE9\nok\n".as_bstr());
+/// ```
+pub trait ToUnixNewlines {
+    /// Normalize all line breaks in the input to LF (`\n`).
+    ///
+    /// Returns a borrowed reference if no transformation is needed.
+    fn to_unix_newlines(&self) -> Cow<'_, BStr>;
+}
+
+/// Converts any mix of LF and CR newlines in a byte string to CRLF.
+pub trait ToDosNewlines {
+    /// Normalize all line breaks in the input to CRLF (`\r\n`).
+    ///
+    /// Returns a borrowed reference if no transformation is needed.
+    fn to_dos_newlines(&self) -> Cow<'_, BStr>;
+}
+
+impl ToUnixNewlines for BStr {
+    fn to_unix_newlines(&self) -> Cow<'_, BStr> {
+        normalize(self, LineEnding::Lf)
+    }
+}
+
+impl ToDosNewlines for BStr {
+    fn to_dos_newlines(&self) -> Cow<'_, BStr> {
+        normalize(self, LineEnding::CrLf)
+    }
+}
+
+impl ToUnixNewlines for BString {
+    fn to_unix_newlines(&self) -> Cow<'_, BStr> {
+        normalize(self.as_ref(), LineEnding::Lf)
+    }
+}
+
+impl ToDosNewlines for BString {
+    fn to_dos_newlines(&self) -> Cow<'_, BStr> {
+        normalize(self.as_ref(), LineEnding::CrLf)
+    }
+}
+
+/// Returns `text` normalized to `ending`, borrowed if nothing needs changing.
+pub fn normalize(text: &BStr, ending: LineEnding) -> Cow<'_, BStr> {
+    let mut out = Vec::new();
+    if ending.normalize_bytes_into(text, &mut out) {
+        Cow::Owned(BString::from(out))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bstr::ByteSlice;
+
+    #[test]
+    fn byte_strings_keep_invalid_utf8() {
+        let text = BString::from(&b"\xFF\xFE\r\nx\ry"[..]);
+        assert_eq!(text.to_unix_newlines(), b"\xFF\xFE\nx\ny".as_bstr());
+        assert_eq!(text.to_dos_newlines(), b"\xFF\xFE\r\nx\r\ny".as_bstr());
+        assert!(matches!(b"a\nb".as_bstr().to_unix_newlines(), Cow::Borrowed(_)));
+        assert!(matches!(normalize(b"a\r\n".as_bstr(), LineEnding::CrLf), Cow::Borrowed(_)));
+    }
+}
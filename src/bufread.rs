@@ -0,0 +1,333 @@
+//! Line reading for `BufRead` that understands every newline style.
+
+use std::io::{self, BufRead};
+
+/// Extends [`BufRead`] with line reading that splits on CR, LF and CRLF alike.
+///
+/// `BufRead::read_line` only splits on `\n` and keeps a preceding `\r`; a file using lone CR
+/// endings comes back as a single line. These methods strip the terminator instead.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::UniversalBufReadExt;
+///
+/// let mut input = "one\r\ntwo\rthree\nfour".as_bytes();
+/// let mut line = String::new();
+/// let mut lines = Vec::new();
+/// while input.read_universal_line(&mut line).unwrap() > 0 {
+///     lines.push(std::mem::take(&mut line));
+/// }
+/// assert_eq!(lines, ["one", "two", "three", "four"]);
+/// ```
+pub trait UniversalBufReadExt: BufRead {
+    /// Reads bytes up to the next CR, LF or CRLF and appends them to `buf`, without the terminator.
+    ///
+    /// - Returns the number of bytes consumed, terminator included; `0` means end of input.
+    /// - After a CR, the reader is asked for one more byte to see whether an LF follows.
+    fn read_universal_line_bytes(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let mut total = 0;
+
+        loop {
+            let (found_cr, used, done) = {
+                let available = match self.fill_buf() {
+                    Ok(available) => available,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                if available.is_empty() {
+                    return Ok(total);
+                }
+                match crate::search::memchr2(b'\n', b'\r', available) {
+                    Some(found) => {
+                        buf.extend_from_slice(&available[..found]);
+                        (available[found] == b'\r', found + 1, true)
+                    }
+                    None => {
+                        buf.extend_from_slice(available);
+                        (false, available.len(), false)
+                    }
+                }
+            };
+            self.consume(used);
+            total += used;
+
+            if found_cr {
+                // An error here is left for the next call; this line is already complete.
+                loop {
+                    match self.fill_buf() {
+                        Ok(next) => {
+                            if next.first() == Some(&b'\n') {
+                                self.consume(1);
+                                total += 1;
+                            }
+                            break;
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(_) => break,
+                    }
+                }
+            }
+            if done {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Like [`read_universal_line_bytes`](Self::read_universal_line_bytes), appending to a `String`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the line is not valid UTF-8, leaving `buf` untouched.
+    fn read_universal_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = Vec::new();
+        let read = self.read_universal_line_bytes(&mut bytes)?;
+        match String::from_utf8(bytes) {
+            Ok(line) => {
+                buf.push_str(&line);
+                Ok(read)
+            }
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Returns an iterator over the lines of this reader, split on CR, LF and CRLF.
+    fn universal_lines(self) -> UniversalLines<Self>
+    where
+        Self: Sized,
+    {
+        UniversalLines { reader: self }
+    }
+}
+
+impl<B: BufRead + ?Sized> UniversalBufReadExt for B {}
+
+/// Iterator over terminator-stripped lines, created by [`UniversalBufReadExt::universal_lines`].
+#[derive(Debug)]
+pub struct UniversalLines<B> {
+    reader: B,
+}
+
+impl<B: BufRead> Iterator for UniversalLines<B> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_universal_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+pub use self::stream::{into_normalized_lines, NormalizedLines};
+
+#[cfg(feature = "stream")]
+mod stream {
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+
+    use futures_core::Stream;
+
+    use super::*;
+
+    pin_project_lite::pin_project! {
+        /// Stream of terminator-stripped lines, created by [`into_normalized_lines`].
+        #[derive(Debug)]
+        pub struct NormalizedLines<S> {
+            #[pin]
+            inner: S,
+            buf: Vec<u8>,
+            start: usize,
+            searched: usize,
+            after_cr: bool,
+            done: bool,
+        }
+    }
+
+    /// Splits a stream of fallible byte chunks into lines on CR, LF and CRLF alike.
+    ///
+    /// - The async analog of [`UniversalBufReadExt::universal_lines`]; a CRLF split
+    ///   between two chunks is one terminator.
+    /// - The inner stream is only polled when no complete line is buffered.
+    /// - A line that is not valid UTF-8 yields an [`io::ErrorKind::InvalidData`] error
+    ///   and is skipped.
+    ///
+    /// Example:
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::{stream, TryStreamExt};
+    /// use newline_normalizer::into_normalized_lines;
+    ///
+    /// let body = stream::iter([Ok::<_, std::io::Error>("one\r"), Ok("\ntwo\rthree")]);
+    /// let lines: Vec<String> = into_normalized_lines(body).try_collect().await.unwrap();
+    /// assert_eq!(lines, ["one", "two", "three"]);
+    /// # });
+    /// ```
+    pub fn into_normalized_lines<S, T, E>(stream: S) -> NormalizedLines<S>
+    where
+        S: Stream<Item = Result<T, E>>,
+        T: AsRef<[u8]>,
+        E: Into<io::Error>,
+    {
+        NormalizedLines { inner: stream, buf: Vec::new(), start: 0, searched: 0, after_cr: false, done: false }
+    }
+
+    impl<S> NormalizedLines<S> {
+        /// Unwraps this adapter, returning the underlying stream. Buffered input is lost.
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+    }
+
+    fn to_string(line: &[u8]) -> io::Result<String> {
+        String::from_utf8(line.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    impl<S, T, E> Stream for NormalizedLines<S>
+    where
+        S: Stream<Item = Result<T, E>>,
+        T: AsRef<[u8]>,
+        E: Into<io::Error>,
+    {
+        type Item = io::Result<String>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let mut this = self.project();
+            loop {
+                if *this.after_cr && *this.start < this.buf.len() {
+                    *this.after_cr = false;
+                    if this.buf[*this.start] == b'\n' {
+                        *this.start += 1;
+                        *this.searched = (*this.searched).max(*this.start);
+                    }
+                }
+
+                if !*this.after_cr {
+                    if let Some(found) = crate::search::memchr2(b'\n', b'\r', &this.buf[*this.searched..]) {
+                        let end = *this.searched + found;
+                        let line = to_string(&this.buf[*this.start..end]);
+                        *this.start = end + 1;
+                        *this.searched = *this.start;
+                        *this.after_cr = this.buf[end] == b'\r';
+                        return Poll::Ready(Some(line));
+                    }
+                    *this.searched = this.buf.len();
+                }
+
+                if *this.done {
+                    if *this.start == this.buf.len() {
+                        return Poll::Ready(None);
+                    }
+                    let line = to_string(&this.buf[*this.start..]);
+                    *this.start = this.buf.len();
+                    return Poll::Ready(Some(line));
+                }
+
+                match ready!(this.inner.as_mut().poll_next(cx)) {
+                    Some(Ok(chunk)) => {
+                        // Drop consumed lines before growing the buffer.
+                        this.buf.drain(..*this.start);
+                        *this.searched -= *this.start;
+                        *this.start = 0;
+                        this.buf.extend_from_slice(chunk.as_ref());
+                    }
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                    None => *this.done = true,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(input: &[u8], capacity: usize) -> Vec<String> {
+        io::BufReader::with_capacity(capacity, input)
+            .universal_lines()
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn splits_on_every_newline_style() {
+        assert_eq!(lines(b"a\r\nb\rc\n\nd", 64), ["a", "b", "c", "", "d"]);
+    }
+
+    #[test]
+    fn crlf_split_across_buffer_refills() {
+        for capacity in 1..8 {
+            assert_eq!(lines(b"ab\r\ncd\r\r\n", capacity), ["ab", "cd", ""]);
+        }
+    }
+
+    #[test]
+    fn counts_terminators_as_consumed() {
+        let mut input = &b"ab\r\ncd"[..];
+        let mut line = String::new();
+        assert_eq!(input.read_universal_line(&mut line).unwrap(), 4);
+        assert_eq!(input.read_universal_line(&mut line).unwrap(), 2);
+        assert_eq!(input.read_universal_line(&mut line).unwrap(), 0);
+        assert_eq!(line, "abcd");
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        let mut input = &b"\xff\n"[..];
+        let mut line = String::new();
+        let err = input.read_universal_line(&mut line).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(line.is_empty());
+    }
+
+    #[cfg(feature = "stream")]
+    fn stream_lines(chunks: &[&str]) -> Vec<String> {
+        use futures::{executor::block_on, stream, TryStreamExt};
+
+        let body = stream::iter(chunks.iter().map(|chunk| Ok::<_, io::Error>(*chunk)));
+        block_on(into_normalized_lines(body).try_collect()).unwrap()
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_crlf_split_across_chunks() {
+        assert_eq!(stream_lines(&["a\r", "\nb\r", "\r", "\n", "c"]), ["a", "b", "", "c"]);
+        assert_eq!(stream_lines(&["a\r", "", "\n"]), ["a"]);
+        assert_eq!(stream_lines(&["a\n\n", "b"]), ["a", "", "b"]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_skips_invalid_lines() {
+        use futures::{executor::block_on, stream, StreamExt};
+
+        let body = stream::iter([Ok::<_, io::Error>(&b"\xff\nok"[..])]);
+        let lines: Vec<io::Result<String>> = block_on(into_normalized_lines(body).collect());
+        assert_eq!(lines[0].as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(lines[1].as_ref().unwrap(), "ok");
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_polls_inner_only_when_needed() {
+        use futures::{executor::block_on, stream, StreamExt};
+        use std::cell::Cell;
+
+        let polled = Cell::new(0);
+        let body = stream::iter(["a\nb\nc\n", "d\n"]).map(|chunk| {
+            polled.set(polled.get() + 1);
+            Ok::<_, io::Error>(chunk)
+        });
+        let mut lines = into_normalized_lines(body);
+        block_on(async {
+            for expected in ["a", "b", "c"] {
+                assert_eq!(lines.next().await.unwrap().unwrap(), expected);
+            }
+            assert_eq!(polled.get(), 1);
+            assert_eq!(lines.next().await.unwrap().unwrap(), "d");
+            assert!(lines.next().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,151 @@
+//! [`Bytes`] in and out, enabled by the `bytes` feature.
+//!
+//! Input that is already normalized comes back as a clone of the same `Bytes`, sharing its
+//! buffer, so services passing `Bytes` along only pay for the bodies that change. A
+//! [`BytesMut`] is normalized within its own buffer.
+
+pub use ::bytes::{Bytes, BytesMut};
+
+use alloc::vec::Vec;
+
+use crate::search::{memchr, memchr2};
+use crate::LineEnding;
+
+/// Returns `bytes` normalized to `ending`: a clone of `bytes` when nothing needs changing,
+/// a new buffer otherwise.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{bytes::{self, Bytes}, LineEnding};
+///
+/// let body = Bytes::from_static(b"a\nb\n");
+/// assert_eq!(bytes::normalize(&body, LineEnding::Lf).as_ptr(), body.as_ptr());
+/// assert_eq!(bytes::normalize(&body, LineEnding::CrLf), "a\r\nb\r\n");
+/// ```
+pub fn normalize(bytes: &Bytes, ending: LineEnding) -> Bytes {
+    let mut out = Vec::new();
+    if ending.normalize_bytes_into(bytes, &mut out) {
+        Bytes::from(out)
+    } else {
+        bytes.clone()
+    }
+}
+
+/// Returns `bytes` with Unix newlines (`\n`), sharing its buffer when nothing changes.
+pub fn to_unix(bytes: &Bytes) -> Bytes {
+    normalize(bytes, LineEnding::Lf)
+}
+
+/// Returns `bytes` with DOS newlines (`\r\n`), sharing its buffer when nothing changes.
+pub fn to_dos(bytes: &Bytes) -> Bytes {
+    normalize(bytes, LineEnding::CrLf)
+}
+
+/// Normalizes `buf` to `ending` within its own buffer, returning whether anything changed.
+///
+/// Converting to LF only ever shrinks the contents. Converting to CRLF grows them by one
+/// byte per LF or CR on its own, reserved once before the contents move back to front.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{bytes::{self, BytesMut}, LineEnding};
+///
+/// let mut buf = BytesMut::from(&b"a\r\nb\rc"[..]);
+/// assert!(bytes::normalize_in_place(&mut buf, LineEnding::Lf));
+/// assert_eq!(buf, "a\nb\nc");
+/// ```
+pub fn normalize_in_place(buf: &mut BytesMut, ending: LineEnding) -> bool {
+    match ending {
+        LineEnding::Lf => unix_in_place(buf),
+        LineEnding::CrLf => dos_in_place(buf),
+    }
+}
+
+fn unix_in_place(buf: &mut BytesMut) -> bool {
+    let len = buf.len();
+    let mut read = 0;
+    let mut write = 0;
+    while let Some(offset) = memchr(b'\r', &buf[read..]) {
+        let cr = read + offset;
+        buf.copy_within(read..cr, write);
+        write += cr - read;
+        buf[write] = b'\n';
+        write += 1;
+        read = cr + 1;
+        if buf.get(read) == Some(&b'\n') {
+            read += 1;
+        }
+    }
+    if read == 0 {
+        return false;
+    }
+    buf.copy_within(read..len, write);
+    buf.truncate(write + len - read);
+    true
+}
+
+fn dos_in_place(buf: &mut BytesMut) -> bool {
+    let len = buf.len();
+    let mut extra = 0;
+    let mut pos = 0;
+    while let Some(offset) = memchr2(b'\n', b'\r', &buf[pos..]) {
+        let found = pos + offset;
+        match buf[found] {
+            b'\r' if buf.get(found + 1) == Some(&b'\n') => pos = found + 2,
+            _ => {
+                extra += 1;
+                pos = found + 1;
+            }
+        }
+    }
+    if extra == 0 {
+        return false;
+    }
+
+    buf.resize(len + extra, 0);
+    let mut read = len;
+    let mut write = len + extra;
+    // Everything before the first lone newline is already in place once `write` meets `read`.
+    while write > read {
+        let Some(found) = buf[..read].iter().rposition(|&b| b == b'\n' || b == b'\r') else {
+            break;
+        };
+        let tail = read - found - 1;
+        buf.copy_within(found + 1..read, write - tail);
+        write -= tail + 2;
+        read = match found {
+            1.. if buf[found] == b'\n' && buf[found - 1] == b'\r' => found - 1,
+            _ => found,
+        };
+        buf[write..write + 2].copy_from_slice(b"\r\n");
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_bytes_share_their_buffer() {
+        let body = Bytes::from_static(b"a\r\nb\r\n");
+        assert_eq!(to_dos(&body).as_ptr(), body.as_ptr());
+        assert_eq!(to_unix(&body), "a\nb\n");
+        assert_eq!(to_dos(&Bytes::from_static(b"a\rb\n")), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn in_place_matches_the_copying_normalizers() {
+        let inputs: [&[u8]; 9] =
+            [b"", b"plain", b"\r", b"\n", b"\r\n\r\n", b"a\nb\rc\r\nd", b"\n\n\r\r", b"x\r\r\n\ny", b"\r\nend\n"];
+        for input in inputs {
+            for ending in [LineEnding::Lf, LineEnding::CrLf] {
+                let expected = normalize(&Bytes::copy_from_slice(input), ending);
+                let mut buf = BytesMut::from(input);
+                let changed = normalize_in_place(&mut buf, ending);
+                assert_eq!(buf, expected, "{input:?} to {ending:?}");
+                assert_eq!(changed, expected != input);
+            }
+        }
+    }
+}
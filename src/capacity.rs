@@ -0,0 +1,95 @@
+//! Allocation policy for owned results.
+
+/// How much memory an owned, normalized result is allocated with.
+///
+/// Only affects results that had to be copied; borrowed results allocate nothing. To reuse
+/// a buffer the caller already owns, append with [`LineEnding::normalize_into`] instead.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{Capacity, LineEnding};
+///
+/// let text = LineEnding::Lf.normalize_with_capacity("a\r\nb", Capacity::Extra(64));
+/// let mut text = text.into_owned();
+/// let capacity = text.capacity();
+/// text.push_str("\nmore lines appended without reallocating");
+/// assert_eq!(text.capacity(), capacity);
+/// ```
+///
+/// [`LineEnding::normalize_into`]: crate::LineEnding::normalize_into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capacity {
+    /// Exactly the result's length, which may cost a reallocation to shrink the buffer.
+    Exact,
+    /// At least `factor` times the result's length. Factors below 1 count as 1.
+    Factor(f64),
+    /// Room for at least this many more bytes after the result.
+    Extra(usize),
+}
+
+impl Capacity {
+    /// The capacity wanted for a result of `len` bytes.
+    pub(crate) fn for_len(self, len: usize) -> usize {
+        match self {
+            Capacity::Exact => len,
+            // The float-to-int cast saturates, and maps NaN to 0.
+            Capacity::Factor(factor) => ((len as f64 * factor) as usize).max(len),
+            Capacity::Extra(extra) => len.saturating_add(extra),
+        }
+    }
+
+    /// Resizes `out`, which holds a finished result, to this policy.
+    pub(crate) fn apply<T>(self, out: &mut alloc::vec::Vec<T>) {
+        let wanted = self.for_len(out.len());
+        if self == Capacity::Exact {
+            out.shrink_to_fit();
+        } else if out.capacity() < wanted {
+            out.reserve_exact(wanted - out.len());
+        }
+    }
+}
+
+/// Grows the result by no more than the normalization itself needs.
+impl Default for Capacity {
+    fn default() -> Self {
+        Capacity::Extra(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEnding;
+
+    #[test]
+    fn wanted_capacity() {
+        assert_eq!(Capacity::Exact.for_len(10), 10);
+        assert_eq!(Capacity::Factor(1.5).for_len(10), 15);
+        assert_eq!(Capacity::Factor(0.5).for_len(10), 10);
+        assert_eq!(Capacity::Factor(f64::NAN).for_len(10), 10);
+        assert_eq!(Capacity::Extra(usize::MAX).for_len(10), usize::MAX);
+    }
+
+    #[test]
+    fn owned_results_follow_the_policy() {
+        let input = "line\r\n".repeat(100);
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let input = input.replace("\r\n", if ending == LineEnding::Lf { "\r" } else { "\n" });
+            let exact = ending.normalize_with_capacity(&input, Capacity::Exact).into_owned();
+            assert_eq!(exact.capacity(), exact.len());
+
+            let doubled = ending.normalize_with_capacity(&input, Capacity::Factor(2.0)).into_owned();
+            assert!(doubled.capacity() >= 2 * doubled.len());
+
+            let extra = ending.normalize_with_capacity(&input, Capacity::Extra(1000)).into_owned();
+            assert!(extra.capacity() >= extra.len() + 1000);
+            assert_eq!((exact.as_str(), doubled.as_str()), (extra.as_str(), extra.as_str()));
+        }
+    }
+
+    #[test]
+    fn borrowed_results_ignore_the_policy() {
+        let normalized = LineEnding::Lf.normalize_with_capacity("a\nb", Capacity::Extra(100));
+        assert!(matches!(normalized, alloc::borrow::Cow::Borrowed("a\nb")));
+    }
+}
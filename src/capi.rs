@@ -0,0 +1,95 @@
+//! A C interface, enabled by the `capi` feature, for linking the normalizer into C and C++
+//! programs as a shared or static library. `include/newline_normalizer.h` declares it.
+//!
+//! Build the library with `cargo rustc --release --lib --features capi --crate-type cdylib`,
+//! or `staticlib`. The functions keep their names and signatures across releases.
+//!
+//! Every function works like `snprintf`: it returns the length of the whole result and
+//! writes the result to `out` only if it fits in `out_cap` bytes, so calling it with no
+//! buffer first asks for the size. Nothing is NUL-terminated.
+
+use alloc::vec::Vec;
+use core::ffi::c_char;
+
+use crate::kernel;
+
+/// Writes the Unix form of the `len` bytes at `input`, every CRLF and lone CR turned into
+/// an LF, to `out`, and returns its length, which is at most `len`.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, and `out` to `out_cap` writable ones; either
+/// may be null when its length is 0. The two may overlap, so `nn_to_unix(text, len, text,
+/// len)` converts in place.
+#[no_mangle]
+pub unsafe extern "C" fn nn_to_unix(input: *const c_char, len: usize, out: *mut c_char, out_cap: usize) -> usize {
+    convert(input, len, out, out_cap, kernel::to_unix_into)
+}
+
+/// Writes the DOS form of the `len` bytes at `input`, every LF and lone CR turned into a
+/// CRLF, to `out`, and returns its length, which is at most [`nn_to_dos_max_len`] of `len`.
+///
+/// # Safety
+///
+/// As for [`nn_to_unix`].
+#[no_mangle]
+pub unsafe extern "C" fn nn_to_dos(input: *const c_char, len: usize, out: *mut c_char, out_cap: usize) -> usize {
+    convert(input, len, out, out_cap, kernel::to_dos_into)
+}
+
+/// The longest [`nn_to_dos`] can make `len` bytes, for sizing a buffer up front.
+#[no_mangle]
+pub extern "C" fn nn_to_dos_max_len(len: usize) -> usize {
+    len.saturating_mul(2)
+}
+
+/// The version of the library, as a NUL-terminated string such as `"0.1.6"`.
+#[no_mangle]
+pub extern "C" fn nn_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Converts with `into`, which appends to the vector or returns `false` if nothing changes.
+unsafe fn convert(input: *const c_char, len: usize, out: *mut c_char, out_cap: usize, into: fn(&[u8], &mut Vec<u8>) -> bool) -> usize {
+    let src = if len == 0 { &[][..] } else { core::slice::from_raw_parts(input.cast::<u8>(), len) };
+    let mut converted = Vec::new();
+    // An unchanged result is copied from `input` itself, which `out` may overlap.
+    let (result, result_len) = if into(src, &mut converted) { (converted.as_ptr(), converted.len()) } else { (input.cast::<u8>(), len) };
+    if result_len <= out_cap && result_len > 0 {
+        core::ptr::copy(result, out.cast::<u8>(), result_len);
+    }
+    result_len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(f: unsafe extern "C" fn(*const c_char, usize, *mut c_char, usize) -> usize, input: &[u8], cap: usize) -> (usize, Vec<u8>) {
+        let mut out = alloc::vec![b'#'; cap];
+        let len = unsafe { f(input.as_ptr().cast(), input.len(), out.as_mut_ptr().cast(), cap) };
+        (len, out)
+    }
+
+    #[test]
+    fn results_fit_or_are_measured() {
+        assert_eq!(call(nn_to_unix, b"a\r\nb\rc", 5), (5, b"a\nb\nc".to_vec()));
+        assert_eq!(call(nn_to_dos, b"a\nb", 8), (4, b"a\r\nb####".to_vec()));
+        assert_eq!(call(nn_to_dos, b"a\nb", 3), (4, b"###".to_vec()));
+        assert_eq!(call(nn_to_unix, b"unchanged\n", 10), (10, b"unchanged\n".to_vec()));
+        assert_eq!(unsafe { nn_to_dos(core::ptr::null(), 0, core::ptr::null_mut(), 0) }, 0);
+        assert_eq!(unsafe { nn_to_dos(b"\n\n".as_ptr().cast(), 2, core::ptr::null_mut(), 0) }, nn_to_dos_max_len(2));
+    }
+
+    #[test]
+    fn converts_in_place() {
+        let mut text = b"one\r\ntwo\r\n".repeat(20);
+        let len = text.len();
+        let ptr = text.as_mut_ptr().cast::<c_char>();
+        let new_len = unsafe { nn_to_unix(ptr, len, ptr, len) };
+        assert_eq!(&text[..new_len], &b"one\ntwo\n".repeat(20)[..]);
+
+        let version = unsafe { core::ffi::CStr::from_ptr(nn_version()) };
+        assert_eq!(version.to_str(), Ok(env!("CARGO_PKG_VERSION")));
+    }
+}
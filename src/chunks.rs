@@ -0,0 +1,205 @@
+//! Normalization of chunked byte streams, such as HTTP bodies.
+
+use alloc::vec::Vec;
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats};
+
+/// Iterator adapter normalizing a sequence of fallible byte chunks.
+///
+/// Created by [`normalize_chunks`]. Chunks that normalize to nothing, such as the LF
+/// completing a CRLF that ended the previous chunk, are skipped. Errors are passed through.
+#[derive(Debug)]
+pub struct NormalizedChunks<I> {
+    inner: I,
+    engine: Engine,
+}
+
+/// Normalizes every chunk of `chunks`, carrying CRLF pairs across chunk boundaries.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_chunks, LineEnding};
+///
+/// let body = vec![Ok::<_, std::io::Error>(b"a\r".to_vec()), Ok(b"\nb\n".to_vec())];
+/// let chunks: Vec<Vec<u8>> = normalize_chunks(body, LineEnding::CrLf).collect::<Result<_, _>>().unwrap();
+/// assert_eq!(chunks.concat(), b"a\r\nb\r\n");
+/// ```
+pub fn normalize_chunks<I, T, E>(chunks: I, ending: LineEnding) -> NormalizedChunks<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<T, E>>,
+    T: AsRef<[u8]>,
+{
+    NormalizedChunks { inner: chunks.into_iter(), engine: Engine::new(ending) }
+}
+
+impl<I> NormalizedChunks<I> {
+    /// Counters for everything normalized so far.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// Unwraps this adapter, returning the underlying iterator.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I, T, E> Iterator for NormalizedChunks<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+    T: AsRef<[u8]>,
+{
+    type Item = Result<Vec<u8>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let chunk = match self.inner.next()? {
+                Ok(chunk) => chunk,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut out = Vec::new();
+            self.engine.normalize_into(chunk.as_ref(), &mut out);
+            if !out.is_empty() {
+                return Some(Ok(out));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+pub use self::stream::NormalizedStream;
+
+#[cfg(feature = "stream")]
+mod stream {
+    use alloc::borrow::Cow;
+    use core::pin::Pin;
+    use core::task::{ready, Context, Poll};
+
+    use bytes::Bytes;
+    use futures_core::Stream;
+
+    use super::*;
+
+    pin_project_lite::pin_project! {
+        /// Stream adapter normalizing fallible byte chunks into [`Bytes`], with backpressure
+        /// from the inner stream.
+        ///
+        /// Chunks that need no change are passed on without copying when they already are `Bytes`.
+        ///
+        /// Example:
+        /// ```
+        /// # futures::executor::block_on(async {
+        /// use bytes::Bytes;
+        /// use futures::{stream, TryStreamExt};
+        /// use newline_normalizer::{LineEnding, NormalizedStream};
+        ///
+        /// let body = stream::iter([Ok::<_, std::io::Error>(Bytes::from("a\r")), Ok(Bytes::from("\nb"))]);
+        /// let chunks: Vec<Bytes> = NormalizedStream::new(body, LineEnding::Lf).try_collect().await.unwrap();
+        /// assert_eq!(chunks.concat(), b"a\nb");
+        /// # });
+        /// ```
+        #[derive(Debug)]
+        pub struct NormalizedStream<S> {
+            #[pin]
+            inner: S,
+            engine: Engine,
+        }
+    }
+
+    impl<S> NormalizedStream<S> {
+        /// Wraps `inner`, normalizing to `ending`.
+        pub fn new(inner: S, ending: LineEnding) -> Self {
+            NormalizedStream { inner, engine: Engine::new(ending) }
+        }
+
+        /// Counters for everything normalized so far.
+        pub fn stats(&self) -> Stats {
+            self.engine.stats()
+        }
+
+        /// Unwraps this adapter, returning the underlying stream.
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+    }
+
+    impl<S, T, E> Stream for NormalizedStream<S>
+    where
+        S: Stream<Item = Result<T, E>>,
+        T: AsRef<[u8]> + Into<Bytes>,
+    {
+        type Item = Result<Bytes, E>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let mut this = self.project();
+            loop {
+                let chunk = match ready!(this.inner.as_mut().poll_next(cx)) {
+                    None => return Poll::Ready(None),
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    Some(Ok(chunk)) => chunk,
+                };
+                let out = this.engine.normalize_chunk(chunk.as_ref());
+                if out.is_empty() {
+                    continue;
+                }
+                return Poll::Ready(Some(Ok(match out {
+                    // Reuse the chunk itself when it passed through byte for byte.
+                    Cow::Borrowed(_) => chunk.into(),
+                    Cow::Owned(out) => Bytes::from(out),
+                })));
+            }
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (0, self.inner.size_hint().1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_chunks_that_normalize_to_nothing() {
+        let body = vec![Ok::<_, ()>("a\r"), Ok("\n"), Ok("b\r\n")];
+        let chunks: Vec<Vec<u8>> = normalize_chunks(body, LineEnding::Lf).collect::<Result<_, _>>().unwrap();
+        assert_eq!(chunks, [b"a\n".to_vec(), b"b\n".to_vec()]);
+    }
+
+    #[test]
+    fn passes_errors_through() {
+        let body = vec![Ok(b"a\n".to_vec()), Err("boom"), Ok(b"b".to_vec())];
+        let mut chunks = normalize_chunks(body, LineEnding::CrLf);
+        assert_eq!(chunks.next(), Some(Ok(b"a\r\n".to_vec())));
+        assert_eq!(chunks.next(), Some(Err("boom")));
+        assert_eq!(chunks.next(), Some(Ok(b"b".to_vec())));
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.stats().converted, 1);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_reuses_unchanged_bytes() {
+        use bytes::Bytes;
+        use futures::{executor::block_on, stream, TryStreamExt};
+
+        let original = Bytes::from_static(b"already\nunix\n");
+        let body = stream::iter([Ok::<_, ()>(original.clone()), Ok(Bytes::from_static(b"x\r"))]);
+        let chunks: Vec<Bytes> = block_on(NormalizedStream::new(body, LineEnding::Lf).try_collect()).unwrap();
+        assert_eq!(chunks[0].as_ptr(), original.as_ptr());
+        assert_eq!(chunks[1], "x\n");
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_rewrites_chunk_completing_a_crlf() {
+        use bytes::Bytes;
+        use futures::{executor::block_on, stream, TryStreamExt};
+
+        let body = stream::iter([Ok::<_, ()>(Bytes::from_static(b"x\r")), Ok(Bytes::from_static(b"\nab\n"))]);
+        let chunks: Vec<Bytes> = block_on(NormalizedStream::new(body, LineEnding::CrLf).try_collect()).unwrap();
+        assert_eq!(chunks.concat(), b"x\r\nab\r\n");
+    }
+}
@@ -0,0 +1,143 @@
+//! Argument parsing glue for [`clap`](::clap), enabled by the `clap` feature.
+//!
+//! [`LineEnding`] gets a value parser of its own, so an `--eol lf|crlf` option needs no
+//! more than its type, and [`NormalizedText`] normalizes multi-line string arguments, read
+//! from a file when given as `@path`.
+//!
+//! ```
+//! use clap::Parser;
+//! use newline_normalizer::{clap::NormalizedText, LineEnding};
+//!
+//! #[derive(Parser)]
+//! struct Cli {
+//!     #[arg(long, default_value = "lf")]
+//!     eol: LineEnding,
+//!     #[arg(long, value_parser = NormalizedText::new(LineEnding::Lf).from_files())]
+//!     message: String,
+//! }
+//!
+//! let cli = Cli::parse_from(["app", "--eol", "crlf", "--message", "one\r\ntwo"]);
+//! assert_eq!(cli.eol, LineEnding::CrLf);
+//! assert_eq!(cli.message, "one\ntwo");
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use std::ffi::OsStr;
+
+use ::clap::builder::{PossibleValue, PossibleValuesParser, StringValueParser, TypedValueParser, ValueParserFactory};
+use ::clap::error::ErrorKind;
+use ::clap::{Arg, Command, Error};
+
+use crate::text::normalize_owned;
+use crate::LineEnding;
+
+/// Parses `lf` (or `unix`) and `crlf` (or `dos`) into a [`LineEnding`], listing both in help
+/// and shell completions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineEndingParser;
+
+impl LineEndingParser {
+    fn choices() -> [PossibleValue; 2] {
+        [
+            PossibleValue::new("lf").alias("unix").help("Unix line endings (\\n)"),
+            PossibleValue::new("crlf").alias("dos").help("DOS line endings (\\r\\n)"),
+        ]
+    }
+}
+
+impl TypedValueParser for LineEndingParser {
+    type Value = LineEnding;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<LineEnding, Error> {
+        let name = PossibleValuesParser::new(Self::choices()).parse_ref(cmd, arg, value)?;
+        // Aliases come back as given, so match on what the name resolves to.
+        let lf = Self::choices()[0].matches(&name, arg.map_or(false, Arg::is_ignore_case_set));
+        Ok(if lf { LineEnding::Lf } else { LineEnding::CrLf })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(Self::choices().into_iter()))
+    }
+}
+
+impl ValueParserFactory for LineEnding {
+    type Parser = LineEndingParser;
+
+    fn value_parser() -> LineEndingParser {
+        LineEndingParser
+    }
+}
+
+/// Parses a string argument with its line endings normalized.
+///
+/// With [`from_files`](Self::from_files), an argument `@path` is replaced by the contents of
+/// that file and `@@text` stands for the literal `@text`.
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizedText {
+    ending: LineEnding,
+    from_files: bool,
+}
+
+impl NormalizedText {
+    /// Normalizes arguments to `ending`.
+    pub fn new(ending: LineEnding) -> Self {
+        NormalizedText { ending, from_files: false }
+    }
+
+    /// Reads arguments starting with `@` from the file they name.
+    pub fn from_files(self) -> Self {
+        NormalizedText { from_files: true, ..self }
+    }
+}
+
+impl TypedValueParser for NormalizedText {
+    type Value = String;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<String, Error> {
+        let text = StringValueParser::new().parse_ref(cmd, arg, value)?;
+        let text = match text.strip_prefix('@') {
+            Some(literal) if self.from_files && literal.starts_with('@') => String::from(literal),
+            Some(path) if self.from_files => std::fs::read_to_string(path).map_err(|e| {
+                let arg = arg.map_or_else(|| String::from("..."), |arg| arg.to_string());
+                cmd.clone().error(ErrorKind::Io, format!("cannot read '{path}' for '{arg}': {e}"))
+            })?,
+            _ => text,
+        };
+        Ok(normalize_owned(text, self.ending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("app")
+            .arg(Arg::new("eol").long("eol").value_parser(LineEnding::value_parser()))
+            .arg(Arg::new("body").long("body").value_parser(NormalizedText::new(LineEnding::CrLf).from_files()))
+    }
+
+    #[test]
+    fn line_endings_by_name() {
+        let eol = |name: &str| command().try_get_matches_from(["app", "--eol", name]).map(|m| m.get_one::<LineEnding>("eol").copied());
+        assert_eq!(eol("lf").unwrap(), Some(LineEnding::Lf));
+        assert_eq!(eol("dos").unwrap(), Some(LineEnding::CrLf));
+        assert_eq!(eol("cr").unwrap_err().kind(), ErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn text_from_arguments_and_files() {
+        let path = std::env::temp_dir().join(format!("newline_normalizer-clap-{}", std::process::id()));
+        std::fs::write(&path, "from\nfile\n").unwrap();
+        let body = |value: &str| {
+            command().try_get_matches_from(["app", "--body", value]).map(|m| m.get_one::<String>("body").cloned().unwrap())
+        };
+        assert_eq!(body("a\nb").unwrap(), "a\r\nb");
+        assert_eq!(body(&format!("@{}", path.display())).unwrap(), "from\r\nfile\r\n");
+        assert_eq!(body("@@handle\n").unwrap(), "@handle\r\n");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(body(&format!("@{}", path.display())).unwrap_err().kind(), ErrorKind::Io);
+    }
+}
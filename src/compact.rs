@@ -0,0 +1,99 @@
+//! Small-string output, enabled by the `compact_str` feature.
+//!
+//! A [`CompactString`] stores up to 24 bytes inline, so normalizing short text such as log
+//! lines or chat messages allocates nothing at all.
+
+pub use compact_str::CompactString;
+
+use crate::LineEnding;
+
+/// Inputs up to this length are normalized straight into the string, a line at a time.
+const INLINE: usize = core::mem::size_of::<CompactString>();
+
+/// Returns `text` normalized to `ending`, stored inline when it is short enough.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{compact, LineEnding};
+///
+/// let line = compact::normalize("hello\r\nworld\r", LineEnding::Lf);
+/// assert_eq!(line, "hello\nworld\n");
+/// assert!(!line.is_heap_allocated());
+/// ```
+pub fn normalize(text: &str, ending: LineEnding) -> CompactString {
+    if text.len() > INLINE {
+        // Owned results hand over their buffer, borrowed ones are copied once.
+        return CompactString::from(ending.normalize(text));
+    }
+    let mut out = CompactString::const_new("");
+    push_normalized(&mut out, text, ending);
+    out
+}
+
+/// Returns `text` with Unix newlines (`\n`), stored inline when it is short enough.
+pub fn to_unix(text: &str) -> CompactString {
+    normalize(text, LineEnding::Lf)
+}
+
+/// Returns `text` with DOS newlines (`\r\n`), stored inline when it is short enough.
+pub fn to_dos(text: &str) -> CompactString {
+    normalize(text, LineEnding::CrLf)
+}
+
+/// Appends `text`, normalized to `ending`, to `out`.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{compact::{self, CompactString}, LineEnding};
+///
+/// let mut out = CompactString::const_new("> ");
+/// compact::push_normalized(&mut out, "a\nb", LineEnding::CrLf);
+/// assert_eq!(out, "> a\r\nb");
+/// ```
+pub fn push_normalized(out: &mut CompactString, text: &str, ending: LineEnding) {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    for found in crate::search::memchr2_iter(b'\n', b'\r', bytes) {
+        if found < pos {
+            // The LF of a CRLF, already written.
+            continue;
+        }
+        out.push_str(&text[pos..found]);
+        out.push_str(ending.as_str());
+        pos = found + 1;
+        if bytes[found] == b'\r' && bytes.get(pos) == Some(&b'\n') {
+            pos += 1;
+        }
+    }
+    out.push_str(&text[pos..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_stays_inline() {
+        let dos = to_dos("a\nb\rc\r\n");
+        assert_eq!(dos, "a\r\nb\r\nc\r\n");
+        assert!(!dos.is_heap_allocated());
+        assert!(!to_unix("").is_heap_allocated());
+    }
+
+    #[test]
+    fn long_text_matches_the_string_api() {
+        let text = "line\r\n\r".repeat(20);
+        assert_eq!(to_unix(&text), "line\n\n".repeat(20));
+        assert_eq!(to_dos(&text), "line\r\n\r\n".repeat(20));
+    }
+
+    #[test]
+    fn push_handles_every_newline() {
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let text = "\r\nx\r\r\n\ny\r";
+            let mut out = CompactString::const_new("");
+            push_normalized(&mut out, text, ending);
+            assert_eq!(out, ending.normalize(text));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Newline-insensitive comparison and hashing without building the normalized text.
+
+use core::hash::Hasher;
+
+/// Returns `true` if `a` and `b` are equal once both are normalized to LF.
+///
+/// - Neither normalized form is allocated: both texts are walked side by side.
+/// - CRLF, CR and LF all count as the same newline, so this also tells whether the
+///   texts are equal once both are normalized to CRLF.
+/// - Unicode line separators are compared as ordinary characters.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::normalized_eq;
+///
+/// assert!(normalized_eq("one\r\ntwo\rthree", "one\ntwo\nthree"));
+/// assert!(!normalized_eq("one\r\n", "one\n\n"));
+/// ```
+pub fn normalized_eq(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+
+    let mut a = Pieces::new(a.as_bytes());
+    let mut b = Pieces::new(b.as_bytes());
+    let mut x: &[u8] = &[];
+    let mut y: &[u8] = &[];
+    loop {
+        while x.is_empty() {
+            match a.next() {
+                Some(piece) => x = piece,
+                None => break,
+            }
+        }
+        while y.is_empty() {
+            match b.next() {
+                Some(piece) => y = piece,
+                None => break,
+            }
+        }
+        if x.is_empty() || y.is_empty() {
+            return x.is_empty() && y.is_empty();
+        }
+        let len = x.len().min(y.len());
+        if x[..len] != y[..len] {
+            return false;
+        }
+        x = &x[len..];
+        y = &y[len..];
+    }
+}
+
+/// Feeds `state` the LF-normalized form of `text`, without allocating it.
+///
+/// - Texts that are [`normalized_eq`] always hash the same, whatever hasher is used:
+///   the bytes reach `state` in fixed-size blocks, independent of where newlines were.
+/// - The result differs from hashing the normalized `str` itself, so do not mix the two
+///   in one table.
+///
+/// Example:
+/// ```
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::Hasher;
+/// use newline_normalizer::normalized_hash;
+///
+/// let hash = |text| {
+///     let mut hasher = DefaultHasher::new();
+///     normalized_hash(text, &mut hasher);
+///     hasher.finish()
+/// };
+/// assert_eq!(hash("one\r\ntwo\r"), hash("one\ntwo\n"));
+/// assert_ne!(hash("one\r\n"), hash("one\n\n"));
+/// ```
+pub fn normalized_hash<H: Hasher + ?Sized>(text: &str, state: &mut H) {
+    let mut block = [0; HASH_BLOCK];
+    let mut filled = 0;
+    for mut piece in Pieces::new(text.as_bytes()) {
+        if filled > 0 {
+            let take = piece.len().min(HASH_BLOCK - filled);
+            block[filled..filled + take].copy_from_slice(&piece[..take]);
+            filled += take;
+            piece = &piece[take..];
+            if filled < HASH_BLOCK {
+                continue;
+            }
+            state.write(&block);
+        }
+        // Whole blocks go to the hasher straight from the text.
+        let whole = piece.len() - piece.len() % HASH_BLOCK;
+        for chunk in piece[..whole].chunks_exact(HASH_BLOCK) {
+            state.write(chunk);
+        }
+        let tail = &piece[whole..];
+        block[..tail.len()].copy_from_slice(tail);
+        filled = tail.len();
+    }
+    state.write(&block[..filled]);
+    // Ends the text like `str` does, so that hashing several texts in a row stays unambiguous.
+    state.write_u8(0xff);
+}
+
+/// Bytes handed to the hasher per `write` call, except for the last one.
+const HASH_BLOCK: usize = 256;
+
+/// The LF-normalized form of a text, as borrowed pieces that concatenate to it.
+struct Pieces<'a> {
+    rest: &'a [u8],
+    lf: bool,
+}
+
+impl<'a> Pieces<'a> {
+    fn new(text: &'a [u8]) -> Self {
+        Pieces { rest: text, lf: false }
+    }
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if core::mem::replace(&mut self.lf, false) {
+            return Some(b"\n");
+        }
+        if self.rest.is_empty() {
+            return None;
+        }
+        let Some(cr) = crate::search::memchr(b'\r', self.rest) else {
+            return Some(core::mem::take(&mut self.rest));
+        };
+        let piece = &self.rest[..cr];
+        // Dropping the CR of a CRLF leaves its LF at the start of the next piece.
+        self.lf = self.rest.get(cr + 1) != Some(&b'\n');
+        self.rest = &self.rest[cr + 1..];
+        Some(piece)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEnding;
+
+    #[test]
+    fn pieces_concatenate_to_the_unix_form() {
+        for text in ["", "\r", "\r\n", "a\r\r\nb\rc\n", "\r\r", "no newlines", "end\r"] {
+            let joined: Vec<u8> = Pieces::new(text.as_bytes()).flatten().copied().collect();
+            assert_eq!(joined, LineEnding::Lf.normalize(text).as_bytes(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn equality_ignores_the_newline_style() {
+        let unix = "one\ntwo\n\nthree\n";
+        for other in ["one\r\ntwo\r\n\r\nthree\r\n", "one\rtwo\r\rthree\r", "one\r\ntwo\r\r\nthree\n", unix] {
+            assert!(normalized_eq(unix, other), "{other:?}");
+            assert!(normalized_eq(other, unix), "{other:?}");
+        }
+        assert!(normalized_eq("", ""));
+    }
+
+    /// Records the `write` calls instead of hashing, to expose any dependence on how input is split.
+    #[derive(Default)]
+    struct Recorder(Vec<Vec<u8>>);
+
+    impl Hasher for Recorder {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.push(bytes.to_vec());
+        }
+    }
+
+    fn record(text: &str) -> Vec<Vec<u8>> {
+        let mut recorder = Recorder::default();
+        normalized_hash(text, &mut recorder);
+        recorder.0
+    }
+
+    #[test]
+    fn hashing_sees_the_same_writes_for_equal_texts() {
+        let unix = "line\n".repeat(300) + "end";
+        let writes = record(&unix);
+        assert_eq!(writes.concat(), [unix.as_bytes(), &[0xff]].concat());
+        assert!(writes.iter().rev().skip(2).all(|write| write.len() == HASH_BLOCK));
+        for other in [unix.replace('\n', "\r\n"), unix.replace('\n', "\r"), unix.replacen('\n', "\r", 100)] {
+            assert_eq!(record(&other), writes);
+        }
+        assert_ne!(record("a\r\n"), record("a\n\n"));
+        assert_eq!(record(""), [vec![], vec![0xff]]);
+    }
+
+    #[test]
+    fn differences_are_found() {
+        for (a, b) in [("a\r\n", "a\n\n"), ("a\r\r\n", "a\n"), ("a\rb", "a\r\nc"), ("abc", "ab"), ("", "\r"), ("x\r", "x")] {
+            assert!(!normalized_eq(a, b), "{a:?} {b:?}");
+            assert!(!normalized_eq(b, a), "{a:?} {b:?}");
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Memory bounds for the streaming adapters.
+
+/// Controls how much the streaming adapters read, process and buffer at once.
+///
+/// An adapter holds one input chunk plus its normalized form, which is at most twice as
+/// long when converting to CRLF. Memory use is therefore bounded by three times the
+/// chunk size. Larger chunks favor throughput, smaller ones a tight memory budget.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{LineEnding, NormalizingReader, StreamConfig};
+///
+/// let config = StreamConfig::new().max_buffered(64 * 1024);
+/// assert_eq!(config.get_chunk_size(), 21 * 1024 + 341);
+/// let reader = NormalizingReader::with_config(&b"a\r\nb"[..], LineEnding::Lf, config);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    chunk_size: usize,
+}
+
+impl StreamConfig {
+    /// Chunk size used when nothing else is configured.
+    pub const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+    /// Creates a configuration with the default chunk size.
+    pub fn new() -> Self {
+        StreamConfig { chunk_size: Self::DEFAULT_CHUNK_SIZE }
+    }
+
+    /// Sets the number of bytes read or accepted per operation. Values below 1 are raised to 1.
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Picks the largest chunk size that keeps buffered data within `bytes`.
+    pub fn max_buffered(self, bytes: usize) -> Self {
+        self.chunk_size(bytes / 3)
+    }
+
+    /// The configured chunk size.
+    pub fn get_chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Upper bound on the bytes an adapter buffers with this configuration.
+    pub fn get_max_buffered(&self) -> usize {
+        self.chunk_size.saturating_mul(3)
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
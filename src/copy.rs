@@ -0,0 +1,138 @@
+//! One-call streaming from a reader to a writer.
+
+use std::io::{self, Read, Write};
+
+use crate::engine::Engine;
+use crate::trace::{trace_span, trace_stats};
+use crate::{LineEnding, Stats, StreamConfig};
+
+const STDIO_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Copies everything from `reader` to `writer`, normalizing newlines on the way.
+///
+/// Works like [`io::copy`], returning [`Stats`] with the bytes read and written and the
+/// line endings seen and converted. The writer is flushed at the end.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_copy, LineEnding};
+///
+/// let mut out = Vec::new();
+/// let stats = normalize_copy(&mut "a\r\nb\rc".as_bytes(), &mut out, LineEnding::Lf).unwrap();
+/// assert_eq!(out, b"a\nb\nc");
+/// assert_eq!((stats.bytes_read, stats.bytes_written, stats.converted), (6, 5, 2));
+/// ```
+pub fn normalize_copy<R, W>(reader: &mut R, writer: &mut W, ending: LineEnding) -> io::Result<Stats>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    copy_with(reader, writer, ending, StreamConfig::new(), &mut |_| {})
+}
+
+/// Like [`normalize_copy`], with buffering bounded by `config`.
+pub fn normalize_copy_with_config<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    ending: LineEnding,
+    config: StreamConfig,
+) -> io::Result<Stats>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    copy_with(reader, writer, ending, config, &mut |_| {})
+}
+
+/// Normalizes standard input onto standard output until end of input.
+///
+/// Both streams are locked for the duration, stdin is read in large chunks, and each chunk
+/// goes to stdout in one vectored write of its unchanged spans and rewritten line endings,
+/// as in [`normalize_copy_vectored`](crate::normalize_copy_vectored), without being copied
+/// into an output buffer. This is all a filter tool needs besides argument parsing. A
+/// closed pipe on stdout, as with `| head`, is returned as an [`io::ErrorKind::BrokenPipe`]
+/// error.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{filter_stdio, LineEnding};
+///
+/// let stats = filter_stdio(LineEnding::Lf)?;
+/// eprintln!("converted {} line endings", stats.converted);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn filter_stdio(ending: LineEnding) -> io::Result<Stats> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let config = StreamConfig::new().chunk_size(STDIO_CHUNK_SIZE);
+    trace_span!(DEBUG, "filter_stdio", ending = ?ending);
+    let stats = crate::normalize_copy_vectored_with_config(&mut stdin.lock(), &mut stdout.lock(), ending, config)?;
+    trace_stats!(stats, "filtered");
+    Ok(stats)
+}
+
+/// Streams `reader` into `writer`, calling `progress` with the bytes read so far after each chunk.
+pub(crate) fn copy_with<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    ending: LineEnding,
+    config: StreamConfig,
+    progress: &mut dyn FnMut(u64),
+) -> io::Result<Stats>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    trace_span!(DEBUG, "normalize_copy", ending = ?ending);
+    let mut engine = Engine::new(ending);
+    let mut input = vec![0; config.get_chunk_size()];
+    let mut output = Vec::with_capacity(config.get_chunk_size());
+
+    loop {
+        let read = match reader.read(&mut input) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.clear();
+        engine.normalize_into(&input[..read], &mut output);
+        writer.write_all(&output)?;
+        progress(engine.stats().bytes_read);
+    }
+
+    writer.flush()?;
+    trace_stats!(engine.stats(), "copied");
+    Ok(engine.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_and_counts() {
+        let mut out = Vec::new();
+        let stats = normalize_copy(&mut &b"a\nb\r\nc\r"[..], &mut out, LineEnding::CrLf).unwrap();
+        assert_eq!(out, b"a\r\nb\r\nc\r\n");
+        assert_eq!((stats.lf, stats.crlf, stats.cr, stats.converted), (1, 1, 1, 2));
+        assert_eq!((stats.bytes_read, stats.bytes_written), (7, 9));
+    }
+
+    #[test]
+    fn small_chunks_keep_crlf_pairs() {
+        let config = StreamConfig::new().chunk_size(1);
+        let mut out = Vec::new();
+        let stats = normalize_copy_with_config(&mut &b"a\r\n\r\r\n"[..], &mut out, LineEnding::Lf, config).unwrap();
+        assert_eq!(out, b"a\n\n\n");
+        assert_eq!((stats.crlf, stats.cr), (2, 1));
+    }
+
+    #[test]
+    fn works_with_trait_objects() {
+        let mut reader: Box<dyn Read> = Box::new(&b"x\ry"[..]);
+        let mut writer: Box<dyn Write> = Box::new(io::sink());
+        let stats = normalize_copy(&mut reader, &mut writer, LineEnding::Lf).unwrap();
+        assert_eq!(stats.bytes_written, 3);
+    }
+}
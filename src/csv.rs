@@ -0,0 +1,78 @@
+//! Normalization of CSV record terminators, leaving newlines inside quoted fields alone.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::search::memchr3_iter;
+use crate::LineEnding;
+
+/// Normalizes the newlines of `text` that lie outside double-quoted fields to `ending`.
+///
+/// Every `"` opens or closes a quoted field, so the doubled `""` of an escaped quote leaves
+/// the field open, as in any well-formed CSV file.
+pub(crate) fn normalize(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    let mut out: Option<Vec<u8>> = None;
+    let mut copied = 0;
+    let mut skip_to = 0;
+    let mut quoted = false;
+
+    for found in memchr3_iter(b'"', b'\r', b'\n', bytes) {
+        if found < skip_to {
+            continue;
+        }
+        let (len, kept) = match bytes[found] {
+            b'"' => {
+                quoted = !quoted;
+                continue;
+            }
+            _ if quoted => continue,
+            b'\n' => (1, ending == LineEnding::Lf),
+            b'\r' if bytes.get(found + 1) == Some(&b'\n') => (2, ending == LineEnding::CrLf),
+            _ => (1, false),
+        };
+        skip_to = found + len;
+        if kept {
+            continue;
+        }
+        let out = out.get_or_insert_with(|| Vec::with_capacity(text.len() + text.len() / 8));
+        out.extend_from_slice(&bytes[copied..found]);
+        out.extend_from_slice(ending.as_bytes());
+        copied = found + len;
+    }
+
+    match out {
+        Some(mut out) => {
+            out.extend_from_slice(&bytes[copied..]);
+            // Only ASCII newlines were replaced, so the result is still UTF-8.
+            Cow::Owned(unsafe { String::from_utf8_unchecked(out) })
+        }
+        None => Cow::Borrowed(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_newlines_are_kept() {
+        let text = "name,note\r\n\"Ann\",\"line one\r\nline two\"\r\nBob,\"say \"\"hi\"\"\nthere\"\n";
+        assert_eq!(
+            normalize(text, LineEnding::Lf),
+            "name,note\n\"Ann\",\"line one\r\nline two\"\nBob,\"say \"\"hi\"\"\nthere\"\n"
+        );
+        assert_eq!(
+            normalize(text, LineEnding::CrLf),
+            "name,note\r\n\"Ann\",\"line one\r\nline two\"\r\nBob,\"say \"\"hi\"\"\nthere\"\r\n"
+        );
+    }
+
+    #[test]
+    fn normalized_records_are_borrowed() {
+        assert!(matches!(normalize("a,\"b\nc\"\n", LineEnding::Lf), Cow::Borrowed(_)));
+        assert!(matches!(normalize("a,\"b\nc\"\r\n", LineEnding::CrLf), Cow::Borrowed(_)));
+        assert_eq!(normalize("a\rb,\"\r\"\r", LineEnding::Lf), "a\nb,\"\r\"\n");
+    }
+}
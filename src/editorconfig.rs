@@ -0,0 +1,179 @@
+//! Looking up the line ending that `.editorconfig` files ask for.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::glob::path_matches;
+use crate::LineEnding;
+
+/// Looks up the line ending that `.editorconfig` files set for `path`, through `end_of_line`.
+///
+/// Returns `None` if no file sets one. Use [`EditorConfig`] to look up many paths in one tree.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{editorconfig_line_ending, LineEnding};
+///
+/// let ending = editorconfig_line_ending("src/main.rs")?.unwrap_or(LineEnding::Lf);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn editorconfig_line_ending<P: AsRef<Path>>(path: P) -> io::Result<Option<LineEnding>> {
+    EditorConfig::new().line_ending(path)
+}
+
+/// Finds the line ending `.editorconfig` files set for each path, reading every file once.
+///
+/// - Follows the EditorConfig rules: the `.editorconfig` closest to a path wins, later
+///   sections win within a file, and the search upward stops at a file with `root = true`.
+/// - Section globs support `*`, `**`, `?`, `[a-z]`, `[!a-z]` and `{a,b}`; numeric ranges
+///   such as `{1..3}` are not supported.
+/// - `end_of_line = cr` is not an ending this crate produces, so it counts as unset, like
+///   `unset` itself.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{EditorConfig, LineEnding};
+///
+/// let mut config = EditorConfig::new();
+/// for path in ["build.bat", "build.sh"] {
+///     let ending = config.line_ending(path)?.unwrap_or(LineEnding::Lf);
+///     println!("{path}: {ending:?}");
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EditorConfig {
+    dirs: HashMap<PathBuf, Option<Config>>,
+}
+
+impl EditorConfig {
+    /// Creates a lookup that has not read any `.editorconfig` yet.
+    pub fn new() -> Self {
+        EditorConfig::default()
+    }
+
+    /// The line ending set for `path`, if any. Relative paths start from the current directory.
+    pub fn line_ending<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Option<LineEnding>> {
+        let path = path.as_ref();
+        let path = if path.is_absolute() { path.to_path_buf() } else { std::env::current_dir()?.join(path) };
+
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if let Some(config) = self.load(current)? {
+                let relative: Vec<_> = path.strip_prefix(current).unwrap_or(&path).iter().map(|part| part.to_string_lossy()).collect();
+                if let Some(ending) = config.end_of_line(&relative.join("/")) {
+                    return Ok(ending);
+                }
+                if config.root {
+                    break;
+                }
+            }
+            dir = current.parent();
+        }
+        Ok(None)
+    }
+
+    fn load(&mut self, dir: &Path) -> io::Result<Option<&Config>> {
+        if !self.dirs.contains_key(dir) {
+            let config = match fs::read_to_string(dir.join(".editorconfig")) {
+                Ok(text) => Some(Config::parse(&text)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            self.dirs.insert(dir.to_path_buf(), config);
+        }
+        Ok(self.dirs[dir].as_ref())
+    }
+}
+
+/// The parts of one `.editorconfig` that matter here.
+#[derive(Debug, Clone)]
+struct Config {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    glob: String,
+    /// `Some(None)` if the section unsets the line ending.
+    end_of_line: Option<Option<LineEnding>>,
+}
+
+impl Config {
+    fn parse(text: &str) -> Config {
+        let mut config = Config { root: false, sections: Vec::new() };
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                config.sections.push(Section { glob: glob.to_string(), end_of_line: None });
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim().to_ascii_lowercase());
+            match config.sections.last_mut() {
+                None if key == "root" => config.root = value == "true",
+                Some(section) if key == "end_of_line" => match value.as_str() {
+                    "lf" => section.end_of_line = Some(Some(LineEnding::Lf)),
+                    "crlf" => section.end_of_line = Some(Some(LineEnding::CrLf)),
+                    "cr" | "unset" => section.end_of_line = Some(None),
+                    // Unknown values are ignored, as the specification asks.
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// The setting of the last section matching `relative`, a `/`-separated path below this file.
+    fn end_of_line(&self, relative: &str) -> Option<Option<LineEnding>> {
+        self.sections.iter().rev().filter(|section| section.end_of_line.is_some()).find_map(|section| {
+            path_matches(&section.glob, relative).then_some(section.end_of_line).flatten()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn sections_override_in_order() {
+        let config = Config::parse(
+            "root = true\n\n[*]\nend_of_line = lf\n\n; Windows scripts\n[*.{bat,cmd}]\nEnd_Of_Line = CRLF\n\n[legacy/**]\nend_of_line = unset\n[*.md]\nend_of_line = bogus\n",
+        );
+        assert!(config.root);
+        assert_eq!(config.end_of_line("src/main.rs"), Some(Some(LineEnding::Lf)));
+        assert_eq!(config.end_of_line("tools/build.BAT"), Some(Some(LineEnding::Lf)));
+        assert_eq!(config.end_of_line("tools/build.bat"), Some(Some(LineEnding::CrLf)));
+        assert_eq!(config.end_of_line("legacy/old.bat"), Some(None));
+        assert_eq!(config.end_of_line("README.md"), Some(Some(LineEnding::Lf)));
+        assert_eq!(Config::parse("[*.rs]\nindent_size = 4\n").end_of_line("a.rs"), None);
+    }
+
+    #[test]
+    fn closer_files_win_until_the_root() {
+        let dir = std::env::temp_dir().join(format!("newline_normalizer-editorconfig-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("project/windows")).unwrap();
+        fs::write(dir.join(".editorconfig"), "[*]\nend_of_line = crlf\n").unwrap();
+        fs::write(dir.join("project/.editorconfig"), "root = true\n[*.txt]\nend_of_line = lf\n").unwrap();
+        fs::write(dir.join("project/windows/.editorconfig"), "[*.txt]\nend_of_line = crlf\n").unwrap();
+
+        let mut config = EditorConfig::new();
+        assert_eq!(config.line_ending(dir.join("project/a.txt")).unwrap(), Some(LineEnding::Lf));
+        assert_eq!(config.line_ending(dir.join("project/windows/b.txt")).unwrap(), Some(LineEnding::CrLf));
+        assert_eq!(config.line_ending(dir.join("project/c.rs")).unwrap(), None);
+        assert_eq!(config.line_ending(dir.join("d.rs")).unwrap(), Some(LineEnding::CrLf));
+        assert_eq!(editorconfig_line_ending(dir.join("project/windows/e.txt")).unwrap(), Some(LineEnding::CrLf));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! Normalization as a minimal edit script instead of a rewritten copy.
+
+use alloc::vec::Vec;
+
+use crate::search::memchr2_iter;
+use crate::LineEnding;
+
+/// One change of an [`edit_script`], at a byte offset of the original text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edit {
+    /// Insert `byte` before the byte at `at`: a CR before a bare LF, or an LF after a lone CR.
+    Insert { at: usize, byte: u8 },
+    /// Delete the CR at `at`, which starts a CRLF.
+    Delete { at: usize },
+    /// Replace the lone CR at `at` with an LF.
+    Replace { at: usize, byte: u8 },
+}
+
+impl Edit {
+    /// The byte offset in the original text the edit applies at.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Edit::Insert { at, .. } | Edit::Delete { at } | Edit::Replace { at, .. } => at,
+        }
+    }
+}
+
+/// Returns the fewest single-byte edits that normalize `text` to `ending`, in order.
+///
+/// Only CR and LF bytes are inserted, deleted or replaced, so version control tools can
+/// apply the change in place and keep blame and diffs of the untouched bytes. An empty
+/// script means `text` is already normalized.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{apply_edits, edit_script, Edit, LineEnding};
+///
+/// let edits = edit_script(b"a\r\nb\rc\n", LineEnding::Lf);
+/// assert_eq!(edits, [Edit::Delete { at: 1 }, Edit::Replace { at: 4, byte: b'\n' }]);
+/// assert_eq!(apply_edits(b"a\r\nb\rc\n", &edits), b"a\nb\nc\n");
+/// ```
+pub fn edit_script(text: &[u8], ending: LineEnding) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    for found in memchr2_iter(b'\n', b'\r', text) {
+        let cr_before = found > 0 && text[found - 1] == b'\r';
+        let lf_after = text.get(found + 1) == Some(&b'\n');
+        let edit = match (ending, text[found]) {
+            (LineEnding::Lf, b'\r') if lf_after => Edit::Delete { at: found },
+            (LineEnding::Lf, b'\r') => Edit::Replace { at: found, byte: b'\n' },
+            (LineEnding::CrLf, b'\n') if !cr_before => Edit::Insert { at: found, byte: b'\r' },
+            (LineEnding::CrLf, b'\r') if !lf_after => Edit::Insert { at: found + 1, byte: b'\n' },
+            _ => continue,
+        };
+        edits.push(edit);
+    }
+    edits
+}
+
+/// Applies `edits`, as returned by [`edit_script`] for `text`, returning the edited copy.
+///
+/// # Panics
+///
+/// If the edits are not in offset order or point past the end of `text`.
+pub fn apply_edits(text: &[u8], edits: &[Edit]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + edits.len());
+    let mut copied = 0;
+    for edit in edits {
+        let at = edit.offset();
+        out.extend_from_slice(&text[copied..at]);
+        copied = at;
+        match *edit {
+            Edit::Insert { byte, .. } => out.push(byte),
+            Edit::Delete { .. } => copied += 1,
+            Edit::Replace { byte, .. } => {
+                out.push(byte);
+                copied += 1;
+            }
+        }
+    }
+    out.extend_from_slice(&text[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_touch_only_wrong_endings() {
+        let text = b"a\nb\r\nc\rd\r";
+        assert_eq!(
+            edit_script(text, LineEnding::CrLf),
+            [Edit::Insert { at: 1, byte: b'\r' }, Edit::Insert { at: 7, byte: b'\n' }, Edit::Insert { at: 9, byte: b'\n' }]
+        );
+        assert_eq!(edit_script(text, LineEnding::Lf).len(), 3);
+        assert!(edit_script(b"a\r\nb\r\n", LineEnding::CrLf).is_empty());
+    }
+
+    #[test]
+    fn applying_matches_normalize() {
+        for text in ["", "\r", "\n\r\r\n\n", "x\r\r\ny\n\rz", "日本\r語\n"] {
+            for ending in [LineEnding::Lf, LineEnding::CrLf] {
+                let edits = edit_script(text.as_bytes(), ending);
+                assert_eq!(apply_edits(text.as_bytes(), &edits), ending.normalize(text).as_bytes());
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Decoding, normalizing and re-encoding in one streaming pass, enabled by the `encoding_rs`
+//! feature.
+//!
+//! [`Transcoder`] reads text in any encoding `encoding_rs` knows, taken from a byte order
+//! mark or given up front, normalizes its line endings and writes it as UTF-8 or another
+//! encoding. Malformed input and characters the target cannot hold are replaced rather than
+//! failing the copy, and reported in the [`TranscodeReport`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::io::{self, Read, Write};
+
+pub use encoding_rs::Encoding;
+use encoding_rs::{CoderResult, Decoder, Encoder, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::engine::Engine;
+use crate::{BomPolicy, LineEnding, Stats};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Settings for [`Transcoder::transcode`].
+///
+/// Example:
+/// ```
+/// use newline_normalizer::encoding::{Encoding, Transcoder};
+/// use newline_normalizer::LineEnding;
+///
+/// let latin1 = Encoding::for_label(b"windows-1252").unwrap();
+/// let mut out = Vec::new();
+/// let report = Transcoder::new(LineEnding::Lf).source(latin1).transcode(&b"caf\xE9\r\n"[..], &mut out)?;
+/// assert_eq!(out, "café\n".as_bytes());
+/// assert_eq!(report.source, latin1);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Transcoder {
+    ending: LineEnding,
+    source: &'static Encoding,
+    target: &'static Encoding,
+    bom: BomPolicy,
+}
+
+/// What [`Transcoder::transcode`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscodeReport {
+    /// The encoding the input was decoded from.
+    pub source: &'static Encoding,
+    /// Whether the input started with a byte order mark.
+    pub had_bom: bool,
+    /// Newline counters, counted over the decoded UTF-8 text.
+    pub stats: Stats,
+    /// Malformed input was replaced with U+FFFD.
+    pub malformed: bool,
+    /// Characters the target encoding has no room for were written as HTML character
+    /// references, as `encoding_rs` does.
+    pub unmappable: bool,
+}
+
+/// Transcodes `reader` into UTF-8 `writer`, normalized to `ending`; see [`Transcoder`].
+pub fn transcode<R: Read, W: Write>(reader: R, writer: W, ending: LineEnding) -> io::Result<TranscodeReport> {
+    Transcoder::new(ending).transcode(reader, writer)
+}
+
+impl Transcoder {
+    /// Normalizes to `ending`, decoding UTF-8 unless the input starts with another byte
+    /// order mark, and writes UTF-8.
+    pub fn new(ending: LineEnding) -> Self {
+        Transcoder { ending, source: UTF_8, target: UTF_8, bom: BomPolicy::Strip }
+    }
+
+    /// Decodes input without a byte order mark from `encoding`. A byte order mark still
+    /// wins, as when browsers decode a page.
+    pub fn source(self, encoding: &'static Encoding) -> Self {
+        Transcoder { source: encoding, ..self }
+    }
+
+    /// Writes `encoding`. UTF-16 is written directly; for the other encodings that
+    /// `encoding_rs` only decodes, its output encoding is written instead.
+    pub fn target(self, encoding: &'static Encoding) -> Self {
+        let target = if encoding == UTF_16LE || encoding == UTF_16BE { encoding } else { encoding.output_encoding() };
+        Transcoder { target, ..self }
+    }
+
+    /// Whether to write a byte order mark for the target: [`BomPolicy::Keep`] writes one if
+    /// the input had one, [`BomPolicy::Add`] always does, and [`BomPolicy::Strip`], the
+    /// default, never does. Only UTF-8 and UTF-16 have one.
+    pub fn bom(self, bom: BomPolicy) -> Self {
+        Transcoder { bom, ..self }
+    }
+
+    /// Copies `reader` into `writer`, decoding, normalizing and encoding as it goes.
+    pub fn transcode<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> io::Result<TranscodeReport> {
+        let mut input = vec![0; BUFFER_SIZE];
+        let mut filled = 0;
+        while filled < 3 {
+            match reader.read(&mut input[filled..3]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let (source, bom_len) = Encoding::for_bom(&input[..filled]).unwrap_or((self.source, 0));
+        let had_bom = bom_len > 0;
+
+        let mut pass = Pass {
+            decoder: source.new_decoder_without_bom_handling(),
+            engine: Engine::new(self.ending),
+            encoder: self.encoder(),
+            utf16_big_endian: self.utf16(),
+            decoded: String::new(),
+            normalized: Vec::new(),
+            out: Vec::new(),
+            malformed: false,
+            unmappable: false,
+        };
+        let write_bom = match self.bom {
+            BomPolicy::Keep => had_bom,
+            BomPolicy::Add => true,
+            BomPolicy::Strip => false,
+        };
+        if write_bom {
+            writer.write_all(self.bom_bytes())?;
+        }
+
+        let mut start = bom_len;
+        loop {
+            pass.run(&input[start..filled], false, &mut writer)?;
+            start = 0;
+            filled = match reader.read(&mut input) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => 0,
+                Err(e) => return Err(e),
+            };
+        }
+        pass.run(&[], true, &mut writer)?;
+        writer.flush()?;
+
+        let Pass { engine, malformed, unmappable, .. } = pass;
+        Ok(TranscodeReport { source, had_bom, stats: engine.stats(), malformed, unmappable })
+    }
+
+    /// An encoder, or `None` for UTF-8 and UTF-16, which are written by hand.
+    fn encoder(&self) -> Option<Encoder> {
+        match self.target {
+            target if target == UTF_8 || self.utf16().is_some() => None,
+            target => Some(target.new_encoder()),
+        }
+    }
+
+    /// For UTF-16 targets, whether they are big-endian.
+    fn utf16(&self) -> Option<bool> {
+        match self.target {
+            target if target == UTF_16LE => Some(false),
+            target if target == UTF_16BE => Some(true),
+            _ => None,
+        }
+    }
+
+    fn bom_bytes(&self) -> &'static [u8] {
+        match self.target {
+            target if target == UTF_8 => b"\xEF\xBB\xBF",
+            target if target == UTF_16LE => b"\xFF\xFE",
+            target if target == UTF_16BE => b"\xFE\xFF",
+            _ => b"",
+        }
+    }
+}
+
+/// The buffers and coders of one [`Transcoder::transcode`] call.
+struct Pass {
+    decoder: Decoder,
+    engine: Engine,
+    encoder: Option<Encoder>,
+    utf16_big_endian: Option<bool>,
+    decoded: String,
+    normalized: Vec<u8>,
+    out: Vec<u8>,
+    malformed: bool,
+    unmappable: bool,
+}
+
+impl Pass {
+    /// Decodes, normalizes and encodes `input`, writing the result.
+    fn run<W: Write>(&mut self, mut input: &[u8], last: bool, writer: &mut W) -> io::Result<()> {
+        self.decoded.clear();
+        loop {
+            let needed = self.decoder.max_utf8_buffer_length(input.len()).unwrap_or(BUFFER_SIZE);
+            self.decoded.reserve(needed);
+            let (result, read, replaced) = self.decoder.decode_to_string(input, &mut self.decoded, last);
+            self.malformed |= replaced;
+            input = &input[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+
+        self.normalized.clear();
+        self.engine.normalize_into(self.decoded.as_bytes(), &mut self.normalized);
+        // Only ASCII newline bytes are rewritten, so the text stays UTF-8.
+        let text = unsafe { core::str::from_utf8_unchecked(&self.normalized) };
+
+        let bytes: &[u8] = match (&mut self.encoder, self.utf16_big_endian) {
+            (None, None) => text.as_bytes(),
+            (None, Some(big_endian)) => {
+                self.out.clear();
+                for unit in text.encode_utf16() {
+                    let bytes = if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() };
+                    self.out.extend_from_slice(&bytes);
+                }
+                &self.out
+            }
+            (Some(encoder), _) => {
+                self.out.clear();
+                let mut text = text;
+                loop {
+                    let needed = encoder.max_buffer_length_from_utf8_if_no_unmappables(text.len()).unwrap_or(BUFFER_SIZE);
+                    self.out.reserve(needed.max(16));
+                    let (result, read, unmappable) = encoder.encode_from_utf8_to_vec(text, &mut self.out, last);
+                    self.unmappable |= unmappable;
+                    text = &text[read..];
+                    if result == CoderResult::InputEmpty {
+                        break;
+                    }
+                }
+                &self.out
+            }
+        };
+        writer.write_all(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    fn run(transcoder: Transcoder, input: &[u8]) -> (Vec<u8>, TranscodeReport) {
+        let mut out = Vec::new();
+        let report = transcoder.transcode(input, &mut out).unwrap();
+        (out, report)
+    }
+
+    #[test]
+    fn byte_order_marks_pick_the_source() {
+        let (out, report) = run(Transcoder::new(LineEnding::CrLf).source(WINDOWS_1252), b"\xFF\xFEa\0\n\0\xE9\0");
+        assert_eq!(out, "a\r\né".as_bytes());
+        assert_eq!((report.source, report.had_bom, report.stats.converted), (UTF_16LE, true, 1));
+
+        let (out, report) = run(Transcoder::new(LineEnding::Lf).bom(BomPolicy::Keep), b"\xEF\xBB\xBFx\r\ny\xFF");
+        assert_eq!(out, "\u{feff}x\ny\u{fffd}".as_bytes());
+        assert!(report.malformed);
+    }
+
+    #[test]
+    fn encodes_the_target() {
+        let (out, _) = run(Transcoder::new(LineEnding::CrLf).target(UTF_16BE).bom(BomPolicy::Add), "é\n".as_bytes());
+        assert_eq!(out, b"\xFE\xFF\0\xE9\0\r\0\n");
+
+        let (out, report) = run(Transcoder::new(LineEnding::Lf).target(SHIFT_JIS), "日本\r\n€".as_bytes());
+        assert_eq!(out, [&b"\x93\xFA\x96\x7B\n"[..], b"&#8364;"].concat());
+        assert!(report.unmappable);
+    }
+}
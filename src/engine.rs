@@ -0,0 +1,126 @@
+//! Buffer-owning normalization shared by the streaming adapters.
+
+#[cfg(any(feature = "stream", feature = "tower"))]
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use crate::machine::{Machine, Status};
+use crate::{LineEnding, Stats};
+
+/// Rewrites newlines chunk by chunk into a growable buffer.
+///
+/// A thin layer over [`Machine`] that sizes the output so every chunk is consumed whole.
+#[derive(Debug, Clone)]
+pub(crate) struct Engine {
+    machine: Machine,
+}
+
+impl Engine {
+    pub(crate) fn new(ending: LineEnding) -> Self {
+        Engine { machine: Machine::new(ending) }
+    }
+
+    pub(crate) fn ending(&self) -> LineEnding {
+        self.machine.ending()
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        self.machine.stats()
+    }
+
+    /// Appends the normalized form of `input` to `out`.
+    pub(crate) fn normalize_into(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        if input.is_empty() {
+            return;
+        }
+
+        // Every input byte becomes at most one full ending.
+        let start = out.len();
+        out.resize(start + input.len() * self.ending().as_bytes().len(), 0);
+        let feed = self.machine.feed(input, &mut out[start..]);
+        debug_assert_eq!(feed.status, Status::NeedsInput);
+        out.truncate(start + feed.written);
+    }
+
+    /// Normalizes one chunk of a stream, borrowing `input` when the output is the same bytes,
+    /// so adapters can pass the chunk itself on.
+    ///
+    /// A chunk already in the target form is only scanned, so it costs no allocation. Others
+    /// are normalized and then compared: the conversion counters alone cannot tell, as a CRLF
+    /// completed across chunks and an LF converted in the same chunk leave both the count and
+    /// the length as they were.
+    #[cfg(any(feature = "stream", feature = "tower"))]
+    pub(crate) fn normalize_chunk<'a>(&mut self, input: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.machine.pass_normalized(input) {
+            return Cow::Borrowed(input);
+        }
+        let mut out = Vec::new();
+        self.normalize_into(input, &mut out);
+        if out == input {
+            Cow::Borrowed(input)
+        } else {
+            Cow::Owned(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(ending: LineEnding, pieces: &[&[u8]]) -> (Vec<u8>, Stats) {
+        let mut engine = Engine::new(ending);
+        let mut out = Vec::new();
+        for piece in pieces {
+            engine.normalize_into(piece, &mut out);
+        }
+        (out, engine.stats())
+    }
+
+    #[test]
+    fn counts_crlf_split_across_chunks_once() {
+        let (out, stats) = run(LineEnding::CrLf, &[b"a\r", b"\nb\n"]);
+        assert_eq!(out, b"a\r\nb\r\n");
+        assert_eq!((stats.lf, stats.crlf, stats.cr, stats.converted), (1, 1, 0, 1));
+        assert_eq!((stats.bytes_read, stats.bytes_written), (5, 6));
+    }
+
+    #[cfg(any(feature = "stream", feature = "tower"))]
+    #[test]
+    fn chunks_are_reused_only_when_equal() {
+        let mut engine = Engine::new(LineEnding::CrLf);
+        assert_eq!(engine.normalize_chunk(b"x\r"), &b"x\r\n"[..]);
+        // Drops the LF completing the CRLF and converts another: same count and length,
+        // different bytes.
+        let out = engine.normalize_chunk(b"\nab\n");
+        assert!(matches!(out, Cow::Owned(_)));
+        assert_eq!(out, &b"ab\r\n"[..]);
+        assert!(matches!(engine.normalize_chunk(b"c\r\n"), Cow::Borrowed(_)));
+    }
+
+    #[cfg(any(feature = "stream", feature = "tower"))]
+    #[test]
+    fn chunks_count_as_normalize_into_does() {
+        let cases: [(LineEnding, &[&[u8]]); 4] = [
+            (LineEnding::Lf, &[b"a\nb\n", b"c\r", b"\nd\n", b"e"]),
+            (LineEnding::CrLf, &[b"a\r\n", b"b\r", b"\nc\r\n", b"\n", b"d\r\n"]),
+            (LineEnding::CrLf, &[b"a\r", b"\r\n", b""]),
+            (LineEnding::Lf, &[b"", b"\r", b"a\r"]),
+        ];
+        for (ending, pieces) in cases {
+            let mut engine = Engine::new(ending);
+            let out: Vec<u8> = pieces.iter().flat_map(|piece| engine.normalize_chunk(piece).into_owned()).collect();
+            let (expected, stats) = run(ending, pieces);
+            assert_eq!(out, expected);
+            assert_eq!(engine.stats(), stats);
+        }
+    }
+
+    #[test]
+    fn counts_conversions_for_unix() {
+        let (out, stats) = run(LineEnding::Lf, &[b"a\r\nb\rc\n"]);
+        assert_eq!(out, b"a\nb\nc\n");
+        assert_eq!(stats.converted, 2);
+        assert!(stats.changed());
+    }
+}
@@ -0,0 +1,144 @@
+//! Request extractors for axum and actix-web, enabled by the `axum` and `actix-web`
+//! features, normalizing a body for the routes that ask for it.
+//!
+//! [`NormalizedText<T>`] reads the whole body, normalizes its line endings and hands the
+//! result to the extractor `T`, such as `String`, `Bytes` or a form of plain text. It is the
+//! per-route counterpart of the [`tower`](crate::tower) middleware. Like it, only raw newline
+//! bytes change: in JSON and URL-encoded bodies line breaks inside strings are escaped, so
+//! give those fields the type [`UnixText`](crate::UnixText) or [`DosText`](crate::DosText)
+//! instead, with the `serde` feature.
+
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use ::bytes::Bytes;
+
+use crate::{Ending, Lf};
+
+/// Extracts `T` from the request body with its line endings normalized to LF, or with
+/// [`CrLf`](crate::CrLf) as `E` to CRLF.
+///
+/// The body counts against the framework's body size limit as it would for `Bytes`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizedText<T, E = Lf>(pub T, pub PhantomData<E>);
+
+impl<T, E> NormalizedText<T, E> {
+    /// Hands over the extracted value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, E> Deref for NormalizedText<T, E> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Example:
+/// ```
+/// use axum::{routing::post, Router};
+/// use newline_normalizer::extract::NormalizedText;
+/// use newline_normalizer::CrLf;
+///
+/// async fn upload(NormalizedText(notes, _): NormalizedText<String>) -> String {
+///     notes
+/// }
+///
+/// async fn export(NormalizedText(report, _): NormalizedText<String, CrLf>) -> String {
+///     report
+/// }
+///
+/// let app: Router = Router::new().route("/upload", post(upload)).route("/export", post(export));
+/// ```
+#[cfg(feature = "axum")]
+impl<S, T, E> ::axum::extract::FromRequest<S> for NormalizedText<T, E>
+where
+    S: Send + Sync,
+    T: ::axum::extract::FromRequest<S>,
+    E: Ending,
+{
+    type Rejection = ::axum::response::Response;
+
+    async fn from_request(request: ::axum::extract::Request, state: &S) -> Result<Self, Self::Rejection> {
+        use ::axum::response::IntoResponse;
+
+        let (mut parts, body) = request.into_parts();
+        let request = ::axum::extract::Request::from_parts(parts.clone(), body);
+        let body = Bytes::from_request(request, state).await.map_err(IntoResponse::into_response)?;
+        let body = crate::bytes::normalize(&body, E::ENDING);
+        parts.headers.insert(::axum::http::header::CONTENT_LENGTH, body.len().into());
+        let request = ::axum::extract::Request::from_parts(parts, ::axum::body::Body::from(body));
+        let value = T::from_request(request, state).await.map_err(IntoResponse::into_response)?;
+        Ok(NormalizedText(value, PhantomData))
+    }
+}
+
+/// actix-web shares the request with the handler and has no way to change its headers, so `T`
+/// still sees the `Content-Length` of the body as sent. Its own extractors only check that
+/// against their size limits, as reading the body for normalizing already did.
+#[cfg(feature = "actix-web")]
+impl<T, E> ::actix_web::FromRequest for NormalizedText<T, E>
+where
+    T: ::actix_web::FromRequest + 'static,
+    E: Ending,
+{
+    type Error = ::actix_web::Error;
+    type Future = core::pin::Pin<alloc::boxed::Box<dyn core::future::Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &::actix_web::HttpRequest, payload: &mut ::actix_web::dev::Payload) -> Self::Future {
+        let body = Bytes::from_request(request, payload);
+        let request = request.clone();
+        alloc::boxed::Box::pin(async move {
+            let body = crate::bytes::normalize(&body.await?, E::ENDING);
+            let mut payload = ::actix_web::dev::Payload::from(body);
+            let value = T::from_request(&request, &mut payload).await.map_err(Into::into)?;
+            Ok(NormalizedText(value, PhantomData))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(feature = "axum", feature = "actix-web"))]
+    use crate::CrLf;
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn axum_handlers_get_normalized_bodies() {
+        use ::axum::body::Body;
+        use ::axum::routing::post;
+        use http_body_util::BodyExt;
+        use tower::ServiceExt;
+
+        async fn echo(NormalizedText(body, _): NormalizedText<String, CrLf>) -> String {
+            body
+        }
+        let app = ::axum::Router::new().route("/", post(echo));
+        let request = ::axum::http::Request::post("/").header("content-length", "4").body(Body::from("a\nb\n")).unwrap();
+        let body = futures::executor::block_on(async {
+            let response = app.oneshot(request).await.unwrap();
+            response.into_body().collect().await.unwrap().to_bytes()
+        });
+        assert_eq!(body, "a\r\nb\r\n");
+    }
+
+    #[cfg(feature = "actix-web")]
+    #[test]
+    fn actix_extractors_get_normalized_bodies() {
+        use ::actix_web::FromRequest;
+
+        let (request, mut payload) = ::actix_web::test::TestRequest::default().set_payload("a\r\nb\r").to_http_parts();
+        let text = futures::executor::block_on(NormalizedText::<String>::from_request(&request, &mut payload)).unwrap();
+        assert_eq!(text.into_inner(), "a\nb\n");
+
+        // A body grown past the length it was sent with.
+        let (request, mut payload) = ::actix_web::test::TestRequest::default().set_payload("a\nb\n").to_http_parts();
+        assert_eq!(request.headers().get(::actix_web::http::header::CONTENT_LENGTH).unwrap(), "4");
+        let text = futures::executor::block_on(NormalizedText::<Bytes, CrLf>::from_request(&request, &mut payload)).unwrap();
+        assert_eq!(text.into_inner(), "a\r\nb\r\n");
+    }
+}
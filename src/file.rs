@@ -0,0 +1,346 @@
+//! Normalizing files on disk.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::copy::copy_with;
+use crate::engine::Engine;
+use crate::trace::{trace_span, trace_stats};
+use crate::{BomPolicy, BomReader, LineEnding, Stats, StreamConfig};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Outcome of [`normalize_file`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileReport {
+    /// Counters for the streamed content.
+    pub stats: Stats,
+    /// Whether the file was replaced. Files that need no change are left untouched.
+    pub changed: bool,
+}
+
+/// Normalizes the newlines of a file, atomically replacing it with the result.
+///
+/// - The file is streamed into a temporary file in the same directory, which is then
+///   renamed over the original, so readers never observe a half-written file.
+/// - Permissions of the original are preserved.
+/// - If no newline needs rewriting, the temporary file is discarded and the original,
+///   including its modification time, stays as it was.
+/// - A symbolic link is followed: the file it points to is replaced and the link stays.
+/// - A file with several hard links is split: the replaced path gets a new file, while the
+///   other links keep the old contents.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{normalize_file, LineEnding};
+///
+/// let report = normalize_file("notes.txt", LineEnding::Lf)?;
+/// println!("rewrote {} line endings", report.stats.converted);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn normalize_file<P: AsRef<Path>>(path: P, ending: LineEnding) -> io::Result<FileReport> {
+    normalize_file_with_progress(path, ending, |_, _| {})
+}
+
+/// Like [`normalize_file`], calling `progress(bytes_processed, bytes_total)` after every chunk.
+///
+/// `bytes_total` is the file size when it was opened.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{normalize_file_with_progress, LineEnding};
+///
+/// normalize_file_with_progress("export.csv", LineEnding::Lf, |done, total| {
+///     eprint!("\r{:.1}%", done as f64 * 100.0 / total.max(1) as f64);
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn normalize_file_with_progress<P, F>(path: P, ending: LineEnding, mut progress: F) -> io::Result<FileReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(u64, u64),
+{
+    rewrite(path.as_ref(), ending, BomPolicy::Keep, &mut progress)
+}
+
+/// Like [`normalize_file`], also adding or removing a UTF-8 byte order mark as `bom` says.
+///
+/// A file whose only change is its byte order mark counts as changed. The counters describe
+/// the text after the byte order mark was handled.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{normalize_file_with_bom, BomPolicy, LineEnding};
+///
+/// normalize_file_with_bom("script.sh", LineEnding::Lf, BomPolicy::Strip)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn normalize_file_with_bom<P: AsRef<Path>>(path: P, ending: LineEnding, bom: BomPolicy) -> io::Result<FileReport> {
+    rewrite(path.as_ref(), ending, bom, &mut |_, _| {})
+}
+
+fn rewrite(path: &Path, ending: LineEnding, bom: BomPolicy, progress: &mut dyn FnMut(u64, u64)) -> io::Result<FileReport> {
+    trace_span!(DEBUG, "normalize_file", path = %path.display(), ending = ?ending);
+    let path = &*resolve_symlink(path)?;
+    let mut source = BomReader::new(File::open(path)?, bom);
+    let metadata = source.get_ref().metadata()?;
+    let total = metadata.len();
+
+    let (temp_path, mut temp) = create_temp_file(path)?;
+    let result = (|| {
+        let config = StreamConfig::new().chunk_size(BUFFER_SIZE);
+        let stats = copy_with(&mut source, &mut temp, ending, config, &mut |done| progress(done, total))?;
+        if !stats.changed() && !source.changed() {
+            trace_stats!(stats, "left file as it was");
+            return Ok(FileReport { stats, changed: false });
+        }
+        temp.sync_all()?;
+        fs::set_permissions(&temp_path, metadata.permissions())?;
+        drop(source);
+        fs::rename(&temp_path, path)?;
+        trace_stats!(stats, "replaced file");
+        Ok(FileReport { stats, changed: true })
+    })();
+
+    if !matches!(result, Ok(FileReport { changed: true, .. })) {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Converts a file to Unix newlines by rewriting it in place, without a temporary file.
+///
+/// - Converting to LF never grows the content, so the file is compacted front to back
+///   and then truncated to its new length. No extra disk space is needed.
+/// - Unlike [`normalize_file`], this is not atomic: if the process dies midway, the file
+///   is left partially converted.
+/// - Bytes are only written from the first rewritten newline onward.
+///
+/// Example:
+/// ```no_run
+/// let report = newline_normalizer::normalize_file_to_unix_in_place("export.csv")?;
+/// println!("saved {} bytes", report.stats.bytes_read - report.stats.bytes_written);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn normalize_file_to_unix_in_place<P: AsRef<Path>>(path: P) -> io::Result<FileReport> {
+    normalize_file_to_unix_in_place_with_progress(path, |_, _| {})
+}
+
+/// Like [`normalize_file_to_unix_in_place`], calling `progress(bytes_processed, bytes_total)` after every chunk.
+pub fn normalize_file_to_unix_in_place_with_progress<P, F>(path: P, mut progress: F) -> io::Result<FileReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(u64, u64),
+{
+    let path = path.as_ref();
+    trace_span!(DEBUG, "normalize_file_in_place", path = %path.display());
+    let mut file = File::options().read(true).write(true).open(path)?;
+    let total = file.metadata()?.len();
+    let mut engine = Engine::new(LineEnding::Lf);
+    let mut input = vec![0; BUFFER_SIZE];
+    let mut output = Vec::with_capacity(BUFFER_SIZE);
+    let mut read_pos = 0;
+    let mut write_pos = 0;
+
+    loop {
+        file.seek(SeekFrom::Start(read_pos))?;
+        let read = match file.read(&mut input) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.clear();
+        engine.normalize_into(&input[..read], &mut output);
+
+        // The output never outgrows the input, so this cannot overwrite unread bytes.
+        if write_pos != read_pos || output != input[..read] {
+            file.seek(SeekFrom::Start(write_pos))?;
+            file.write_all(&output)?;
+        }
+        read_pos += read as u64;
+        write_pos += output.len() as u64;
+        progress(read_pos, total);
+    }
+
+    if write_pos != read_pos {
+        file.set_len(write_pos)?;
+    }
+    file.flush()?;
+
+    let stats = engine.stats();
+    trace_stats!(stats, "rewrote file in place");
+    Ok(FileReport { stats, changed: stats.changed() })
+}
+
+/// The file behind `path`, following a symbolic link: renaming over the link would
+/// replace the link, not the file it points to.
+pub(crate) fn resolve_symlink(path: &Path) -> io::Result<Cow<'_, Path>> {
+    Ok(if fs::symlink_metadata(path)?.file_type().is_symlink() { Cow::Owned(fs::canonicalize(path)?) } else { Cow::Borrowed(path) })
+}
+
+/// Creates a uniquely named file next to `path`, so the final rename stays on one filesystem.
+pub(crate) fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+
+    loop {
+        let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = dir.join(format!(".{}.{}-{}.nlnorm.tmp", name, std::process::id(), unique));
+        match File::options().write(true).create_new(true).open(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("newline_normalizer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rewrites_file_and_leaves_no_temp_files() {
+        let dir = temp_dir("rewrite");
+        let path = dir.join("input.txt");
+        fs::write(&path, "a\r\nb\rc").unwrap();
+
+        let report = normalize_file(&path, LineEnding::Lf).unwrap();
+        assert!(report.changed);
+        assert_eq!(report.stats.converted, 2);
+        assert_eq!(fs::read(&path).unwrap(), b"a\nb\nc");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_normalized_file_untouched() {
+        let dir = temp_dir("untouched");
+        let path = dir.join("input.txt");
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+
+        let report = normalize_file(&path, LineEnding::CrLf).unwrap();
+        assert!(!report.changed);
+        assert_eq!(report.stats.crlf, 2);
+        assert_eq!(fs::read(&path).unwrap(), b"a\r\nb\r\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("permissions");
+        let path = dir.join("script.sh");
+        fs::write(&path, "echo\r\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o751)).unwrap();
+
+        normalize_file(&path, LineEnding::Lf).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o751);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks() {
+        let dir = temp_dir("symlink");
+        let (target, link) = (dir.join("target.txt"), dir.join("link.txt"));
+        fs::write(&target, "a\r\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(normalize_file(&link, LineEnding::Lf).unwrap().changed);
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"a\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shrinks_file_in_place() {
+        let dir = temp_dir("in-place");
+        let path = dir.join("input.txt");
+        let input = "line\r\n".repeat(BUFFER_SIZE / 3) + "end\r";
+        fs::write(&path, &input).unwrap();
+
+        let report = normalize_file_to_unix_in_place(&path).unwrap();
+        assert!(report.changed);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line\n".repeat(BUFFER_SIZE / 3) + "end\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn in_place_leaves_unix_file_as_is() {
+        let dir = temp_dir("in-place-unix");
+        let path = dir.join("input.txt");
+        fs::write(&path, "a\nb\n").unwrap();
+
+        let report = normalize_file_to_unix_in_place(&path).unwrap();
+        assert!(!report.changed);
+        assert_eq!(fs::read(&path).unwrap(), b"a\nb\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_progress_up_to_file_size() {
+        let dir = temp_dir("progress");
+        let path = dir.join("input.txt");
+        let input = "x\r\n".repeat(BUFFER_SIZE);
+        fs::write(&path, &input).unwrap();
+
+        let mut calls = Vec::new();
+        normalize_file_with_progress(&path, LineEnding::Lf, |done, total| calls.push((done, total))).unwrap();
+        let total = input.len() as u64;
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(calls.last(), Some(&(total, total)));
+
+        calls.clear();
+        fs::write(&path, &input).unwrap();
+        normalize_file_to_unix_in_place_with_progress(&path, |done, total| calls.push((done, total))).unwrap();
+        assert_eq!(calls.last(), Some(&(total, total)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn byte_order_mark_changes_count() {
+        let dir = temp_dir("bom");
+        let path = dir.join("input.txt");
+        fs::write(&path, "\u{feff}a\nb\n").unwrap();
+
+        assert!(!normalize_file_with_bom(&path, LineEnding::Lf, BomPolicy::Add).unwrap().changed);
+        let report = normalize_file_with_bom(&path, LineEnding::Lf, BomPolicy::Strip).unwrap();
+        assert!(report.changed);
+        assert_eq!(report.stats.converted, 0);
+        assert_eq!(fs::read(&path).unwrap(), b"a\nb\n");
+        assert!(normalize_file_with_bom(&path, LineEnding::CrLf, BomPolicy::Add).unwrap().changed);
+        assert_eq!(fs::read(&path).unwrap(), "\u{feff}a\r\nb\r\n".as_bytes());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let dir = temp_dir("missing");
+        assert!(normalize_file(dir.join("nope.txt"), LineEnding::Lf).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! The glob syntax of `.editorconfig` sections, also used to filter the files of a tree.
+//!
+//! `*` and `?` stay within a path segment, `**` crosses them, and `[...]`, `[!...]` and
+//! `{a,b}` work as in shells.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+/// Whether `glob` matches `relative`, a `/`-separated path below the directory the glob is
+/// written for. Globs starting with `/` or containing one match the whole path; others
+/// match the file name in every directory.
+pub(crate) fn path_matches(glob: &str, relative: &str) -> bool {
+    match glob.strip_prefix('/') {
+        Some(anchored) => glob_matches(anchored, relative),
+        None if glob.contains('/') => glob_matches(glob, relative),
+        None => glob_matches(glob, relative.rsplit('/').next().unwrap_or(relative)),
+    }
+}
+
+/// Whether `glob` matches the whole of `path`, whose segments are separated by `/`.
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let path: Vec<char> = path.chars().collect();
+    expand_braces(glob).iter().any(|glob| matches(&glob.chars().collect::<Vec<_>>(), &path))
+}
+
+/// Expands every `{a,b}` in `glob` into the globs it stands for.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let bytes = glob.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => {
+                if let Some((close, alternatives)) = alternatives(glob, i) {
+                    let (head, tail) = (&glob[..i], &glob[close + 1..]);
+                    return alternatives.iter().flat_map(|alternative| expand_braces(&format!("{head}{alternative}{tail}"))).collect();
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    vec![glob.to_string()]
+}
+
+/// The closing brace of the set opening at `open`, and its comma-separated alternatives.
+fn alternatives(glob: &str, open: usize) -> Option<(usize, Vec<&str>)> {
+    let bytes = glob.as_bytes();
+    let (mut depth, mut start, mut parts) = (0, open + 1, Vec::new());
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            b'}' => {
+                parts.push(&glob[start..i]);
+                return (parts.len() > 1).then_some((i, parts));
+            }
+            b',' if depth == 0 => {
+                parts.push(&glob[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Matches a brace-free glob against a whole path.
+fn matches(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        ['*', rest @ ..] => (0..=path.len()).take_while(|&skip| skip == 0 || path[skip - 1] != '/').any(|skip| matches(rest, &path[skip..])),
+        ['?', rest @ ..] => matches!(path.first(), Some(&c) if c != '/') && matches(rest, &path[1..]),
+        ['[', rest @ ..] => match class(rest) {
+            Some((negated, set, after)) => match path.first() {
+                Some(&c) if c != '/' && in_class(set, c) != negated => matches(after, &path[1..]),
+                _ => false,
+            },
+            None => path.first() == Some(&'[') && matches(rest, &path[1..]),
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+    }
+}
+
+/// Splits a character class, after its `[`, into its negation, its members and the rest of the glob.
+fn class(glob: &[char]) -> Option<(bool, &[char], &[char])> {
+    let negated = matches!(glob.first(), Some('!' | '^'));
+    let body = &glob[usize::from(negated)..];
+    // A `]` right at the start is a member, not the end.
+    let close = body.iter().skip(1).position(|&c| c == ']')? + 1;
+    Some((negated, &body[..close], &body[close + 1..]))
+}
+
+fn in_class(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if (set[i]..=set[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("*.rs", "main.rs"));
+        assert!(!glob_matches("*.rs", "src/main.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(glob_matches("**.rs", "src/main.rs"));
+        assert!(glob_matches("*.{bat,cmd}", "run.cmd"));
+        assert!(glob_matches("{a,{b,c}}.txt", "c.txt"));
+        assert!(glob_matches("{single}.txt", "{single}.txt"));
+        assert!(glob_matches("file[0-9].?s", "file7.js"));
+        assert!(!glob_matches("file[!0-9].txt", "file7.txt"));
+        assert!(glob_matches("a\\*b", "a*b") && !glob_matches("a\\*b", "axb"));
+        assert!(glob_matches("[unclosed", "[unclosed"));
+        assert!(path_matches("*.rs", "src/main.rs") && !path_matches("/*.rs", "src/main.rs"));
+    }
+}
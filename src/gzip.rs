@@ -0,0 +1,109 @@
+//! Gzip streaming, enabled by the `flate2` feature.
+//!
+//! Decompression and normalization happen in one streaming pass, with chunk boundaries
+//! handled by the same machinery as [`NormalizingReader`] and [`NormalizingWriter`].
+
+use std::io::{self, Read, Write};
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+pub use flate2::Compression;
+
+use crate::copy::copy_with;
+use crate::{LineEnding, NormalizingReader, NormalizingWriter, Stats, StreamConfig};
+
+/// Reader yielding the decompressed, normalized content of a gzip stream.
+///
+/// Concatenated gzip members, as produced by log rotation, are read as one stream.
+///
+/// Example:
+/// ```
+/// use std::io::{Read, Write};
+/// use flate2::{write::GzEncoder, Compression};
+/// use newline_normalizer::{gzip, LineEnding};
+///
+/// let mut compressed = GzEncoder::new(Vec::new(), Compression::fast());
+/// compressed.write_all(b"one\r\ntwo\r\n").unwrap();
+/// let compressed = compressed.finish().unwrap();
+///
+/// let mut text = String::new();
+/// gzip::decoder(&compressed[..], LineEnding::Lf).read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "one\ntwo\n");
+/// ```
+pub fn decoder<R: Read>(reader: R, ending: LineEnding) -> NormalizingReader<MultiGzDecoder<R>> {
+    NormalizingReader::new(MultiGzDecoder::new(reader), ending)
+}
+
+/// Writer normalizing text and compressing it into a gzip stream.
+///
+/// Call `into_inner().finish()` when done to write the gzip trailer.
+pub fn encoder<W: Write>(writer: W, ending: LineEnding, level: Compression) -> NormalizingWriter<GzEncoder<W>> {
+    NormalizingWriter::new(GzEncoder::new(writer, level), ending)
+}
+
+/// Decompresses a gzip stream from `reader`, normalizes it, and writes it to `writer`.
+///
+/// With `recompress` set, the output is gzip-compressed at that level; otherwise it is
+/// written as plain text. Returns counters for the decompressed content.
+pub fn normalize_gzip<R, W>(reader: R, writer: W, ending: LineEnding, recompress: Option<Compression>) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = MultiGzDecoder::new(reader);
+    match recompress {
+        Some(level) => {
+            let mut encoder = GzEncoder::new(writer, level);
+            let stats = copy_with(&mut decoder, &mut encoder, ending, StreamConfig::new(), &mut |_| {})?;
+            encoder.finish()?;
+            Ok(stats)
+        }
+        None => {
+            let mut writer = writer;
+            copy_with(&mut decoder, &mut writer, ending, StreamConfig::new(), &mut |_| {})
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_concatenated_members() {
+        let mut compressed = compress(b"a\r");
+        compressed.extend(compress(b"\nb\r"));
+        let mut out = Vec::new();
+        decoder(&compressed[..], LineEnding::Lf).read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"a\nb\n");
+    }
+
+    #[test]
+    fn recompresses_output() {
+        let mut out = Vec::new();
+        let stats = normalize_gzip(&compress(b"x\ny\n")[..], &mut out, LineEnding::CrLf, Some(Compression::fast())).unwrap();
+        assert_eq!(stats.converted, 2);
+
+        let mut text = Vec::new();
+        MultiGzDecoder::new(&out[..]).read_to_end(&mut text).unwrap();
+        assert_eq!(text, b"x\r\ny\r\n");
+    }
+
+    #[test]
+    fn encoder_normalizes_before_compressing() {
+        let mut writer = encoder(Vec::new(), LineEnding::Lf, Compression::fast());
+        writer.write_all(b"a\r\nb").unwrap();
+        let compressed = writer.into_inner().finish().unwrap();
+
+        let mut out = Vec::new();
+        normalize_gzip(&compressed[..], &mut out, LineEnding::Lf, None).unwrap();
+        assert_eq!(out, b"a\nb");
+    }
+}
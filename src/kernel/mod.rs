@@ -0,0 +1,545 @@
+//! Rewrite loops behind [`ToUnixNewlines`](crate::ToUnixNewlines) and
+//! [`ToDosNewlines`](crate::ToDosNewlines).
+//!
+//! Both first look for the first newline that needs rewriting with memchr, so input that
+//! is already normalized is never copied. From there a [`Kernel`] takes over: on x86 an
+//! AVX2 or SSE2 one picked at runtime, on aarch64 a NEON one, otherwise the `core::simd`
+//! one if the `portable-simd` feature is on, and the scalar loop if not.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(target_arch = "aarch64")]
+mod neon;
+#[cfg(feature = "portable-simd")]
+mod portable;
+mod scalar;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86;
+
+/// An implementation of the rewrite loops behind the string normalizers.
+///
+/// The fastest supported kernel is picked on first use and cached, so later calls cost
+/// one atomic load. [`force`](Kernel::force) overrides the choice, which lets tests and
+/// benchmarks pin down a specific implementation. Every kernel produces the same output.
+/// Inputs shorter than 64 bytes bypass the kernels for a plain byte loop.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{Kernel, ToUnixNewlines};
+///
+/// assert!(Kernel::Scalar.force());
+/// assert_eq!(Kernel::current(), Kernel::Scalar);
+/// assert_eq!("a\r\nb".to_unix_newlines(), "a\nb");
+/// Kernel::detect().force();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Kernel {
+    /// Portable byte loops, available everywhere.
+    Scalar,
+    /// SSE2, 16 bytes at a time.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Sse2,
+    /// AVX2, 32 bytes at a time.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Avx2,
+    /// NEON, 16 bytes at a time.
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+    /// `core::simd`, enabled by the `portable-simd` feature.
+    #[cfg(feature = "portable-simd")]
+    Portable,
+}
+
+/// The selected kernel as an index into [`Kernel::ALL`] plus one, or 0 before the first call.
+static SELECTED: AtomicU8 = AtomicU8::new(0);
+
+impl Kernel {
+    /// Every kernel compiled into this build, whether or not this CPU supports it.
+    pub const ALL: &'static [Kernel] = &[
+        Kernel::Scalar,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse2,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2,
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon,
+        #[cfg(feature = "portable-simd")]
+        Kernel::Portable,
+    ];
+
+    /// The kernel the normalizers use, detecting it on the first call.
+    pub fn current() -> Kernel {
+        match SELECTED.load(Ordering::Relaxed) {
+            0 => {
+                let kernel = Kernel::detect();
+                kernel.select();
+                kernel
+            }
+            selected => Kernel::ALL[usize::from(selected) - 1],
+        }
+    }
+
+    /// Makes the normalizers use this kernel from now on, process-wide.
+    ///
+    /// Returns `false`, changing nothing, if this CPU does not support it.
+    pub fn force(self) -> bool {
+        if !self.is_supported() {
+            return false;
+        }
+        self.select();
+        true
+    }
+
+    fn select(self) {
+        let index = Kernel::ALL.iter().position(|&kernel| kernel == self).unwrap_or(0);
+        // Concurrent first calls all store the same value, so a plain store is enough.
+        SELECTED.store(index as u8 + 1, Ordering::Relaxed);
+    }
+
+    /// The fastest kernel this CPU supports.
+    pub fn detect() -> Kernel {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if Kernel::Avx2.is_supported() {
+                return Kernel::Avx2;
+            }
+            if Kernel::Sse2.is_supported() {
+                return Kernel::Sse2;
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if Kernel::Neon.is_supported() {
+                return Kernel::Neon;
+            }
+        }
+        #[cfg(feature = "portable-simd")]
+        let fallback = Kernel::Portable;
+        #[cfg(not(feature = "portable-simd"))]
+        let fallback = Kernel::Scalar;
+        fallback
+    }
+
+    /// Whether this CPU can run the kernel. Without `std`, only compile-time target features count.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Kernel::Scalar => true,
+            #[cfg(feature = "portable-simd")]
+            Kernel::Portable => true,
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "std"))]
+            Kernel::Sse2 => std::is_x86_feature_detected!("sse2"),
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), feature = "std"))]
+            Kernel::Avx2 => std::is_x86_feature_detected!("avx2"),
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(feature = "std")))]
+            Kernel::Sse2 => cfg!(target_feature = "sse2"),
+            #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(feature = "std")))]
+            Kernel::Avx2 => cfg!(target_feature = "avx2"),
+            #[cfg(all(target_arch = "aarch64", feature = "std"))]
+            Kernel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[cfg(all(target_arch = "aarch64", not(feature = "std")))]
+            Kernel::Neon => cfg!(target_feature = "neon"),
+        }
+    }
+}
+
+/// Inputs shorter than this skip the searches and kernels for a plain byte loop, since
+/// setting those up costs more than scanning a few dozen bytes.
+const TINY: usize = 64;
+
+/// Returns the Unix form of `src`, or `None` if it has no CR.
+pub(crate) fn to_unix(src: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    to_unix_into(src, &mut out).then_some(out)
+}
+
+/// Returns the DOS form of `src`, or `None` if every newline already is a CRLF.
+pub(crate) fn to_dos(src: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    to_dos_into(src, &mut out).then_some(out)
+}
+
+/// Appends the Unix form of `src` to `out`. Returns `false`, leaving `out` untouched, if `src` has no CR.
+pub(crate) fn to_unix_into(src: &[u8], out: &mut Vec<u8>) -> bool {
+    if src.len() < TINY {
+        return scalar::to_unix_tiny(src, out);
+    }
+    to_unix_into_with(Kernel::current(), src, out)
+}
+
+/// Appends the DOS form of `src` to `out`. Returns `false`, leaving `out` untouched, if every
+/// newline already is a CRLF.
+pub(crate) fn to_dos_into(src: &[u8], out: &mut Vec<u8>) -> bool {
+    if src.len() < TINY {
+        return scalar::to_dos_tiny(src, out);
+    }
+    to_dos_into_with(Kernel::current(), src, out)
+}
+
+/// Returns `true` if `src` has the line endings `to_unix` or `to_dos` would give it.
+pub(crate) fn is_normalized(src: &[u8], crlf: bool) -> bool {
+    match crlf {
+        false => crate::search::memchr(b'\r', src).is_none(),
+        true => first_lone_newline(Kernel::current(), src).is_none(),
+    }
+}
+
+fn to_unix_into_with(kernel: Kernel, src: &[u8], out: &mut Vec<u8>) -> bool {
+    debug_assert!(kernel.is_supported());
+    let Some(first) = crate::search::memchr(b'\r', src) else {
+        return false;
+    };
+    out.reserve(src.len());
+    out.extend_from_slice(&src[..first]);
+
+    // SAFETY: callers only pass kernels the CPU supports.
+    let pos = match kernel {
+        Kernel::Scalar => first,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse2 => unsafe { x86::to_unix_sse2(src, first, out) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { x86::to_unix_avx2(src, first, out) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::to_unix(src, first, out) },
+        #[cfg(feature = "portable-simd")]
+        Kernel::Portable => portable::to_unix(src, first, out),
+    };
+    scalar::to_unix_from(src, pos, out);
+    true
+}
+
+fn to_dos_into_with(kernel: Kernel, src: &[u8], out: &mut Vec<u8>) -> bool {
+    debug_assert!(kernel.is_supported());
+    let Some(first) = first_lone_newline(kernel, src) else {
+        return false;
+    };
+
+    // Almost normalized input has only a few lone newlines. The search that found the
+    // first one goes on to collect the next few, and if that finds them all, the rewrite
+    // is just copies between them and the rest of the input is not scanned again.
+    let mut lone = [0; FEW];
+    lone[0] = first;
+    let mut found = 1;
+    let mut complete = false;
+    while found < FEW {
+        match next_lone_newline(kernel, src, lone[found - 1] + 1) {
+            Some(at) => {
+                lone[found] = at;
+                found += 1;
+            }
+            None => {
+                complete = true;
+                break;
+            }
+        }
+    }
+    let resume = lone[found - 1] + 1;
+    let rest = if complete { 0 } else { count_lone_newlines(kernel, src, resume) };
+
+    // Counting first lets the output be allocated once, at its final size. The SIMD
+    // kernels need some slack on top for their block copies.
+    let grown = src.len() + found + rest;
+    let end = out.len() + grown;
+    out.reserve(if rest == 0 { grown } else { grown + SLACK });
+    let mut copied = 0;
+    for &at in &lone[..found] {
+        out.extend_from_slice(&src[copied..at]);
+        out.extend_from_slice(b"\r\n");
+        copied = at + 1;
+    }
+    if rest == 0 {
+        out.extend_from_slice(&src[copied..]);
+        return true;
+    }
+
+    // SAFETY: callers only pass kernels the CPU supports, `resume` is at least 1, and `out`
+    // has room for the whole output plus `SLACK`.
+    let pos = match kernel {
+        Kernel::Scalar => resume,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse2 => unsafe { x86::to_dos_sse2(src, resume, out) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { x86::to_dos_avx2(src, resume, out) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::to_dos(src, resume, out) },
+        #[cfg(feature = "portable-simd")]
+        Kernel::Portable => unsafe { portable::to_dos(src, resume, out) },
+    };
+    // SAFETY: `out` still has `SLACK` bytes to spare past the whole output.
+    unsafe { scalar::to_dos_from(src, pos, out) };
+    debug_assert_eq!(out.len(), end);
+    true
+}
+
+/// Lone newlines collected by the search before handing over to a counting pass and a kernel.
+const FEW: usize = 8;
+
+/// Extra output capacity the SIMD kernels may write past the final length: up to three registers.
+const SLACK: usize = 3 * 32;
+
+/// Whether the CR or LF at `found` is not half of a CRLF.
+///
+/// Both neighbours are checked and combined without branching, since on mixed input
+/// whether the byte is a CR or an LF is unpredictable.
+fn is_lone(src: &[u8], found: usize) -> bool {
+    let cr = src[found] == b'\r';
+    let next = src.get(found + 1).copied().unwrap_or(0);
+    let prev = found.checked_sub(1).map_or(0, |prev| src[prev]);
+    (cr & (next != b'\n')) | (!cr & (prev != b'\r'))
+}
+
+/// Position of the first CR or LF that is not half of a CRLF.
+fn first_lone_newline(kernel: Kernel, src: &[u8]) -> Option<usize> {
+    if !src.is_empty() && (src[0] == b'\n' || src[0] == b'\r') && is_lone(src, 0) {
+        return Some(0);
+    }
+    next_lone_newline(kernel, src, 1)
+}
+
+/// Position of the first CR or LF in `src[pos..]` that is not half of a CRLF. `pos` must be at least 1.
+fn next_lone_newline(kernel: Kernel, src: &[u8], pos: usize) -> Option<usize> {
+    // SAFETY: callers only pass kernels the CPU supports, and `pos` is at least 1.
+    let searched = match kernel {
+        Kernel::Scalar => Err(pos),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse2 => unsafe { x86::find_lone_sse2(src, pos) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { x86::find_lone_avx2(src, pos) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::find_lone(src, pos) },
+        #[cfg(feature = "portable-simd")]
+        Kernel::Portable => portable::find_lone(src, pos),
+    };
+    let pos = match searched {
+        Ok(found) => return Some(found),
+        Err(pos) => pos.min(src.len()),
+    };
+    crate::search::memchr2_iter(b'\n', b'\r', &src[pos..]).map(|found| found + pos).find(|&found| is_lone(src, found))
+}
+
+/// Number of CRs and LFs in `src[pos..]` that are not half of a CRLF. `pos` must be at least 1.
+fn count_lone_newlines(kernel: Kernel, src: &[u8], pos: usize) -> usize {
+    // SAFETY: callers only pass kernels the CPU supports, and `pos` is at least 1.
+    let (counted, pos) = match kernel {
+        Kernel::Scalar => (0, pos),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse2 => unsafe { x86::count_lone_sse2(src, pos) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { x86::count_lone_avx2(src, pos) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::count_lone(src, pos) },
+        #[cfg(feature = "portable-simd")]
+        Kernel::Portable => portable::count_lone(src, pos),
+    };
+    let pos = pos.min(src.len());
+    counted + crate::search::memchr2_iter(b'\n', b'\r', &src[pos..]).filter(|&found| is_lone(src, found + pos)).count()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64", feature = "portable-simd"))]
+#[inline(always)]
+unsafe fn drop_masked<const WIDTH: usize, const SHIFT: u32>(block: &[u8; 64], mut mask: u64, mut dst: *mut u8) -> *mut u8 {
+    let mut start = 0;
+    while mask != 0 {
+        let i = (mask.trailing_zeros() >> SHIFT) as usize;
+        core::ptr::copy_nonoverlapping(block.as_ptr().add(start), dst, WIDTH);
+        dst = dst.add(i - start);
+        start = i + 1;
+        mask &= mask - 1;
+    }
+    core::ptr::copy_nonoverlapping(block.as_ptr().add(start), dst, WIDTH);
+    dst.add(WIDTH - start)
+}
+
+/// Writes the first `WIDTH` bytes of `block` to `dst`, replacing the bytes flagged in `mask`
+/// with CRLF. Returns the new end.
+///
+/// # Safety
+///
+/// `dst` must be valid for writing `3 * WIDTH` bytes.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64", feature = "portable-simd"))]
+#[inline(always)]
+unsafe fn expand_masked<const WIDTH: usize, const SHIFT: u32>(block: &[u8; 64], mut mask: u64, mut dst: *mut u8) -> *mut u8 {
+    let mut start = 0;
+    while mask != 0 {
+        let i = (mask.trailing_zeros() >> SHIFT) as usize;
+        core::ptr::copy_nonoverlapping(block.as_ptr().add(start), dst, WIDTH);
+        dst = dst.add(i - start);
+        core::ptr::copy_nonoverlapping(b"\r\n".as_ptr(), dst, 2);
+        dst = dst.add(2);
+        start = i + 1;
+        mask &= mask - 1;
+    }
+    core::ptr::copy_nonoverlapping(block.as_ptr().add(start), dst, WIDTH);
+    dst.add(WIDTH - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_unix_with(kernel: Kernel, src: &[u8]) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        to_unix_into_with(kernel, src, &mut out).then_some(out)
+    }
+
+    fn to_dos_with(kernel: Kernel, src: &[u8]) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        to_dos_into_with(kernel, src, &mut out).then_some(out)
+    }
+
+    /// Deterministic inputs mixing text with every newline style at every offset.
+    fn inputs() -> Vec<Vec<u8>> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut inputs = Vec::new();
+        for len in (0..200).chain([1000, 4096]) {
+            for density in [2, 8, 40] {
+                let input = (0..len)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        match state % density {
+                            0 => b'\r',
+                            1 => b'\n',
+                            _ => b'a' + (state % 26) as u8,
+                        }
+                    })
+                    .collect();
+                inputs.push(input);
+            }
+        }
+        inputs
+    }
+
+    #[test]
+    fn kernels_match_scalar() {
+        for input in inputs() {
+            let unix = to_unix_with(Kernel::Scalar, &input);
+            let dos = to_dos_with(Kernel::Scalar, &input);
+            for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+                assert_eq!(to_unix_with(kernel, &input), unix, "{kernel:?} {input:?}");
+                assert_eq!(to_dos_with(kernel, &input), dos, "{kernel:?} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn scalar_collapses_crlf_runs() {
+        let input = b"a\r\n\r\n\r\r\n\rb\r\n\n\r";
+        assert_eq!(to_unix_with(Kernel::Scalar, input).unwrap(), b"a\n\n\n\n\nb\n\n\n");
+        let input = "line\r\n".repeat(50) + "end";
+        assert_eq!(to_unix_with(Kernel::Scalar, input.as_bytes()).unwrap(), ("line\n".repeat(50) + "end").as_bytes());
+    }
+
+    #[test]
+    fn scalar_dos_keeps_paired_bytes() {
+        let input = b"\na\r\n\n\r\r\nb\r";
+        assert_eq!(to_dos_with(Kernel::Scalar, input).unwrap(), b"\r\na\r\n\r\n\r\n\r\nb\r\n");
+        assert_eq!(to_dos_with(Kernel::Scalar, b"\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn crlf_straddling_register_boundaries() {
+        for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+            for split in 2..70 {
+                let mut input = vec![b'x'; 80];
+                input[split - 1] = b'\r';
+                input[split] = b'\n';
+                input[0] = b'\n';
+                let unix = to_unix_with(kernel, &input).unwrap();
+                assert_eq!(unix.len(), 79, "{kernel:?} {split}");
+                let dos = to_dos_with(kernel, &input).unwrap();
+                assert_eq!(dos.len(), 81, "{kernel:?} {split}");
+                assert_eq!(&dos[..2], b"\r\n");
+            }
+        }
+    }
+
+    #[test]
+    fn dos_output_is_allocated_at_its_final_size() {
+        let input = "a\nb\r\nc\r".repeat(100);
+        for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+            let out = to_dos_with(kernel, input.as_bytes()).unwrap();
+            assert_eq!(out.len(), input.len() + 200);
+            assert!(out.capacity() <= out.len() + SLACK, "{kernel:?}");
+        }
+    }
+
+    #[test]
+    fn almost_normalized_dos_input() {
+        let base = "line\r\n".repeat(200);
+        for lone in 0..FEW * 2 {
+            let mut input = base.clone().into_bytes();
+            for i in 0..lone {
+                // Turns a CRLF into a lone LF, in spread out lines.
+                input[i * 97 % 200 * 6 + 4] = b'x';
+            }
+            let dos = to_dos_with(Kernel::Scalar, &input);
+            for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+                let out = to_dos_with(kernel, &input);
+                assert_eq!(out, dos, "{kernel:?} {lone}");
+                if let Some(out) = out.filter(|_| lone <= FEW) {
+                    assert_eq!(out.capacity(), out.len(), "{kernel:?} {lone}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn appends_after_existing_output() {
+        for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+            let input = "x\n".repeat(40);
+            let mut out = b"head:".to_vec();
+            assert!(to_dos_into_with(kernel, input.as_bytes(), &mut out));
+            assert_eq!(out, ["head:", &"x\r\n".repeat(40)].concat().as_bytes());
+
+            let mut out = b"head:".to_vec();
+            assert!(to_unix_into_with(kernel, "x\r\n".repeat(40).as_bytes(), &mut out));
+            assert_eq!(out, ["head:", &input].concat().as_bytes());
+        }
+    }
+
+    #[test]
+    fn tiny_inputs_match_the_kernels() {
+        let text = b"\r\nab\r\r\n\n\rc\n\r";
+        for start in 0..text.len() {
+            for end in start..=text.len() {
+                let input = &text[start..end];
+                let mut out = Vec::new();
+                assert_eq!(scalar::to_unix_tiny(input, &mut out).then_some(out), to_unix_with(Kernel::Scalar, input));
+                let mut out = Vec::new();
+                assert_eq!(scalar::to_dos_tiny(input, &mut out).then_some(out), to_dos_with(Kernel::Scalar, input));
+            }
+        }
+    }
+
+    #[test]
+    fn forcing_a_kernel() {
+        let detected = Kernel::detect();
+        assert!(Kernel::Scalar.force());
+        assert_eq!(Kernel::current(), Kernel::Scalar);
+        assert_eq!(to_dos(b"a\nb").unwrap(), b"a\r\nb");
+        for &kernel in Kernel::ALL {
+            assert_eq!(kernel.force(), kernel.is_supported());
+        }
+        assert!(detected.force());
+        assert_eq!(Kernel::current(), detected);
+    }
+
+    #[test]
+    fn normalized_input_is_not_copied() {
+        for &kernel in Kernel::ALL.iter().filter(|kernel| kernel.is_supported()) {
+            assert_eq!(to_unix_with(kernel, b"a\nb\n"), None);
+            assert_eq!(to_dos_with(kernel, b"a\r\nb\r\n"), None);
+        }
+    }
+
+    #[test]
+    fn checks_agree_with_conversions() {
+        for input in inputs().into_iter().chain([b"\r\n".to_vec(), b"\n".to_vec(), b"\r".to_vec()]) {
+            assert_eq!(is_normalized(&input, false), to_unix(&input).is_none(), "{input:?}");
+            assert_eq!(is_normalized(&input, true), to_dos(&input).is_none(), "{input:?}");
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! NEON kernels for aarch64, mirroring the x86 ones 16 bytes at a time.
+//!
+//! NEON has no movemask, so compare results are narrowed to one nibble per byte and
+//! all but the top bit of each nibble is cleared; byte `i` is then flagged by bit
+//! `4 * i + 3`.
+
+use core::arch::aarch64::*;
+
+use alloc::vec::Vec;
+
+use super::{drop_masked, expand_masked};
+
+const WIDTH: usize = 16;
+
+/// Flags every byte of `cmp` that is all ones, as described in the module docs.
+#[inline(always)]
+unsafe fn nibble_mask(cmp: uint8x16_t) -> u64 {
+    let narrowed = vshrn_n_u16::<4>(vreinterpretq_u16_u8(cmp));
+    vget_lane_u64::<0>(vreinterpret_u64_u8(narrowed)) & 0x8888_8888_8888_8888
+}
+
+/// Unix rewrite of `src[pos..]`. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support NEON.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn to_unix(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    let cr = vdupq_n_u8(b'\r');
+    let lf = vdupq_n_u8(b'\n');
+    let flip = vdupq_n_u8(b'\r' ^ b'\n');
+    let mut block = [0u8; 64];
+    // Unix output never outgrows its input, so with `WIDTH` bytes of slack for the block
+    // copies, writing through `dst` stays within capacity.
+    out.reserve(src.len() - pos + WIDTH);
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `pos + WIDTH < src.len()`, so both loads stay in bounds. No more bytes were
+        // written than consumed, so the reserved space still has room for `2 * WIDTH` more.
+        let ptr = src.as_ptr().add(pos);
+        let v = vld1q_u8(ptr);
+        let is_cr = vceqq_u8(v, cr);
+        if vmaxvq_u8(is_cr) == 0 {
+            vst1q_u8(dst, v);
+            dst = dst.add(WIDTH);
+            pos += WIDTH;
+            continue;
+        }
+        // Every CR becomes an LF, then the ones already followed by an LF are dropped.
+        let next = vld1q_u8(ptr.add(1));
+        let paired = nibble_mask(vandq_u8(is_cr, vceqq_u8(next, lf)));
+        let replaced = veorq_u8(v, vandq_u8(is_cr, flip));
+        if paired == 0 {
+            vst1q_u8(dst, replaced);
+            dst = dst.add(WIDTH);
+        } else {
+            vst1q_u8(block.as_mut_ptr(), replaced);
+            dst = drop_masked::<WIDTH, 2>(&block, paired, dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Flags the CRs and LFs in `src[pos..pos + WIDTH]` that are not half of a CRLF.
+///
+/// # Safety
+///
+/// The CPU must support NEON, and `1 <= pos` and `pos + WIDTH < src.len()` must hold.
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn lone_mask(src: &[u8], pos: usize) -> u64 {
+    let cr = vdupq_n_u8(b'\r');
+    let lf = vdupq_n_u8(b'\n');
+    // SAFETY: the caller keeps the block and its neighbouring bytes in bounds.
+    let ptr = src.as_ptr().add(pos);
+    let v = vld1q_u8(ptr);
+    let is_cr = vceqq_u8(v, cr);
+    let is_lf = vceqq_u8(v, lf);
+    if vmaxvq_u8(vorrq_u8(is_cr, is_lf)) == 0 {
+        return 0;
+    }
+    // An LF not preceded by a CR, or a CR not followed by an LF.
+    let prev = vld1q_u8(ptr.sub(1));
+    let next = vld1q_u8(ptr.add(1));
+    let lone_lf = vbicq_u8(is_lf, vceqq_u8(prev, cr));
+    let lone_cr = vbicq_u8(is_cr, vceqq_u8(next, lf));
+    nibble_mask(vorrq_u8(lone_lf, lone_cr))
+}
+
+/// DOS rewrite of `src[pos..]`. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support NEON, `pos` must be at least 1, and `out` must have room for the
+/// rest of the output plus `3 * WIDTH` bytes.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn to_dos(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    debug_assert!(pos > 0);
+    let mut block = [0u8; 64];
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `1 <= pos` and `pos + WIDTH < src.len()` keep the loads in bounds, and
+        // the caller reserved room for the expansions plus the overshoot of the block copies.
+        let v = vld1q_u8(src.as_ptr().add(pos));
+        let lone = lone_mask(src, pos);
+        if lone == 0 {
+            vst1q_u8(dst, v);
+            dst = dst.add(WIDTH);
+        } else {
+            vst1q_u8(block.as_mut_ptr(), v);
+            dst = expand_masked::<WIDTH, 2>(&block, lone, dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Finds the first CR or LF in `src[pos..]` that is not half of a CRLF.
+///
+/// Returns `Ok(position)` if found, or `Err(stop)` with where the scalar scan should resume.
+///
+/// # Safety
+///
+/// The CPU must support NEON, and `pos` must be at least 1.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn find_lone(src: &[u8], mut pos: usize) -> Result<usize, usize> {
+    while pos + WIDTH < src.len() {
+        let lone = lone_mask(src, pos);
+        if lone != 0 {
+            return Ok(pos + (lone.trailing_zeros() >> 2) as usize);
+        }
+        pos += WIDTH;
+    }
+    Err(pos)
+}
+
+/// Counts the CRs and LFs in `src[pos..]` that are not half of a CRLF.
+///
+/// Returns the count and where the scalar count should resume.
+///
+/// # Safety
+///
+/// The CPU must support NEON, and `pos` must be at least 1.
+#[target_feature(enable = "neon")]
+pub(super) unsafe fn count_lone(src: &[u8], mut pos: usize) -> (usize, usize) {
+    let mut count = 0;
+    while pos + WIDTH < src.len() {
+        count += lone_mask(src, pos).count_ones() as usize;
+        pos += WIDTH;
+    }
+    (count, pos)
+}
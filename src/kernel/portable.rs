@@ -0,0 +1,131 @@
+//! `core::simd` kernels for every architecture, enabled by the nightly-only `portable-simd`
+//! feature.
+//!
+//! The same algorithm as the hand-written kernels, 32 bytes at a time, left to the
+//! compiler to lower for the target.
+
+use core::ptr;
+use core::simd::cmp::SimdPartialEq;
+use core::simd::{u8x32, Select};
+
+use alloc::vec::Vec;
+
+use super::{drop_masked, expand_masked};
+
+const WIDTH: usize = 32;
+
+/// Unix rewrite of `src[pos..]`. Returns where it stopped.
+pub(super) fn to_unix(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    let cr = u8x32::splat(b'\r');
+    let lf = u8x32::splat(b'\n');
+    let mut block = [0u8; 64];
+    // Unix output never outgrows its input, so with `WIDTH` bytes of slack for the block
+    // copies, writing through `dst` stays within capacity.
+    out.reserve(src.len() - pos + WIDTH);
+    let mut dst = unsafe { out.as_mut_ptr().add(out.len()) };
+
+    while pos + WIDTH < src.len() {
+        let v = u8x32::from_slice(&src[pos..pos + WIDTH]);
+        let is_cr = v.simd_eq(cr);
+        // SAFETY: no more bytes were written than consumed, so the reserved space still has
+        // room for `2 * WIDTH` more.
+        unsafe {
+            if !is_cr.any() {
+                ptr::write_unaligned(dst.cast::<u8x32>(), v);
+                dst = dst.add(WIDTH);
+                pos += WIDTH;
+                continue;
+            }
+            // Every CR becomes an LF, then the ones already followed by an LF are dropped.
+            let next = u8x32::from_slice(&src[pos + 1..pos + 1 + WIDTH]);
+            let paired = (is_cr & next.simd_eq(lf)).to_bitmask();
+            let replaced = is_cr.select(lf, v);
+            if paired == 0 {
+                ptr::write_unaligned(dst.cast::<u8x32>(), replaced);
+                dst = dst.add(WIDTH);
+            } else {
+                replaced.copy_to_slice(&mut block[..WIDTH]);
+                dst = drop_masked::<WIDTH, 0>(&block, paired, dst);
+            }
+        }
+        pos += WIDTH;
+    }
+
+    // SAFETY: everything up to `dst` was written above.
+    unsafe { out.set_len(dst.offset_from(out.as_ptr()) as usize) };
+    pos
+}
+
+/// Flags the CRs and LFs in `src[pos..pos + WIDTH]` that are not half of a CRLF.
+///
+/// Needs `1 <= pos` and `pos + WIDTH < src.len()`.
+#[inline]
+fn lone_mask(src: &[u8], pos: usize) -> u64 {
+    let cr = u8x32::splat(b'\r');
+    let lf = u8x32::splat(b'\n');
+    let v = u8x32::from_slice(&src[pos..pos + WIDTH]);
+    let is_cr = v.simd_eq(cr);
+    let is_lf = v.simd_eq(lf);
+    if !(is_cr | is_lf).any() {
+        return 0;
+    }
+    // An LF not preceded by a CR, or a CR not followed by an LF.
+    let prev = u8x32::from_slice(&src[pos - 1..pos - 1 + WIDTH]);
+    let next = u8x32::from_slice(&src[pos + 1..pos + 1 + WIDTH]);
+    ((is_lf & !prev.simd_eq(cr)) | (is_cr & !next.simd_eq(lf))).to_bitmask()
+}
+
+/// DOS rewrite of `src[pos..]`. Returns where it stopped.
+///
+/// # Safety
+///
+/// `pos` must be at least 1, and `out` must have room for the rest of the output plus
+/// `3 * WIDTH` bytes.
+pub(super) unsafe fn to_dos(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    debug_assert!(pos > 0);
+    let mut block = [0u8; 64];
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        let v = u8x32::from_slice(&src[pos..pos + WIDTH]);
+        let lone = lone_mask(src, pos);
+        // SAFETY: the caller reserved room for the expansions plus the overshoot of the block copies.
+        if lone == 0 {
+            ptr::write_unaligned(dst.cast::<u8x32>(), v);
+            dst = dst.add(WIDTH);
+        } else {
+            v.copy_to_slice(&mut block[..WIDTH]);
+            dst = expand_masked::<WIDTH, 0>(&block, lone, dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Finds the first CR or LF in `src[pos..]` that is not half of a CRLF. `pos` must be at least 1.
+///
+/// Returns `Ok(position)` if found, or `Err(stop)` with where the scalar scan should resume.
+pub(super) fn find_lone(src: &[u8], mut pos: usize) -> Result<usize, usize> {
+    while pos + WIDTH < src.len() {
+        let lone = lone_mask(src, pos);
+        if lone != 0 {
+            return Ok(pos + lone.trailing_zeros() as usize);
+        }
+        pos += WIDTH;
+    }
+    Err(pos)
+}
+
+/// Counts the CRs and LFs in `src[pos..]` that are not half of a CRLF. `pos` must be at least 1.
+///
+/// Returns the count and where the scalar count should resume.
+pub(super) fn count_lone(src: &[u8], mut pos: usize) -> (usize, usize) {
+    let mut count = 0;
+    while pos + WIDTH < src.len() {
+        count += lone_mask(src, pos).count_ones() as usize;
+        pos += WIDTH;
+    }
+    (count, pos)
+}
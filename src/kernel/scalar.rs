@@ -0,0 +1,105 @@
+//! Portable rewrite loops, also used for the tails the SIMD kernels leave behind.
+//!
+//! Each newline costs one copy of the text before it and one unconditional write. Whether
+//! the newline was rewritten only changes how far the output advances, so mixed CR, LF and
+//! CRLF input does not turn into a stream of mispredicted branches.
+
+use alloc::vec::Vec;
+
+/// Appends the Unix form of `src[pos..]` to `out`.
+///
+/// A run of CRLF lines cannot be copied in bulk, since the CR of every line in it is
+/// dropped: the copy of each line up to its CR, with the LF before it, is the least it costs.
+pub(crate) fn to_unix_from(src: &[u8], pos: usize, out: &mut Vec<u8>) {
+    // The output never outgrows the input, and each write lands before the input's end.
+    out.reserve(src.len() - pos);
+    let start = pos;
+    let mut pos = pos;
+    let mut dst = out.len();
+
+    for cr in crate::search::memchr_iter(b'\r', &src[start..]).map(|cr| cr + start) {
+        let text = &src[pos..cr];
+        // The LF of a CRLF is kept and copied along with the line after it, so a CR
+        // only becomes an LF when it stands alone.
+        let lone = src.get(cr + 1) != Some(&b'\n');
+        // SAFETY: `dst + text.len()` is below `out.len() + src.len() - start`.
+        unsafe {
+            let at = out.as_mut_ptr().add(dst);
+            core::ptr::copy_nonoverlapping(text.as_ptr(), at, text.len());
+            *at.add(text.len()) = b'\n';
+        }
+        dst += text.len() + usize::from(lone);
+        pos = cr + 1;
+    }
+
+    // SAFETY: every byte up to `dst` was written above.
+    unsafe { out.set_len(dst) };
+    out.extend_from_slice(&src[pos..]);
+}
+
+/// Appends the DOS form of `src[pos..]` to `out`.
+///
+/// An LF at `pos` counts as paired if `src[pos - 1]` is a CR.
+///
+/// # Safety
+///
+/// `out` must have room for the DOS form of `src[pos..]` plus one byte.
+pub(crate) unsafe fn to_dos_from(src: &[u8], pos: usize, out: &mut Vec<u8>) {
+    let start = pos;
+    let mut pos = pos;
+    let mut dst = out.len();
+
+    for found in crate::search::memchr2_iter(b'\n', b'\r', &src[start..]).map(|found| found + start) {
+        let byte = src[found];
+        let paired = !super::is_lone(src, found);
+        let text = &src[pos..found];
+        // A paired newline byte is written back as itself, a lone one becomes CRLF.
+        let first = if paired { byte } else { b'\r' };
+        unsafe {
+            let at = out.as_mut_ptr().add(dst);
+            core::ptr::copy_nonoverlapping(text.as_ptr(), at, text.len());
+            *at.add(text.len()) = first;
+            *at.add(text.len() + 1) = b'\n';
+        }
+        dst += text.len() + 2 - usize::from(paired);
+        pos = found + 1;
+    }
+
+    unsafe { out.set_len(dst) };
+    out.extend_from_slice(&src[pos..]);
+}
+
+/// Like `to_unix_into`, for inputs short enough that a byte loop beats any search.
+pub(crate) fn to_unix_tiny(src: &[u8], out: &mut Vec<u8>) -> bool {
+    let Some(first) = src.iter().position(|&byte| byte == b'\r') else {
+        return false;
+    };
+    out.reserve(src.len());
+    out.extend_from_slice(&src[..first]);
+    let mut after_cr = false;
+    for &byte in &src[first..] {
+        // The LF of a CRLF was already written for its CR.
+        if !(after_cr && byte == b'\n') {
+            out.push(if byte == b'\r' { b'\n' } else { byte });
+        }
+        after_cr = byte == b'\r';
+    }
+    true
+}
+
+/// Like `to_dos_into`, for inputs short enough that a byte loop beats any search.
+pub(crate) fn to_dos_tiny(src: &[u8], out: &mut Vec<u8>) -> bool {
+    let Some(first) = (0..src.len()).find(|&i| matches!(src[i], b'\n' | b'\r') && super::is_lone(src, i)) else {
+        return false;
+    };
+    out.reserve(2 * src.len() - first);
+    out.extend_from_slice(&src[..first]);
+    for (i, &byte) in src.iter().enumerate().skip(first) {
+        if matches!(byte, b'\n' | b'\r') && super::is_lone(src, i) {
+            out.extend_from_slice(b"\r\n");
+        } else {
+            out.push(byte);
+        }
+    }
+    true
+}
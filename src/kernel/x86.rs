@@ -0,0 +1,296 @@
+//! SSE2 and AVX2 kernels.
+//!
+//! Each kernel walks the input one register at a time. Blocks without a CR (or without
+//! any newline, for DOS) are copied whole; otherwise register masks say which bytes to
+//! drop or expand, so only the newlines themselves are visited. Every block also reads
+//! the byte after it (and, for DOS, the one before), so the kernels stop one register
+//! short of the end and return where the scalar loop should take over.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use alloc::vec::Vec;
+
+use super::{drop_masked, expand_masked};
+
+/// Unix rewrite of `src[pos..]`, 16 bytes at a time. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support SSE2.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn to_unix_sse2(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    const WIDTH: usize = 16;
+    let cr = _mm_set1_epi8(b'\r' as i8);
+    let lf = _mm_set1_epi8(b'\n' as i8);
+    let flip = _mm_set1_epi8((b'\r' ^ b'\n') as i8);
+    let mut block = [0u8; 64];
+    // Unix output never outgrows its input, so with `WIDTH` bytes of slack for the block
+    // copies, writing through `dst` stays within capacity.
+    out.reserve(src.len() - pos + WIDTH);
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `pos + WIDTH < src.len()`, so both loads stay in bounds. No more bytes were
+        // written than consumed, so the reserved space still has room for `2 * WIDTH` more.
+        let ptr = src.as_ptr().add(pos);
+        let v = _mm_loadu_si128(ptr as *const __m128i);
+        let is_cr = _mm_cmpeq_epi8(v, cr);
+        if _mm_movemask_epi8(is_cr) == 0 {
+            _mm_storeu_si128(dst as *mut __m128i, v);
+            dst = dst.add(WIDTH);
+            pos += WIDTH;
+            continue;
+        }
+        // Every CR becomes an LF, then the ones already followed by an LF are dropped.
+        let next = _mm_loadu_si128(ptr.add(1) as *const __m128i);
+        let paired = _mm_movemask_epi8(_mm_and_si128(is_cr, _mm_cmpeq_epi8(next, lf))) as u32;
+        let replaced = _mm_xor_si128(v, _mm_and_si128(is_cr, flip));
+        if paired == 0 {
+            _mm_storeu_si128(dst as *mut __m128i, replaced);
+            dst = dst.add(WIDTH);
+        } else {
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, replaced);
+            dst = drop_masked::<WIDTH, 0>(&block, paired.into(), dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Unix rewrite of `src[pos..]`, 32 bytes at a time. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn to_unix_avx2(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    const WIDTH: usize = 32;
+    let cr = _mm256_set1_epi8(b'\r' as i8);
+    let lf = _mm256_set1_epi8(b'\n' as i8);
+    let flip = _mm256_set1_epi8((b'\r' ^ b'\n') as i8);
+    let mut block = [0u8; 64];
+    // Unix output never outgrows its input, so with `WIDTH` bytes of slack for the block
+    // copies, writing through `dst` stays within capacity.
+    out.reserve(src.len() - pos + WIDTH);
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `pos + WIDTH < src.len()`, so both loads stay in bounds. No more bytes were
+        // written than consumed, so the reserved space still has room for `2 * WIDTH` more.
+        let ptr = src.as_ptr().add(pos);
+        let v = _mm256_loadu_si256(ptr as *const __m256i);
+        let is_cr = _mm256_cmpeq_epi8(v, cr);
+        if _mm256_movemask_epi8(is_cr) == 0 {
+            _mm256_storeu_si256(dst as *mut __m256i, v);
+            dst = dst.add(WIDTH);
+            pos += WIDTH;
+            continue;
+        }
+        let next = _mm256_loadu_si256(ptr.add(1) as *const __m256i);
+        let paired = _mm256_movemask_epi8(_mm256_and_si256(is_cr, _mm256_cmpeq_epi8(next, lf))) as u32;
+        let replaced = _mm256_xor_si256(v, _mm256_and_si256(is_cr, flip));
+        if paired == 0 {
+            _mm256_storeu_si256(dst as *mut __m256i, replaced);
+            dst = dst.add(WIDTH);
+        } else {
+            _mm256_storeu_si256(block.as_mut_ptr() as *mut __m256i, replaced);
+            dst = drop_masked::<WIDTH, 0>(&block, paired.into(), dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Flags the CRs and LFs in `src[pos..pos + 16]` that are not half of a CRLF.
+///
+/// # Safety
+///
+/// The CPU must support SSE2, and `1 <= pos` and `pos + 16 < src.len()` must hold.
+#[target_feature(enable = "sse2")]
+#[inline]
+unsafe fn lone_mask_sse2(src: &[u8], pos: usize) -> u32 {
+    let cr = _mm_set1_epi8(b'\r' as i8);
+    let lf = _mm_set1_epi8(b'\n' as i8);
+    // SAFETY: the caller keeps the block and its neighbouring bytes in bounds.
+    let ptr = src.as_ptr().add(pos);
+    let v = _mm_loadu_si128(ptr as *const __m128i);
+    let is_cr = _mm_cmpeq_epi8(v, cr);
+    let is_lf = _mm_cmpeq_epi8(v, lf);
+    if _mm_movemask_epi8(_mm_or_si128(is_cr, is_lf)) == 0 {
+        return 0;
+    }
+    // An LF not preceded by a CR, or a CR not followed by an LF.
+    let prev = _mm_loadu_si128(ptr.sub(1) as *const __m128i);
+    let next = _mm_loadu_si128(ptr.add(1) as *const __m128i);
+    let lone_lf = _mm_andnot_si128(_mm_cmpeq_epi8(prev, cr), is_lf);
+    let lone_cr = _mm_andnot_si128(_mm_cmpeq_epi8(next, lf), is_cr);
+    _mm_movemask_epi8(_mm_or_si128(lone_lf, lone_cr)) as u32
+}
+
+/// Flags the CRs and LFs in `src[pos..pos + 32]` that are not half of a CRLF.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and `1 <= pos` and `pos + 32 < src.len()` must hold.
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn lone_mask_avx2(src: &[u8], pos: usize) -> u32 {
+    let cr = _mm256_set1_epi8(b'\r' as i8);
+    let lf = _mm256_set1_epi8(b'\n' as i8);
+    // SAFETY: the caller keeps the block and its neighbouring bytes in bounds.
+    let ptr = src.as_ptr().add(pos);
+    let v = _mm256_loadu_si256(ptr as *const __m256i);
+    let is_cr = _mm256_cmpeq_epi8(v, cr);
+    let is_lf = _mm256_cmpeq_epi8(v, lf);
+    if _mm256_movemask_epi8(_mm256_or_si256(is_cr, is_lf)) == 0 {
+        return 0;
+    }
+    let prev = _mm256_loadu_si256(ptr.sub(1) as *const __m256i);
+    let next = _mm256_loadu_si256(ptr.add(1) as *const __m256i);
+    let lone_lf = _mm256_andnot_si256(_mm256_cmpeq_epi8(prev, cr), is_lf);
+    let lone_cr = _mm256_andnot_si256(_mm256_cmpeq_epi8(next, lf), is_cr);
+    _mm256_movemask_epi8(_mm256_or_si256(lone_lf, lone_cr)) as u32
+}
+
+/// DOS rewrite of `src[pos..]`, 16 bytes at a time. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support SSE2, `pos` must be at least 1, and `out` must have room for the
+/// rest of the output plus `3 * 16` bytes.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn to_dos_sse2(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    const WIDTH: usize = 16;
+    debug_assert!(pos > 0);
+    let mut block = [0u8; 64];
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `1 <= pos` and `pos + WIDTH < src.len()` keep the loads in bounds, and
+        // the caller reserved room for the expansions plus the overshoot of the block copies.
+        let v = _mm_loadu_si128(src.as_ptr().add(pos) as *const __m128i);
+        let lone = lone_mask_sse2(src, pos);
+        if lone == 0 {
+            _mm_storeu_si128(dst as *mut __m128i, v);
+            dst = dst.add(WIDTH);
+        } else {
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, v);
+            dst = expand_masked::<WIDTH, 0>(&block, lone.into(), dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// DOS rewrite of `src[pos..]`, 32 bytes at a time. Returns where it stopped.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, `pos` must be at least 1, and `out` must have room for the
+/// rest of the output plus `3 * 32` bytes.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn to_dos_avx2(src: &[u8], mut pos: usize, out: &mut Vec<u8>) -> usize {
+    const WIDTH: usize = 32;
+    debug_assert!(pos > 0);
+    let mut block = [0u8; 64];
+    let mut dst = out.as_mut_ptr().add(out.len());
+
+    while pos + WIDTH < src.len() {
+        // SAFETY: `1 <= pos` and `pos + WIDTH < src.len()` keep the loads in bounds, and
+        // the caller reserved room for the expansions plus the overshoot of the block copies.
+        let v = _mm256_loadu_si256(src.as_ptr().add(pos) as *const __m256i);
+        let lone = lone_mask_avx2(src, pos);
+        if lone == 0 {
+            _mm256_storeu_si256(dst as *mut __m256i, v);
+            dst = dst.add(WIDTH);
+        } else {
+            _mm256_storeu_si256(block.as_mut_ptr() as *mut __m256i, v);
+            dst = expand_masked::<WIDTH, 0>(&block, lone.into(), dst);
+        }
+        pos += WIDTH;
+    }
+
+    out.set_len(dst.offset_from(out.as_ptr()) as usize);
+    pos
+}
+
+/// Finds the first CR or LF in `src[pos..]` that is not half of a CRLF, 16 bytes at a time.
+///
+/// Returns `Ok(position)` if found, or `Err(stop)` with where the scalar scan should resume.
+///
+/// # Safety
+///
+/// The CPU must support SSE2, and `pos` must be at least 1.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn find_lone_sse2(src: &[u8], mut pos: usize) -> Result<usize, usize> {
+    while pos + 16 < src.len() {
+        let lone = lone_mask_sse2(src, pos);
+        if lone != 0 {
+            return Ok(pos + lone.trailing_zeros() as usize);
+        }
+        pos += 16;
+    }
+    Err(pos)
+}
+
+/// Finds the first CR or LF in `src[pos..]` that is not half of a CRLF, 32 bytes at a time.
+///
+/// Returns `Ok(position)` if found, or `Err(stop)` with where the scalar scan should resume.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and `pos` must be at least 1.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn find_lone_avx2(src: &[u8], mut pos: usize) -> Result<usize, usize> {
+    while pos + 32 < src.len() {
+        let lone = lone_mask_avx2(src, pos);
+        if lone != 0 {
+            return Ok(pos + lone.trailing_zeros() as usize);
+        }
+        pos += 32;
+    }
+    Err(pos)
+}
+
+/// Counts the CRs and LFs in `src[pos..]` that are not half of a CRLF, 16 bytes at a time.
+///
+/// Returns the count and where the scalar count should resume.
+///
+/// # Safety
+///
+/// The CPU must support SSE2, and `pos` must be at least 1.
+#[target_feature(enable = "sse2")]
+pub(super) unsafe fn count_lone_sse2(src: &[u8], mut pos: usize) -> (usize, usize) {
+    let mut count = 0;
+    while pos + 16 < src.len() {
+        count += lone_mask_sse2(src, pos).count_ones() as usize;
+        pos += 16;
+    }
+    (count, pos)
+}
+
+/// Counts the CRs and LFs in `src[pos..]` that are not half of a CRLF, 32 bytes at a time.
+///
+/// Returns the count and where the scalar count should resume.
+///
+/// # Safety
+///
+/// The CPU must support AVX2, and `pos` must be at least 1.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn count_lone_avx2(src: &[u8], mut pos: usize) -> (usize, usize) {
+    let mut count = 0;
+    while pos + 32 < src.len() {
+        count += lone_mask_avx2(src, pos).count_ones() as usize;
+        pos += 32;
+    }
+    (count, pos)
+}
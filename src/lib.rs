@@ -2,6 +2,157 @@
 //!
 //! Optimized for speed and minimal allocations. Returns a `Cow<str>` to avoid copies
 //! when no change is needed.
+//!
+//! The `std` feature is on by default. Without it the crate is `no_std` and needs only
+//! `alloc`; the string traits, [`Normalizer`], analysis, [`StreamNormalizer`], [`normalize_chunks`] and the
+//! sans-IO [`Machine`] and [`BlockMachine`] remain available, while the I/O adapters and file functions require `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "std")]
+mod binary;
+mod blocks;
+mod bom;
+#[cfg(feature = "bstr")]
+pub mod bstr;
+#[cfg(feature = "std")]
+mod bufread;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+mod capacity;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod chunks;
+#[cfg(feature = "clap")]
+pub mod clap;
+#[cfg(feature = "compact_str")]
+pub mod compact;
+mod compare;
+mod config;
+#[cfg(feature = "std")]
+mod copy;
+mod csv;
+mod edits;
+#[cfg(feature = "std")]
+mod editorconfig;
+#[cfg(feature = "encoding_rs")]
+pub mod encoding;
+mod engine;
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+pub mod extract;
+#[cfg(feature = "std")]
+mod glob;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "futures-io")]
+pub mod futures_io;
+#[cfg(feature = "flate2")]
+pub mod gzip;
+mod kernel;
+mod line_ending;
+#[cfg(feature = "std")]
+mod line_writer;
+mod machine;
+mod mail;
+mod normalizer;
+mod options;
+mod position;
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "std")]
+mod range;
+#[cfg(feature = "std")]
+mod reader;
+#[cfg(feature = "ropey")]
+mod rope;
+mod search;
+#[cfg(feature = "serde")]
+pub mod serde_helpers;
+mod stats;
+#[cfg(feature = "similar")]
+pub mod similar;
+#[cfg(feature = "test-strategies")]
+pub mod strategies;
+mod stream;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+mod text;
+mod trace;
+#[cfg(feature = "std")]
+mod tree;
+#[cfg(feature = "tower")]
+pub mod tower;
+mod unicode;
+#[cfg(all(feature = "tokio-uring", target_os = "linux"))]
+pub mod uring;
+#[cfg(feature = "std")]
+mod vectored;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+mod writer;
+
+pub use analysis::{analyze, analyze_with_line_lengths, Analysis, Bom, LineLengths};
+#[cfg(feature = "std")]
+pub use binary::looks_binary;
+pub use blocks::normalize_in_blocks;
+pub use bom::BomPolicy;
+#[cfg(feature = "std")]
+pub use bom::BomReader;
+#[cfg(feature = "std")]
+pub use bufread::{UniversalBufReadExt, UniversalLines};
+#[cfg(all(feature = "std", feature = "stream"))]
+pub use bufread::{into_normalized_lines, NormalizedLines};
+pub use capacity::Capacity;
+pub use chunks::{normalize_chunks, NormalizedChunks};
+#[cfg(feature = "stream")]
+pub use chunks::NormalizedStream;
+pub use compare::{normalized_eq, normalized_hash};
+pub use config::StreamConfig;
+#[cfg(feature = "std")]
+pub use copy::{filter_stdio, normalize_copy, normalize_copy_with_config};
+pub use edits::{apply_edits, edit_script, Edit};
+#[cfg(feature = "std")]
+pub use editorconfig::{editorconfig_line_ending, EditorConfig};
+#[cfg(feature = "std")]
+pub use file::{
+    normalize_file, normalize_file_to_unix_in_place, normalize_file_to_unix_in_place_with_progress,
+    normalize_file_with_bom, normalize_file_with_progress, FileReport,
+};
+pub use kernel::Kernel;
+pub use line_ending::LineEnding;
+#[cfg(feature = "std")]
+pub use line_writer::NormalizingLineWriter;
+pub use machine::{BlockMachine, Feed, Machine, Status};
+pub use mail::{check_mail_body, normalize_mail_body, MailReport};
+pub use normalizer::{Normalizer, NormalizerBuilder};
+pub use options::{ensure_final_newline, normalize_to_dominant, strip_final_newline, CrCrLf, FinalNewline, LoneCarriageReturn, LoneCr};
+#[cfg(feature = "std")]
+pub use parallel::normalize_copy_parallel;
+pub use position::{Position, PositionMapper};
+#[cfg(feature = "std")]
+pub use range::{normalize_range, RangeReport};
+#[cfg(feature = "std")]
+pub use reader::NormalizingReader;
+#[cfg(feature = "ropey")]
+pub use rope::normalize_rope;
+pub use stats::Stats;
+pub use stream::StreamNormalizer;
+pub use text::{CrLf, DosText, Ending, Lf, NormalizedString, NotNormalized, UnixText};
+#[cfg(feature = "std")]
+pub use tree::{normalize_tree, normalize_tree_with_callback, TreeFile, TreeOptions, TreeReport};
+#[cfg(feature = "std")]
+pub use vectored::{normalize_copy_vectored, normalize_copy_vectored_with_config};
+#[cfg(feature = "std")]
+pub use writer::NormalizingWriter;
 
 /// Converts any mix of CRLF (`\r\n`) and CR (`\r`) newlines to LF (`\n`).
 ///
@@ -20,7 +171,7 @@ pub trait ToUnixNewlines {
     /// Normalize all line breaks in the input to LF (`\n`).
     ///
     /// Returns a borrowed reference if no transformation is needed.
-    fn to_unix_newlines(&self) -> std::borrow::Cow<str>;
+    fn to_unix_newlines(&self) -> alloc::borrow::Cow<'_, str>;
 }
 
 /// Converts any mix of LF (`\n`) and CR (`\r`) newlines to CRLF (`\r\n`).
@@ -40,100 +191,33 @@ pub trait ToDosNewlines {
     /// Normalize all line breaks in the input to CRLF (`\r\n`).
     ///
     /// Returns a borrowed reference if no transformation is needed.
-    fn to_dos_newlines(&self) -> std::borrow::Cow<str>;
+    fn to_dos_newlines(&self) -> alloc::borrow::Cow<'_, str>;
 }
 
 impl ToUnixNewlines for str {
-    fn to_unix_newlines(&self) -> std::borrow::Cow<str> {
-        let slice = self.as_bytes();
-        let len = slice.len();
-        let end_index = len.saturating_sub(1);
-        let mut iter = memchr::memchr_iter(b'\r', slice);
-
-        let Some(mut cr) = iter.next() else {
-            return std::borrow::Cow::Borrowed(self);
-        };
-
-        let mut out = Vec::with_capacity(len);
-        let mut pos = 0;
-
-        loop {
-            out.extend_from_slice(&slice[pos..cr]);
-            out.push(b'\n');
-
-            pos = cr + 1;
-            if cr < end_index && slice[pos] == b'\n' {
-                pos += 1;
-            }
-
-            match iter.next() {
-                Some(next_cr) => cr = next_cr,
-                None => break,
-            }
-        }
-
-        if pos < len {
-            out.extend_from_slice(&slice[pos..]);
+    fn to_unix_newlines(&self) -> alloc::borrow::Cow<'_, str> {
+        let out = kernel::to_unix(self.as_bytes());
+        trace::trace_event!(trace, len = self.len(), fast_path = out.is_none(), "normalized to LF");
+        match out {
+            // Only ASCII CR bytes were replaced or removed, so the result is still UTF-8.
+            Some(out) => alloc::borrow::Cow::Owned(unsafe { String::from_utf8_unchecked(out) }),
+            None => alloc::borrow::Cow::Borrowed(self),
         }
-
-        std::borrow::Cow::Owned(unsafe { String::from_utf8_unchecked(out) })
     }
 }
 
 impl ToDosNewlines for str {
-    fn to_dos_newlines(&self) -> std::borrow::Cow<str> {
-        let slice = self.as_bytes();
-        let len = slice.len();
-        let end_index = len.saturating_sub(1);
-        let mut iter = memchr::memchr2_iter(b'\n', b'\r', slice);
-
-        // Skip all properly formatted CRLF pairs
-        let mut crlf = usize::MAX;
-        while let Some(match_pos) = iter.next() {
-            if (slice[match_pos] == b'\r' && match_pos < end_index && slice[match_pos + 1] == b'\n') ||
-                (slice[match_pos] == b'\n' && match_pos > 0 && slice[match_pos - 1] == b'\r')  {
-                continue;
-            }
-            crlf = match_pos;
-            break;
-        }
-
-        if crlf == usize::MAX {
-            return std::borrow::Cow::Borrowed(self);
-        }
-
-        let mut out = Vec::with_capacity(len);
-        let mut pos = 0;
-        let mut current;
-
-        loop {
-            if crlf >= pos {
-                out.extend_from_slice(&slice[pos..crlf]);
-                out.extend_from_slice(&[b'\r', b'\n']);
-                current = slice[crlf];
-                pos = crlf + 1;
-                
-                if current == b'\r' && crlf < end_index && slice[pos] == b'\n' {
-                    pos += 1;
-                }
-            }
-
-            match iter.next() {
-                Some(next_crlf) => crlf = next_crlf,
-                None => break,
-            }
-        }
-        
-        if pos < len {
-            out.extend_from_slice(&slice[pos..]);
+    fn to_dos_newlines(&self) -> alloc::borrow::Cow<'_, str> {
+        let out = kernel::to_dos(self.as_bytes());
+        trace::trace_event!(trace, len = self.len(), fast_path = out.is_none(), "normalized to CRLF");
+        match out {
+            // Only ASCII newline bytes were expanded, so the result is still UTF-8.
+            Some(out) => alloc::borrow::Cow::Owned(unsafe { String::from_utf8_unchecked(out) }),
+            None => alloc::borrow::Cow::Borrowed(self),
         }
-
-        std::borrow::Cow::Owned(unsafe { String::from_utf8_unchecked(out) })
     }
 }
 
-
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,178 @@
+//! Target line ending selection.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::options::Options;
+use crate::{kernel, Capacity, LoneCarriageReturn, LoneCr, ToDosNewlines, ToUnixNewlines};
+
+/// A line ending the normalizers can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// Unix line ending, `\n`.
+    Lf,
+    /// DOS/Windows line ending, `\r\n`.
+    CrLf,
+}
+
+impl LineEnding {
+    /// The line ending as a string slice.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+
+    /// The line ending as bytes.
+    pub fn as_bytes(self) -> &'static [u8] {
+        self.as_str().as_bytes()
+    }
+
+    /// Normalizes every newline in `text` to this line ending.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::CrLf.normalize("a\nb\r"), "a\r\nb\r\n");
+    /// ```
+    pub fn normalize(self, text: &str) -> Cow<'_, str> {
+        match self {
+            LineEnding::Lf => text.to_unix_newlines(),
+            LineEnding::CrLf => text.to_dos_newlines(),
+        }
+    }
+
+    /// Returns `true` if [`normalize`](Self::normalize) would give `text` back unchanged, that
+    /// is, if every newline in it already is this line ending. Nothing is allocated.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// assert!(LineEnding::CrLf.is_normalized("a\r\nb"));
+    /// assert!(!LineEnding::CrLf.is_normalized("a\r\nb\n"));
+    /// ```
+    pub fn is_normalized(self, text: &str) -> bool {
+        kernel::is_normalized(text.as_bytes(), self == LineEnding::CrLf)
+    }
+
+    /// Like [`normalize`](Self::normalize), also turning the Unicode line breaks NEL
+    /// (U+0085), LINE SEPARATOR (U+2028) and PARAGRAPH SEPARATOR (U+2029) into this ending.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::Lf.normalize_unicode("a\u{2028}b\r\nc\u{85}"), "a\nb\nc\n");
+    /// ```
+    pub fn normalize_unicode(self, text: &str) -> Cow<'_, str> {
+        crate::unicode::normalize(text, self)
+    }
+
+    /// Like [`normalize`](Self::normalize), leaving a CR that is not part of a CRLF as it
+    /// is: only LF and CRLF are newlines, as for [`str::lines`]. For text where a lone CR is
+    /// content, such as a terminal capture overwriting its progress line.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::CrLf.normalize_keeping_lone_cr("10%\r99%\ndone"), "10%\r99%\r\ndone");
+    /// ```
+    pub fn normalize_keeping_lone_cr(self, text: &str) -> Cow<'_, str> {
+        match (Options { lone_cr: LoneCr::Keep, ..Options::new(self) }).apply(text) {
+            Ok(text) => text,
+            Err(_) => unreachable!("lone CRs are kept"),
+        }
+    }
+
+    /// Like [`normalize`](Self::normalize), failing at the first CR that is not part of a
+    /// CRLF instead of converting it, for protocols such as HTTP and SMTP where a bare CR
+    /// means the text is corrupt. The normalizer doubles as a validator.
+    ///
+    /// # Errors
+    ///
+    /// [`LoneCarriageReturn`] with the byte offset of the first lone CR.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::{LineEnding, LoneCarriageReturn};
+    ///
+    /// assert_eq!(LineEnding::CrLf.try_normalize("a\nb\r\n").unwrap(), "a\r\nb\r\n");
+    /// assert_eq!(LineEnding::CrLf.try_normalize("a\rb"), Err(LoneCarriageReturn { offset: 1 }));
+    /// ```
+    pub fn try_normalize(self, text: &str) -> Result<Cow<'_, str>, LoneCarriageReturn> {
+        Options { lone_cr: LoneCr::Error, ..Options::new(self) }.apply(text)
+    }
+
+    /// Like [`normalize`](Self::normalize) for CSV: record terminators are normalized, while
+    /// CR and LF inside double-quoted fields, such as the line breaks of a spreadsheet cell,
+    /// are left as they are.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// let csv = "id,note\r\n1,\"two\nlines\"\r\n";
+    /// assert_eq!(LineEnding::Lf.normalize_csv(csv), "id,note\n1,\"two\nlines\"\n");
+    /// assert_eq!(LineEnding::CrLf.normalize_csv("1,\"a\nb\"\n"), "1,\"a\nb\"\r\n");
+    /// ```
+    pub fn normalize_csv(self, text: &str) -> Cow<'_, str> {
+        crate::csv::normalize(text, self)
+    }
+
+    /// Like [`normalize`](Self::normalize), allocating an owned result according to `capacity`.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::{Capacity, LineEnding};
+    ///
+    /// let text = LineEnding::Lf.normalize_with_capacity("a\r\nb", Capacity::Exact);
+    /// assert_eq!(text.len(), text.into_owned().capacity());
+    /// ```
+    pub fn normalize_with_capacity(self, text: &str, capacity: Capacity) -> Cow<'_, str> {
+        let mut out = Vec::with_capacity(match capacity {
+            Capacity::Exact => 0,
+            _ => capacity.for_len(text.len()),
+        });
+        if !self.normalize_bytes_into(text.as_bytes(), &mut out) {
+            return Cow::Borrowed(text);
+        }
+        capacity.apply(&mut out);
+        // Only ASCII newline bytes were rewritten, so the result is still UTF-8.
+        Cow::Owned(unsafe { String::from_utf8_unchecked(out) })
+    }
+
+    /// Appends `text`, normalized to this line ending, to `out`, reusing its capacity.
+    ///
+    /// Returns `true` if any newline was rewritten.
+    ///
+    /// Example:
+    /// ```
+    /// use newline_normalizer::LineEnding;
+    ///
+    /// let mut out = String::from("> ");
+    /// assert!(LineEnding::CrLf.normalize_into("a\nb", &mut out));
+    /// assert!(!LineEnding::CrLf.normalize_into("\r\n", &mut out));
+    /// assert_eq!(out, "> a\r\nb\r\n");
+    /// ```
+    pub fn normalize_into(self, text: &str, out: &mut String) -> bool {
+        // Only ASCII newline bytes are rewritten, so `out` stays UTF-8.
+        let bytes = unsafe { out.as_mut_vec() };
+        let changed = self.normalize_bytes_into(text.as_bytes(), bytes);
+        if !changed {
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        changed
+    }
+
+    pub(crate) fn normalize_bytes_into(self, src: &[u8], out: &mut Vec<u8>) -> bool {
+        match self {
+            LineEnding::Lf => kernel::to_unix_into(src, out),
+            LineEnding::CrLf => kernel::to_dos_into(src, out),
+        }
+    }
+}
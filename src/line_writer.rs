@@ -0,0 +1,230 @@
+//! Line-buffered `io::Write` adapter normalizing newlines.
+
+use std::io::{self, Write};
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats, StreamConfig};
+
+/// Like [`NormalizingWriter`](crate::NormalizingWriter), but only passes on complete lines.
+///
+/// - Normalized output is buffered until a newline is written, then everything up to the
+///   last newline is written to the inner writer and the inner writer is flushed.
+/// - A CRLF is never split between two writes to the inner writer.
+/// - A line longer than one chunk of [`StreamConfig`] is written in pieces.
+/// - The rest of the buffer is written on [`flush`](Write::flush) and, best-effort, on drop.
+///
+/// Example:
+/// ```
+/// use std::io::Write;
+/// use newline_normalizer::NormalizingLineWriter;
+///
+/// let mut writer = NormalizingLineWriter::dos(Vec::new());
+/// writer.write_all(b"one\ntw").unwrap();
+/// assert_eq!(writer.get_ref(), b"one\r\n");
+/// writer.flush().unwrap();
+/// assert_eq!(writer.get_ref(), b"one\r\ntw");
+/// ```
+#[derive(Debug)]
+pub struct NormalizingLineWriter<W: Write> {
+    // Only `None` once taken by `into_inner` or `into_parts`.
+    inner: Option<W>,
+    engine: Engine,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl<W: Write> NormalizingLineWriter<W> {
+    /// Creates a writer producing `ending`.
+    pub fn new(inner: W, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new())
+    }
+
+    /// Creates a writer producing `ending` whose buffering is bounded by `config`.
+    pub fn with_config(inner: W, ending: LineEnding, config: StreamConfig) -> Self {
+        NormalizingLineWriter {
+            inner: Some(inner),
+            engine: Engine::new(ending),
+            buffer: Vec::new(),
+            chunk_size: config.get_chunk_size(),
+        }
+    }
+
+    /// Creates a writer producing Unix newlines (`\n`).
+    pub fn unix(inner: W) -> Self {
+        Self::new(inner, LineEnding::Lf)
+    }
+
+    /// Creates a writer producing DOS newlines (`\r\n`).
+    pub fn dos(inner: W) -> Self {
+        Self::new(inner, LineEnding::CrLf)
+    }
+
+    /// The line ending this writer produces.
+    pub fn ending(&self) -> LineEnding {
+        self.engine.ending()
+    }
+
+    /// Counters for everything normalized so far, including buffered output.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// The normalized output not yet written to the inner writer.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("inner writer taken")
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("inner writer taken")
+    }
+
+    /// Writes out the buffer and returns the underlying writer.
+    ///
+    /// On error the writer is dropped together with any output it did not accept.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.write_out(self.buffer.len())?;
+        Ok(self.inner.take().expect("inner writer taken"))
+    }
+
+    /// Returns the underlying writer and the buffered output, without writing anything.
+    pub fn into_parts(mut self) -> (W, Vec<u8>) {
+        let buffer = std::mem::take(&mut self.buffer);
+        (self.inner.take().expect("inner writer taken"), buffer)
+    }
+
+    /// Writes the first `end` buffered bytes, dropping whatever was written even on error.
+    fn write_out(&mut self, end: usize) -> io::Result<()> {
+        let inner = self.inner.as_mut().expect("inner writer taken");
+        let mut written = 0;
+        let result = loop {
+            if written == end {
+                break Ok(());
+            }
+            match inner.write(&self.buffer[written..end]) {
+                Ok(0) => break Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write buffered lines")),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buffer.drain(..written);
+        result
+    }
+
+    /// Writes out every complete line, or everything once a chunk's worth is buffered.
+    fn write_lines(&mut self) -> io::Result<()> {
+        let end = match crate::search::memrchr(b'\n', &self.buffer) {
+            Some(last) => last + 1,
+            None if self.buffer.len() >= self.chunk_size => self.buffer.len(),
+            None => return Ok(()),
+        };
+        self.write_out(end)?;
+        self.inner.as_mut().expect("inner writer taken").flush()
+    }
+}
+
+impl<W: Write> Write for NormalizingLineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Retry lines left over from a failed write before accepting more.
+        if crate::search::memchr(b'\n', &self.buffer).is_some() || self.buffer.len() >= self.chunk_size {
+            self.write_lines()?;
+        }
+
+        let buf = &buf[..buf.len().min(self.chunk_size)];
+        self.engine.normalize_into(buf, &mut self.buffer);
+        // The input is accepted either way; a failure here is retried on the next call.
+        let _ = self.write_lines();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_out(self.buffer.len())?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for NormalizingLineWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_out(self.buffer.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records every `write` call made to it.
+    #[derive(Default)]
+    struct Recorder {
+        writes: Vec<Vec<u8>>,
+        fail: bool,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::Error::new(io::ErrorKind::Other, "unavailable"));
+            }
+            self.writes.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn holds_partial_lines_back() {
+        let mut writer = NormalizingLineWriter::dos(Recorder::default());
+        for byte in b"a\r\nbc\rd" {
+            writer.write_all(&[*byte]).unwrap();
+        }
+        assert_eq!(writer.get_ref().writes, [b"a\r\n".to_vec(), b"bc\r\n".to_vec()]);
+        assert_eq!(writer.buffer(), b"d");
+        let (_, buffer) = writer.into_parts();
+        assert_eq!(buffer, b"d");
+    }
+
+    #[test]
+    fn writes_up_to_last_newline() {
+        let mut writer = NormalizingLineWriter::unix(Vec::new());
+        writer.write_all(b"a\r\nb\rc").unwrap();
+        assert_eq!(writer.get_ref(), b"a\nb\n");
+        assert_eq!(writer.into_inner().unwrap(), b"a\nb\nc");
+    }
+
+    #[test]
+    fn long_lines_are_written_per_chunk() {
+        let config = StreamConfig::new().chunk_size(4);
+        let mut writer = NormalizingLineWriter::with_config(Vec::new(), LineEnding::CrLf, config);
+        writer.write_all(b"abcdefgh\nij").unwrap();
+        assert_eq!(writer.get_ref(), b"abcdefgh\r\n");
+        assert_eq!(writer.buffer(), b"ij");
+    }
+
+    #[test]
+    fn failed_lines_are_retried() {
+        let mut writer = NormalizingLineWriter::unix(Recorder { fail: true, ..Recorder::default() });
+        assert_eq!(writer.write(b"a\n").unwrap(), 2);
+        assert!(writer.write(b"b\n").is_err());
+        writer.get_mut().fail = false;
+        writer.write_all(b"b\n").unwrap();
+        assert_eq!(writer.get_ref().writes.concat(), b"a\nb\n");
+    }
+
+    #[test]
+    fn drop_writes_the_rest() {
+        let mut out = Vec::new();
+        NormalizingLineWriter::unix(&mut out).write_all(b"a\rb").unwrap();
+        assert_eq!(out, b"a\nb");
+    }
+}
@@ -0,0 +1,379 @@
+//! Sans-IO newline normalization.
+//!
+//! [`Machine`] owns no buffers and performs no I/O: the caller feeds it input and output
+//! slices and gets told how far it got. It only depends on `core`, so it works without
+//! `std` and inside any event loop or host.
+
+use crate::{LineEnding, Stats};
+
+/// Why [`Machine::feed`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// All input was consumed and all output owed so far was written. Feed more input.
+    NeedsInput,
+    /// The output slice is full. Drain it and call `feed` again with the unconsumed input.
+    OutputFull,
+}
+
+/// Result of one [`Machine::feed`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feed {
+    /// Bytes of input consumed.
+    pub consumed: usize,
+    /// Bytes written to the start of the output slice.
+    pub written: usize,
+    /// Whether more input or more output space is needed.
+    pub status: Status,
+}
+
+/// Streaming newline normalizer driven by caller-provided buffers.
+///
+/// - Any output slice of at least one byte makes progress; a CRLF that does not fit is
+///   split and its LF written on the next call.
+/// - A CR ending one input chunk pairs with an LF starting the next.
+/// - Nothing is held back, so there is no finishing step: once `feed` returns
+///   [`Status::NeedsInput`] for the last chunk, the output is complete.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{LineEnding, Machine, Status};
+///
+/// let mut machine = Machine::new(LineEnding::CrLf);
+/// let mut out = [0u8; 3];
+/// let feed = machine.feed(b"a\nb\n", &mut out);
+/// assert_eq!((feed.consumed, feed.written, feed.status), (2, 3, Status::OutputFull));
+/// assert_eq!(&out[..3], b"a\r\n");
+///
+/// let feed = machine.feed(b"b\n", &mut out);
+/// assert_eq!((feed.consumed, feed.written, feed.status), (2, 3, Status::NeedsInput));
+/// assert_eq!(&out[..3], b"b\r\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Machine {
+    ending: LineEnding,
+    after_cr: bool,
+    pending_lf: bool,
+    stats: Stats,
+}
+
+impl Machine {
+    /// Creates a machine producing `ending`.
+    pub fn new(ending: LineEnding) -> Self {
+        Machine { ending, after_cr: false, pending_lf: false, stats: Stats::default() }
+    }
+
+    /// The line ending this machine produces.
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    /// Counters for everything fed so far.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Returns `true` if output from earlier input is still owed.
+    pub fn has_pending_output(&self) -> bool {
+        self.pending_lf
+    }
+
+    /// Forgets all state, as if the machine were newly created.
+    pub fn reset(&mut self) {
+        *self = Machine::new(self.ending);
+    }
+
+    /// Normalizes as much of `input` into `output` as fits.
+    pub fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Feed {
+        let mut consumed = 0;
+        let mut written = 0;
+        let status = self.feed_inner(input, output, &mut consumed, &mut written);
+        self.stats.bytes_read += consumed as u64;
+        self.stats.bytes_written += written as u64;
+        Feed { consumed, written, status }
+    }
+
+    /// Takes in `input` without copying it if it is already in the target form, counting it
+    /// as [`feed`](Machine::feed) would. Returns `false`, doing nothing, if it is not, or if
+    /// the line break ending the input before it is not settled yet.
+    #[cfg(any(feature = "stream", feature = "tower"))]
+    pub(crate) fn pass_normalized(&mut self, input: &[u8]) -> bool {
+        if self.after_cr || self.pending_lf || !crate::kernel::is_normalized(input, self.ending == LineEnding::CrLf) {
+            return false;
+        }
+        let newlines = crate::search::memchr_iter(b'\n', input).count() as u64;
+        match self.ending {
+            LineEnding::Lf => self.stats.lf += newlines,
+            LineEnding::CrLf => self.stats.crlf += newlines,
+        }
+        self.stats.bytes_read += input.len() as u64;
+        self.stats.bytes_written += input.len() as u64;
+        true
+    }
+
+    fn feed_inner(&mut self, input: &[u8], output: &mut [u8], consumed: &mut usize, written: &mut usize) -> Status {
+        if self.pending_lf {
+            let Some(first) = output.first_mut() else {
+                return Status::OutputFull;
+            };
+            *first = b'\n';
+            *written = 1;
+            self.pending_lf = false;
+        }
+
+        if self.after_cr {
+            let Some(&first) = input.first() else {
+                return Status::NeedsInput;
+            };
+            self.after_cr = false;
+            if first == b'\n' {
+                // The CR ending the previous chunk was counted alone; it was half of a CRLF.
+                self.stats.cr -= 1;
+                self.stats.crlf += 1;
+                if self.ending == LineEnding::CrLf {
+                    self.stats.converted -= 1;
+                }
+                *consumed = 1;
+            }
+        }
+
+        let ending = self.ending.as_bytes();
+        while *consumed < input.len() {
+            let rest = &input[*consumed..];
+            let found = crate::search::memchr2(b'\n', b'\r', rest);
+            let plain = found.unwrap_or(rest.len());
+
+            let space = output.len() - *written;
+            let copied = plain.min(space);
+            output[*written..*written + copied].copy_from_slice(&rest[..copied]);
+            *consumed += copied;
+            *written += copied;
+            if copied < plain {
+                return Status::OutputFull;
+            }
+            if found.is_none() {
+                break;
+            }
+
+            if *written == output.len() {
+                return Status::OutputFull;
+            }
+            let (len, kind) = if rest[plain] == b'\n' {
+                (1, LineKind::Lf)
+            } else {
+                match rest.get(plain + 1) {
+                    Some(b'\n') => (2, LineKind::CrLf),
+                    Some(_) => (1, LineKind::Cr),
+                    None => {
+                        self.after_cr = true;
+                        (1, LineKind::Cr)
+                    }
+                }
+            };
+            self.count(kind);
+            *consumed += len;
+
+            if output.len() - *written < ending.len() {
+                output[*written] = ending[0];
+                *written += 1;
+                self.pending_lf = true;
+                return Status::OutputFull;
+            }
+            output[*written..*written + ending.len()].copy_from_slice(ending);
+            *written += ending.len();
+        }
+
+        Status::NeedsInput
+    }
+
+    fn count(&mut self, kind: LineKind) {
+        match kind {
+            LineKind::Lf => self.stats.lf += 1,
+            LineKind::CrLf => self.stats.crlf += 1,
+            LineKind::Cr => self.stats.cr += 1,
+        }
+        let matches = matches!(
+            (kind, self.ending),
+            (LineKind::Lf, LineEnding::Lf) | (LineKind::CrLf, LineEnding::CrLf)
+        );
+        if !matches {
+            self.stats.converted += 1;
+        }
+    }
+}
+
+/// A [`Machine`] with a fixed output block of `N` bytes, for targets without an allocator.
+///
+/// - The block lives inline, so `N` trades stack or static memory for fewer, larger
+///   calls to the sink. On cache-less cores a few hundred bytes is usually enough.
+/// - Every slice handed to the sink is between 1 and `N` bytes long.
+/// - `N` must be at least 1; `BlockMachine<0>` fails to compile when constructed.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{BlockMachine, LineEnding};
+///
+/// let mut machine = BlockMachine::<4>::new(LineEnding::Lf);
+/// let mut out = Vec::new();
+/// machine.push(b"one\r\ntwo\r", |block| {
+///     assert!(block.len() <= 4);
+///     out.extend_from_slice(block);
+///     Ok::<(), ()>(())
+/// }).unwrap();
+/// assert_eq!(out, b"one\ntwo\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlockMachine<const N: usize = 256> {
+    machine: Machine,
+    block: [u8; N],
+}
+
+impl<const N: usize> BlockMachine<N> {
+    /// Size of the output block.
+    pub const BLOCK_SIZE: usize = N;
+
+    const NOT_EMPTY: () = assert!(N > 0, "BlockMachine needs a block of at least one byte");
+
+    /// Creates a machine producing `ending`.
+    #[allow(clippy::let_unit_value)]
+    pub fn new(ending: LineEnding) -> Self {
+        let () = Self::NOT_EMPTY;
+        BlockMachine { machine: Machine::new(ending), block: [0; N] }
+    }
+
+    /// The underlying machine, for its line ending and counters.
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Forgets all state, as if the machine were newly created.
+    pub fn reset(&mut self) {
+        self.machine.reset();
+    }
+
+    /// Normalizes all of `input`, handing the output to `sink` a block at a time.
+    ///
+    /// Stops at the first error from `sink`. Output already handed over stays counted, but
+    /// the machine is then out of step with the input and should be reset.
+    pub fn push<E, F>(&mut self, mut input: &[u8], mut sink: F) -> Result<(), E>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        loop {
+            let feed = self.machine.feed(input, &mut self.block);
+            input = &input[feed.consumed..];
+            if feed.written > 0 {
+                sink(&self.block[..feed.written])?;
+            }
+            if feed.status == Status::NeedsInput {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LineKind {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drives the machine with tiny input and output slices.
+    fn drive(ending: LineEnding, input: &[u8], in_size: usize, out_size: usize) -> (Vec<u8>, Stats) {
+        let mut machine = Machine::new(ending);
+        let mut out = Vec::new();
+        let mut buf = vec![0; out_size];
+        for chunk in input.chunks(in_size) {
+            let mut chunk = chunk;
+            loop {
+                let feed = machine.feed(chunk, &mut buf);
+                out.extend_from_slice(&buf[..feed.written]);
+                chunk = &chunk[feed.consumed..];
+                if feed.status == Status::NeedsInput {
+                    break;
+                }
+            }
+        }
+        assert!(!machine.has_pending_output());
+        (out, machine.stats())
+    }
+
+    #[test]
+    fn every_input_and_output_size() {
+        let input = b"ab\r\ncd\ref\n\r\r\ng\r";
+        for in_size in 1..=input.len() {
+            for out_size in 1..8 {
+                let (out, stats) = drive(LineEnding::CrLf, input, in_size, out_size);
+                assert_eq!(out, b"ab\r\ncd\r\nef\r\n\r\n\r\ng\r\n", "in {in_size} out {out_size}");
+                assert_eq!((stats.lf, stats.crlf, stats.cr, stats.converted), (1, 2, 3, 4));
+
+                let (out, _) = drive(LineEnding::Lf, input, in_size, out_size);
+                assert_eq!(out, b"ab\ncd\nef\n\n\ng\n", "in {in_size} out {out_size}");
+            }
+        }
+    }
+
+    /// Pushes `input` through a `BlockMachine<N>` in chunks of `in_size`.
+    fn push_blocks<const N: usize>(ending: LineEnding, input: &[u8], in_size: usize) -> Vec<u8> {
+        let mut machine = BlockMachine::<N>::new(ending);
+        let mut out = Vec::new();
+        for chunk in input.chunks(in_size) {
+            machine
+                .push(chunk, |block| {
+                    assert!((1..=N).contains(&block.len()));
+                    out.extend_from_slice(block);
+                    Ok::<(), ()>(())
+                })
+                .unwrap();
+        }
+        assert!(!machine.machine().has_pending_output());
+        out
+    }
+
+    #[test]
+    fn block_machine_matches_machine() {
+        let input = b"ab\r\ncd\ref\n\r\r\ng\r";
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            for in_size in 1..=input.len() {
+                let (expected, _) = drive(ending, input, in_size, 64);
+                assert_eq!(push_blocks::<1>(ending, input, in_size), expected, "{ending:?} {in_size}");
+                assert_eq!(push_blocks::<3>(ending, input, in_size), expected, "{ending:?} {in_size}");
+                assert_eq!(push_blocks::<256>(ending, input, in_size), expected, "{ending:?} {in_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn block_machine_stops_at_sink_errors() {
+        let mut machine: BlockMachine = BlockMachine::new(LineEnding::CrLf);
+        assert_eq!(BlockMachine::<256>::BLOCK_SIZE, 256);
+        let mut calls = 0;
+        let result = machine.push(&[b'\n'; 1000], |_| {
+            calls += 1;
+            Err("full")
+        });
+        assert_eq!((result, calls), (Err("full"), 1));
+        assert_eq!(machine.machine().stats().bytes_written, 256);
+    }
+
+    #[test]
+    fn empty_output_makes_no_progress() {
+        let mut machine = Machine::new(LineEnding::Lf);
+        let feed = machine.feed(b"abc", &mut []);
+        assert_eq!((feed.consumed, feed.written, feed.status), (0, 0, Status::OutputFull));
+    }
+
+    #[test]
+    fn pending_lf_is_written_without_input() {
+        let mut machine = Machine::new(LineEnding::CrLf);
+        let mut out = [0u8; 1];
+        assert_eq!(machine.feed(b"\n", &mut out).status, Status::OutputFull);
+        assert!(machine.has_pending_output());
+        let feed = machine.feed(b"", &mut out);
+        assert_eq!((feed.written, feed.status, out), (1, Status::NeedsInput, *b"\n"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Mail bodies with the strict CRLF line endings that RFC 5322 and SMTP require.
+
+use alloc::borrow::Cow;
+
+use crate::search::memchr2_iter;
+use crate::LineEnding;
+
+/// What [`normalize_mail_body`] or [`check_mail_body`] found in a body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MailReport {
+    /// Bare LFs, which [`normalize_mail_body`] turns into CRLFs.
+    pub bare_lf: usize,
+    /// Lone CRs, which [`normalize_mail_body`] turns into CRLFs.
+    pub bare_cr: usize,
+    /// Lines longer than [`MailReport::MAX_LINE_LENGTH`]. No line ending fixes those; the
+    /// body needs a transfer encoding such as quoted-printable or base64.
+    pub long_lines: usize,
+    /// The zero-based number of the first long line.
+    pub first_long_line: Option<usize>,
+}
+
+impl MailReport {
+    /// The longest line RFC 5322 allows, in bytes without the CRLF.
+    pub const MAX_LINE_LENGTH: usize = 998;
+
+    /// Returns `true` if the body had only CRLF line endings and no long line.
+    pub fn is_compliant(&self) -> bool {
+        self.bare_lf == 0 && self.bare_cr == 0 && self.long_lines == 0
+    }
+}
+
+/// Converts `body` to CRLF line endings, as SMTP servers expect, and reports the bare LFs
+/// and CRs it fixed and the long lines it could not.
+///
+/// The result has no CR or LF outside a CRLF. Lines keep their numbers, so
+/// [`MailReport::first_long_line`] points into both texts.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::normalize_mail_body;
+///
+/// let (body, report) = normalize_mail_body("Hello,\n\nsee you.\r\n");
+/// assert_eq!(body, "Hello,\r\n\r\nsee you.\r\n");
+/// assert_eq!((report.bare_lf, report.bare_cr, report.long_lines), (2, 0, 0));
+/// ```
+pub fn normalize_mail_body(body: &str) -> (Cow<'_, str>, MailReport) {
+    let report = check_mail_body(body);
+    let body = if report.bare_lf == 0 && report.bare_cr == 0 { Cow::Borrowed(body) } else { LineEnding::CrLf.normalize(body) };
+    (body, report)
+}
+
+/// Like [`normalize_mail_body`], only reporting what is wrong with `body`.
+pub fn check_mail_body<T: AsRef<[u8]> + ?Sized>(body: &T) -> MailReport {
+    let bytes = body.as_ref();
+    let mut report = MailReport::default();
+    let (mut line, mut start) = (0, 0);
+    let mut end_line = |report: &mut MailReport, start: usize, end: usize| {
+        if end - start > MailReport::MAX_LINE_LENGTH {
+            report.long_lines += 1;
+            report.first_long_line.get_or_insert(line);
+        }
+        line += 1;
+    };
+    for found in memchr2_iter(b'\n', b'\r', bytes) {
+        if found < start {
+            // The LF of a CRLF.
+            continue;
+        }
+        end_line(&mut report, start, found);
+        start = match bytes[found] {
+            b'\n' => {
+                report.bare_lf += 1;
+                found + 1
+            }
+            _ if bytes.get(found + 1) == Some(&b'\n') => found + 2,
+            _ => {
+                report.bare_cr += 1;
+                found + 1
+            }
+        };
+    }
+    end_line(&mut report, start, bytes.len());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    #[test]
+    fn fixes_bare_endings() {
+        let (body, report) = normalize_mail_body("a\rb\nc\r\n\r\r\n");
+        assert_eq!(body, "a\r\nb\r\nc\r\n\r\n\r\n");
+        assert_eq!((report.bare_lf, report.bare_cr), (1, 2));
+        let fixed = check_mail_body(&*body);
+        assert!(fixed.is_compliant(), "{fixed:?}");
+        assert!(matches!(normalize_mail_body("ok\r\n").0, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn reports_long_lines() {
+        let long = "x".repeat(MailReport::MAX_LINE_LENGTH + 1);
+        let fits = "y".repeat(MailReport::MAX_LINE_LENGTH);
+        let body = [fits.as_str(), &long, "z", &long].join("\r\n") + "\n";
+        let report = check_mail_body(&body);
+        assert_eq!((report.long_lines, report.first_long_line, report.bare_lf), (2, Some(1), 1));
+        assert!(!report.is_compliant());
+        assert!(check_mail_body(&String::new()).is_compliant());
+    }
+}
@@ -0,0 +1,272 @@
+//! Normalization into a reused buffer.
+
+use alloc::vec::Vec;
+
+use crate::options::Options;
+use crate::{BomPolicy, CrCrLf, FinalNewline, LineEnding, LoneCarriageReturn, LoneCr};
+
+/// Normalizes many strings, reusing one output buffer across calls.
+///
+/// - Each call returns either the input itself, when nothing needs changing, or a view of
+///   the internal buffer, which is overwritten by the next call.
+/// - The buffer only grows, so after warming up, normalizing stops allocating.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::Normalizer;
+///
+/// let mut normalizer = Normalizer::new();
+/// for message in ["one\r\n", "two\n", "three\r"] {
+///     let unix = normalizer.unix(message);
+///     assert!(unix.ends_with('\n') && !unix.contains('\r'));
+/// }
+/// assert_eq!(normalizer.dos("a\nb"), "a\r\nb");
+/// ```
+///
+/// [`unix`](Normalizer::unix), [`dos`](Normalizer::dos) and
+/// [`normalize`](Normalizer::normalize) only convert line endings. [`apply`](Normalizer::apply)
+/// also applies the options of a [`NormalizerBuilder`], and converts to LF for a normalizer
+/// that was not built with one.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    scratch: Vec<u8>,
+    options: Options,
+}
+
+impl Normalizer {
+    /// Creates a normalizer with an empty buffer.
+    pub fn new() -> Self {
+        Normalizer::with_capacity(0)
+    }
+
+    /// Creates a normalizer whose buffer holds `capacity` bytes before it needs to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        NormalizerBuilder::new(LineEnding::Lf).capacity(capacity).build()
+    }
+
+    /// Starts configuring a normalizer that converts to `ending`.
+    pub fn builder(ending: LineEnding) -> NormalizerBuilder {
+        NormalizerBuilder::new(ending)
+    }
+
+    /// Normalizes `text` to Unix newlines (`\n`).
+    pub fn unix<'a>(&'a mut self, text: &'a str) -> &'a str {
+        self.normalize(text, LineEnding::Lf)
+    }
+
+    /// Normalizes `text` to DOS newlines (`\r\n`).
+    pub fn dos<'a>(&'a mut self, text: &'a str) -> &'a str {
+        self.normalize(text, LineEnding::CrLf)
+    }
+
+    /// Normalizes `text` to `ending`.
+    pub fn normalize<'a>(&'a mut self, text: &'a str, ending: LineEnding) -> &'a str {
+        self.scratch.clear();
+        if !ending.normalize_bytes_into(text.as_bytes(), &mut self.scratch) {
+            return text;
+        }
+        // Only ASCII newline bytes were rewritten, so the buffer is still UTF-8.
+        unsafe { core::str::from_utf8_unchecked(&self.scratch) }
+    }
+
+    /// Normalizes `text` with every option the normalizer was built with.
+    ///
+    /// # Panics
+    ///
+    /// If the normalizer was built with [`LoneCr::Error`] or [`CrCrLf::Error`] and `text`
+    /// has what they reject; call [`try_apply`](Normalizer::try_apply) then.
+    pub fn apply<'a>(&'a mut self, text: &'a str) -> &'a str {
+        match self.try_apply(text) {
+            Ok(text) => text,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Like [`apply`](Normalizer::apply), returning an error for a lone CR instead of
+    /// panicking.
+    ///
+    /// # Errors
+    ///
+    /// [`LoneCarriageReturn`] with the byte offset of the first lone CR, if the normalizer
+    /// was built with [`LoneCr::Error`], or of the first `\r\r\n` with [`CrCrLf::Error`].
+    pub fn try_apply<'a>(&'a mut self, text: &'a str) -> Result<&'a str, LoneCarriageReturn> {
+        self.scratch.clear();
+        if !self.options.apply_into(text.as_bytes(), &mut self.scratch)? {
+            return Ok(text);
+        }
+        // Only whole newlines and byte order marks were replaced or removed, so the buffer
+        // is still UTF-8.
+        Ok(unsafe { core::str::from_utf8_unchecked(&self.scratch) })
+    }
+
+    /// Bytes the buffer holds before it needs to grow.
+    pub fn capacity(&self) -> usize {
+        self.scratch.capacity()
+    }
+
+    /// Releases the buffer's memory.
+    pub fn shrink(&mut self) {
+        self.scratch = Vec::new();
+    }
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configures a [`Normalizer`], one option at a time.
+///
+/// Every option starts out doing what the rest of the crate does: only the line endings
+/// change, CR, LF and CRLF being the newlines. [`Normalizer::apply`] then makes all the
+/// configured changes in a single pass.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{BomPolicy, FinalNewline, LineEnding, LoneCr, Normalizer};
+///
+/// let mut normalizer = Normalizer::builder(LineEnding::CrLf)
+///     .unicode(true)
+///     .bom(BomPolicy::Strip)
+///     .lone_cr(LoneCr::Keep)
+///     .final_newline(FinalNewline::Ensure)
+///     .build();
+/// assert_eq!(normalizer.apply("\u{FEFF}one\u{2028}two\rthree\n\n"), "one\r\ntwo\rthree\r\n");
+/// assert_eq!(normalizer.apply("done"), "done\r\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct NormalizerBuilder {
+    options: Options,
+    capacity: usize,
+}
+
+impl NormalizerBuilder {
+    /// Starts with the defaults, converting to `ending`.
+    pub fn new(ending: LineEnding) -> Self {
+        NormalizerBuilder { options: Options::new(ending), capacity: 0 }
+    }
+
+    /// Whether to convert each text to the line ending most of its newlines use instead, as
+    /// [`normalize_to_dominant`](crate::normalize_to_dominant) does, the `ending` of the
+    /// builder only going to text with no LF or CRLF. Off by default.
+    pub fn dominant(mut self, dominant: bool) -> Self {
+        self.options.dominant = dominant;
+        self
+    }
+
+    /// Whether NEL, LINE SEPARATOR and PARAGRAPH SEPARATOR are newlines too, as in
+    /// [`LineEnding::normalize_unicode`]. Off by default.
+    pub fn unicode(mut self, unicode: bool) -> Self {
+        self.options.unicode = unicode;
+        self
+    }
+
+    /// What to do with a UTF-8 byte order mark. Kept by default.
+    pub fn bom(mut self, bom: BomPolicy) -> Self {
+        self.options.bom = bom;
+        self
+    }
+
+    /// What to do with a CR that is not part of a CRLF. Converted by default; with
+    /// [`LoneCr::Error`], use [`Normalizer::try_apply`].
+    pub fn lone_cr(mut self, lone_cr: LoneCr) -> Self {
+        self.options.lone_cr = lone_cr;
+        self
+    }
+
+    /// What to do with `\r\r\n` and longer runs of CRs before an LF. Two newlines by default;
+    /// with [`CrCrLf::Error`], use [`Normalizer::try_apply`].
+    pub fn cr_cr_lf(mut self, cr_cr_lf: CrCrLf) -> Self {
+        self.options.cr_cr_lf = cr_cr_lf;
+        self
+    }
+
+    /// What to do with the newlines that end the text. Converted by default.
+    pub fn final_newline(mut self, final_newline: FinalNewline) -> Self {
+        self.options.final_newline = final_newline;
+        self
+    }
+
+    /// Collapses every run of more than `max` blank lines, lines with nothing between their
+    /// newlines, to `max` of them. Off by default.
+    pub fn max_blank_lines(mut self, max: usize) -> Self {
+        self.options.max_blank_lines = Some(max);
+        self
+    }
+
+    /// Whether to remove the spaces and tabs at the end of every line. With
+    /// [`max_blank_lines`](Self::max_blank_lines), lines of only spaces and tabs then count
+    /// as blank. Off by default.
+    pub fn trim_trailing_whitespace(mut self, trim: bool) -> Self {
+        self.options.trim_trailing_whitespace = trim;
+        self
+    }
+
+    /// Bytes the normalizer's buffer holds before it needs to grow.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Creates the normalizer.
+    pub fn build(&self) -> Normalizer {
+        Normalizer { scratch: Vec::with_capacity(self.capacity), options: self.options }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_input_when_unchanged() {
+        let mut normalizer = Normalizer::new();
+        let text = "a\nb";
+        assert!(core::ptr::eq(normalizer.unix(text), text));
+        assert_eq!(normalizer.capacity(), 0);
+    }
+
+    #[test]
+    fn reuses_the_buffer() {
+        let mut normalizer = Normalizer::new();
+        let long = "a long line\n".repeat(20);
+        assert_eq!(normalizer.dos(&long), "a long line\r\n".repeat(20));
+        let capacity = normalizer.capacity();
+        assert_eq!(normalizer.unix("short\r\n"), "short\n");
+        assert_eq!(normalizer.dos("x\ry"), "x\r\ny");
+        assert_eq!(normalizer.capacity(), capacity);
+
+        normalizer.shrink();
+        assert_eq!(normalizer.capacity(), 0);
+    }
+
+    #[test]
+    fn built_options_combine() {
+        let mut normalizer = Normalizer::builder(LineEnding::Lf).bom(BomPolicy::Add).final_newline(FinalNewline::Strip).build();
+        assert_eq!(normalizer.apply("a\r\rb\r\n\r"), "\u{FEFF}a\n\nb");
+        assert_eq!(normalizer.apply("\n\n"), "\u{FEFF}");
+
+        let mut normalizer = Normalizer::builder(LineEnding::Lf).lone_cr(LoneCr::Keep).unicode(true).build();
+        let text = "a\rb\u{85}c\r";
+        assert_eq!(normalizer.apply(text), "a\rb\nc\r");
+        let kept = "a\rb\n";
+        assert!(core::ptr::eq(normalizer.apply(kept), kept));
+
+        let mut normalizer = Normalizer::builder(LineEnding::CrLf).dominant(true).final_newline(FinalNewline::Ensure).build();
+        assert_eq!(normalizer.apply("a\nb\nc\r\nd"), "a\nb\nc\nd\n");
+        assert_eq!(normalizer.apply("a\r\nb\r\nc\n"), "a\r\nb\r\nc\r\n");
+        assert_eq!(normalizer.apply("a"), "a\r\n");
+    }
+
+    #[test]
+    fn ensures_exactly_one_final_newline() {
+        let mut normalizer = Normalizer::builder(LineEnding::CrLf).final_newline(FinalNewline::Ensure).build();
+        for (text, expected) in [("a", "a\r\n"), ("a\n\r\n\r", "a\r\n"), ("a\r\n", "a\r\n"), ("", ""), ("\n\n", "\r\n")] {
+            assert_eq!(normalizer.apply(text), expected, "{text:?}");
+        }
+        let text = "a\r\nb\r\n";
+        assert!(core::ptr::eq(normalizer.apply(text), text));
+        assert_eq!(Normalizer::new().apply("x\r"), "x\n");
+    }
+}
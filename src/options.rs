@@ -0,0 +1,433 @@
+//! The policies a [`NormalizerBuilder`](crate::NormalizerBuilder) combines, and the single
+//! pass that applies them.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::search::{memchr2_iter, memchr3_iter, memchr4_iter, memchr_iter};
+use crate::unicode::{NEL_LEAD, SEPARATOR_LEAD};
+use crate::{analyze, BomPolicy, LineEnding};
+
+/// What to do with a CR that is not followed by an LF.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LoneCr {
+    /// Counts it as a newline and converts it to the target ending, as the rest of the
+    /// crate does.
+    #[default]
+    Newline,
+    /// Leaves it in the text as an ordinary character, as `str::lines` does.
+    Keep,
+    /// Fails with [`LoneCarriageReturn`], for protocols such as HTTP and SMTP where a bare
+    /// CR means the text is corrupt or forged.
+    Error,
+}
+
+/// What to do with CRs doubled in front of an LF, `\r\r\n`, which text converted to CRLF
+/// twice is full of.
+///
+/// Only runs of CRs that end in a CRLF are meant; `\r\r\r\n` counts as one too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CrCrLf {
+    /// Reads the CRs before the last as lone CRs, each handled as the [`LoneCr`] policy
+    /// says, then a CRLF: two newlines by default, as everywhere else in the crate.
+    #[default]
+    Two,
+    /// Reads the whole run as one newline, undoing the double conversion.
+    One,
+    /// Fails with [`LoneCarriageReturn`] at the first CR of the run.
+    Error,
+}
+
+/// The error of the normalizers that reject lone CRs: `offset` is the byte offset of the
+/// first CR not followed by an LF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LoneCarriageReturn {
+    /// The byte offset of the CR in the input.
+    pub offset: usize,
+}
+
+impl fmt::Display for LoneCarriageReturn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lone carriage return at byte {}", self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LoneCarriageReturn {}
+
+/// What to do with the newlines at the end of the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FinalNewline {
+    /// Converts them like any other newline.
+    #[default]
+    Keep,
+    /// Ends non-empty text with exactly one newline, adding one or dropping the extra ones,
+    /// as [`ensure_final_newline`] does.
+    Ensure,
+    /// Removes them all, as [`strip_final_newline`] does.
+    Strip,
+}
+
+/// Normalizes `text` to the line ending most of its newlines already use, so that only the
+/// minority changes: the file keeps its style and becomes consistent. Text with no LF or
+/// CRLF gets `fallback`.
+///
+/// Ties go to LF. Finding the dominant ending costs one extra scan of `text`; see
+/// [`Analysis::dominant`](crate::Analysis::dominant).
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_to_dominant, LineEnding};
+///
+/// assert_eq!(normalize_to_dominant("a\r\nb\r\nc\nd\r", LineEnding::Lf), "a\r\nb\r\nc\r\nd\r\n");
+/// assert_eq!(normalize_to_dominant("a\rb", LineEnding::CrLf), "a\r\nb");
+/// ```
+pub fn normalize_to_dominant(text: &str, fallback: LineEnding) -> Cow<'_, str> {
+    analyze(text).dominant().unwrap_or(fallback).normalize(text)
+}
+
+/// Normalizes `text` to `ending` and ends it with exactly one newline, adding one or
+/// dropping the blank lines at the end, in the same pass. Empty text stays empty.
+///
+/// This is [`FinalNewline::Ensure`] on its own, for POSIX tools and linters that want a
+/// final newline.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{ensure_final_newline, LineEnding};
+///
+/// assert_eq!(ensure_final_newline("a\r\nb", LineEnding::Lf), "a\nb\n");
+/// assert_eq!(ensure_final_newline("a\n\n\n", LineEnding::CrLf), "a\r\n");
+/// ```
+pub fn ensure_final_newline(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    match (Options { final_newline: FinalNewline::Ensure, ..Options::new(ending) }).apply(text) {
+        Ok(text) => text,
+        Err(_) => unreachable!("lone CRs are newlines"),
+    }
+}
+
+/// Normalizes `text` to `ending` and removes every newline at its end, in the same pass,
+/// as shell command substitution does. For text embedded in a single-line context, such as
+/// an environment variable or an HTTP header value.
+///
+/// This is [`FinalNewline::Strip`] on its own.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{strip_final_newline, LineEnding};
+///
+/// assert_eq!(strip_final_newline("a\r\nb\r\n\n", LineEnding::Lf), "a\nb");
+/// assert_eq!(strip_final_newline("a\nb", LineEnding::CrLf), "a\r\nb");
+/// ```
+pub fn strip_final_newline(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    match (Options { final_newline: FinalNewline::Strip, ..Options::new(ending) }).apply(text) {
+        Ok(text) => text,
+        Err(_) => unreachable!("lone CRs are newlines"),
+    }
+}
+
+/// Everything a [`Normalizer`](crate::Normalizer) is configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Options {
+    pub(crate) ending: LineEnding,
+    /// Whether `ending` only applies to text without a dominant ending.
+    pub(crate) dominant: bool,
+    pub(crate) unicode: bool,
+    pub(crate) bom: BomPolicy,
+    pub(crate) lone_cr: LoneCr,
+    pub(crate) final_newline: FinalNewline,
+    /// The most blank lines kept in a row, if runs of them are collapsed.
+    pub(crate) max_blank_lines: Option<usize>,
+    pub(crate) trim_trailing_whitespace: bool,
+    pub(crate) cr_cr_lf: CrCrLf,
+}
+
+impl Options {
+    pub(crate) fn new(ending: LineEnding) -> Self {
+        Options { ending, dominant: false, unicode: false, bom: BomPolicy::Keep, lone_cr: LoneCr::Newline, final_newline: FinalNewline::Keep, max_blank_lines: None, trim_trailing_whitespace: false, cr_cr_lf: CrCrLf::Two }
+    }
+
+    /// Returns `true` if only the line endings change, which the kernels handle alone.
+    fn only_endings(&self) -> bool {
+        *self == Options::new(self.ending)
+    }
+
+    /// Normalizes `text`, borrowing it when nothing changes.
+    pub(crate) fn apply<'a>(&self, text: &'a str) -> Result<Cow<'a, str>, LoneCarriageReturn> {
+        let mut out = Vec::new();
+        if !self.apply_into(text.as_bytes(), &mut out)? {
+            return Ok(Cow::Borrowed(text));
+        }
+        // Only whole newlines and byte order marks were replaced or removed, so the result
+        // is still UTF-8.
+        Ok(Cow::Owned(unsafe { String::from_utf8_unchecked(out) }))
+    }
+
+    /// Appends the normalized form of `text` to `out` and returns `true`, or returns
+    /// `false` and leaves `out` as it is if `text` is already normalized. After an error,
+    /// `out` holds part of the result.
+    pub(crate) fn apply_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<bool, LoneCarriageReturn> {
+        if self.dominant {
+            let ending = analyze(text).dominant().unwrap_or(self.ending);
+            return Options { ending, dominant: false, ..*self }.apply_into(text, out);
+        }
+        if self.only_endings() {
+            return Ok(self.ending.normalize_bytes_into(text, out));
+        }
+        let mut rewrite = Rewrite { text, out, copied: 0, changed: false };
+        let (prefix, skip) = self.bom.edit(text);
+        if !prefix.is_empty() || skip > 0 {
+            rewrite.replace(0, skip, prefix);
+        }
+        let body = skip;
+        let tail = match self.final_newline {
+            FinalNewline::Keep => text.len(),
+            FinalNewline::Ensure | FinalNewline::Strip => self.trailing_newlines(&text[body..]) + body,
+        };
+        let search = &text[body..tail];
+        // LFs already are the target when converting to LF, and need not be visited unless
+        // lines are counted or trimmed.
+        let every_line = self.max_blank_lines.is_some() || self.trim_trailing_whitespace;
+        let ending = if every_line { LineEnding::CrLf } else { self.ending };
+        match (self.unicode, ending) {
+            (false, LineEnding::Lf) => self.endings(&mut rewrite, body, tail, memchr_iter(b'\r', search))?,
+            (false, LineEnding::CrLf) => self.endings(&mut rewrite, body, tail, memchr2_iter(b'\r', b'\n', search))?,
+            (true, LineEnding::Lf) => self.endings(&mut rewrite, body, tail, memchr3_iter(b'\r', NEL_LEAD, SEPARATOR_LEAD, search))?,
+            (true, LineEnding::CrLf) => self.endings(&mut rewrite, body, tail, memchr4_iter([b'\r', b'\n', NEL_LEAD, SEPARATOR_LEAD], search))?,
+        }
+        match self.final_newline {
+            FinalNewline::Keep => {}
+            FinalNewline::Ensure if tail == text.len() => {
+                if tail > body {
+                    rewrite.replace(tail, tail, self.ending.as_bytes());
+                }
+            }
+            FinalNewline::Ensure => {
+                if &text[tail..] != self.ending.as_bytes() {
+                    rewrite.replace(tail, text.len(), self.ending.as_bytes());
+                }
+            }
+            FinalNewline::Strip => {
+                if tail < text.len() {
+                    rewrite.replace(tail, text.len(), b"");
+                }
+            }
+        }
+        Ok(rewrite.finish())
+    }
+
+    /// Converts the newlines among `candidates`, the positions of possible newline starts
+    /// counted from `start`, up to `end`, drops those ending a blank line too many, and
+    /// trims the lines.
+    fn endings(&self, rewrite: &mut Rewrite<'_>, start: usize, end: usize, candidates: impl Iterator<Item = usize>) -> Result<(), LoneCarriageReturn> {
+        let text = &rewrite.text[..end];
+        let mut line_start = start;
+        // Blank lines in a row so far: lines with nothing left once trimmed.
+        let mut blank = 0;
+        for found in candidates.map(|found| found + start) {
+            if found < line_start {
+                continue;
+            }
+            let Some(len) = self.newline_at(text, found)? else {
+                continue;
+            };
+            let content_end = self.trimmed(text, line_start, found);
+            blank = if content_end == line_start { blank + 1 } else { 0 };
+            line_start = found + len;
+            if self.max_blank_lines.map_or(false, |max| blank > max) {
+                rewrite.replace(content_end, line_start, b"");
+            } else if content_end < found || &text[found..line_start] != self.ending.as_bytes() {
+                rewrite.replace(content_end, line_start, self.ending.as_bytes());
+            }
+        }
+        let content_end = self.trimmed(text, line_start, end);
+        if content_end < end {
+            rewrite.replace(content_end, end, b"");
+        }
+        Ok(())
+    }
+
+    /// Where the line from `start` to `end` ends once trailing spaces and tabs are trimmed,
+    /// if they are.
+    fn trimmed(&self, text: &[u8], start: usize, mut end: usize) -> usize {
+        if self.trim_trailing_whitespace {
+            while end > start && matches!(text[end - 1], b' ' | b'\t') {
+                end -= 1;
+            }
+        }
+        end
+    }
+
+    /// The length of the newline starting at `pos`, if one does.
+    fn newline_at(&self, text: &[u8], pos: usize) -> Result<Option<usize>, LoneCarriageReturn> {
+        let next = |offset: usize| text.get(pos + offset).copied();
+        Ok(match text[pos] {
+            b'\n' => Some(1),
+            b'\r' if next(1) == Some(b'\n') => Some(2),
+            b'\r' if self.cr_cr_lf != CrCrLf::Two && doubled_crlf(&text[pos..]) => match self.cr_cr_lf {
+                CrCrLf::Error => return Err(LoneCarriageReturn { offset: pos }),
+                _ => Some(text[pos..].iter().position(|&b| b == b'\n').unwrap_or(0) + 1),
+            },
+            b'\r' => match self.lone_cr {
+                LoneCr::Newline => Some(1),
+                LoneCr::Keep => None,
+                LoneCr::Error => return Err(LoneCarriageReturn { offset: pos }),
+            },
+            NEL_LEAD if self.unicode && next(1) == Some(0x85) => Some(2),
+            SEPARATOR_LEAD if self.unicode && next(1) == Some(0x80) && matches!(next(2), Some(0xA8 | 0xA9)) => Some(3),
+            _ => None,
+        })
+    }
+
+    /// Where the run of newlines that ends `text` starts, with the spaces and tabs between
+    /// them if lines are trimmed.
+    fn trailing_newlines(&self, text: &[u8]) -> usize {
+        let mut end = text.len();
+        loop {
+            let rest = &text[..end];
+            let len = if rest.ends_with(b"\r\n") {
+                let crs = rest[..rest.len() - 1].iter().rev().take_while(|&&b| b == b'\r').count();
+                match self.cr_cr_lf {
+                    CrCrLf::One => crs + 1,
+                    // Left for the forward pass to report.
+                    CrCrLf::Error if crs > 1 => return end,
+                    _ => 2,
+                }
+            } else if rest.ends_with(b"\n") || (rest.ends_with(b"\r") && self.lone_cr == LoneCr::Newline) {
+                1
+            } else if self.unicode && rest.ends_with("\u{85}".as_bytes()) {
+                2
+            } else if self.unicode && (rest.ends_with("\u{2028}".as_bytes()) || rest.ends_with("\u{2029}".as_bytes())) {
+                3
+            } else if self.trim_trailing_whitespace && (rest.ends_with(b" ") || rest.ends_with(b"\t")) {
+                1
+            } else {
+                return end;
+            };
+            end -= len;
+        }
+    }
+}
+
+/// Returns `true` if `text` starts with two or more CRs and then an LF.
+fn doubled_crlf(text: &[u8]) -> bool {
+    let crs = text.iter().take_while(|&&b| b == b'\r').count();
+    crs > 1 && text.get(crs) == Some(&b'\n')
+}
+
+/// Copies a text into a buffer, starting only once something in it changes.
+struct Rewrite<'a> {
+    text: &'a [u8],
+    out: &'a mut Vec<u8>,
+    /// The end of the text already copied or replaced.
+    copied: usize,
+    changed: bool,
+}
+
+impl Rewrite<'_> {
+    /// Writes `with` in place of the bytes from `start` to `end`, which lie at or past
+    /// everything replaced so far.
+    fn replace(&mut self, start: usize, end: usize, with: &[u8]) {
+        if !self.changed {
+            self.changed = true;
+            // Room for an added final newline too.
+            self.out.reserve(self.text.len() + self.text.len() / 8 + 2);
+        }
+        self.out.extend_from_slice(&self.text[self.copied..start]);
+        self.out.extend_from_slice(with);
+        self.copied = end;
+    }
+
+    fn finish(self) -> bool {
+        if self.changed {
+            self.out.extend_from_slice(&self.text[self.copied..]);
+        }
+        self.changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lone_cr_stays_as_in_str_lines() {
+        let text = "progress 10%\rprogress 99%\r\ndone\n\r";
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let kept = ending.normalize_keeping_lone_cr(text);
+            assert!(kept.lines().eq(text.lines()), "{kept:?}");
+        }
+        assert_eq!(LineEnding::Lf.normalize_keeping_lone_cr(text), "progress 10%\rprogress 99%\ndone\n\r");
+        let unchanged = "a\rb\n";
+        assert!(matches!(LineEnding::Lf.normalize_keeping_lone_cr(unchanged), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn lone_cr_errors_point_at_the_cr() {
+        assert_eq!(LineEnding::CrLf.try_normalize("a\nb\r\nc\rd"), Err(LoneCarriageReturn { offset: 6 }));
+        assert_eq!(LineEnding::Lf.try_normalize("a\r\n\r"), Err(LoneCarriageReturn { offset: 3 }));
+        assert_eq!(LineEnding::CrLf.try_normalize("a\nb\r\n").unwrap(), "a\r\nb\r\n");
+
+        let options = Options { lone_cr: LoneCr::Error, bom: BomPolicy::Strip, final_newline: FinalNewline::Strip, ..Options::new(LineEnding::Lf) };
+        assert_eq!(options.apply("\u{FEFF}ok\r"), Err(LoneCarriageReturn { offset: 5 }));
+        assert_eq!(options.apply("\u{FEFF}ok\r\n").unwrap(), "ok");
+    }
+
+    #[test]
+    fn collapses_runs_of_blank_lines() {
+        let options = |max| Options { max_blank_lines: Some(max), ..Options::new(LineEnding::Lf) };
+        let text = "\n\n\na\r\n\r\n\r\n\r\nb\n\nc\n\n\n";
+        assert_eq!(options(1).apply(text).unwrap(), "\na\n\nb\n\nc\n\n");
+        assert_eq!(options(0).apply(text).unwrap(), "a\nb\nc\n");
+        assert!(matches!(options(2).apply("a\n\n\nb\n").unwrap(), Cow::Borrowed(_)));
+
+        let ensure = Options { final_newline: FinalNewline::Ensure, unicode: true, ..options(1) };
+        assert_eq!(ensure.apply("a\u{2028}\u{2028}\u{2028}b\n\n").unwrap(), "a\n\nb\n");
+    }
+
+    #[test]
+    fn final_newline_is_added_once() {
+        for (text, expected) in [("a", "a\n"), ("a\r", "a\n"), ("a\r\n\r\n", "a\n"), ("\r\n", "\n"), ("", "")] {
+            assert_eq!(ensure_final_newline(text, LineEnding::Lf), expected, "{text:?}");
+        }
+        assert!(matches!(ensure_final_newline("a\r\n", LineEnding::CrLf), Cow::Borrowed(_)));
+        assert_eq!(ensure_final_newline("a\nb", LineEnding::CrLf), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn final_newlines_are_stripped() {
+        for (text, expected) in [("a\n\r\n\r", "a"), ("a\rb\r", "a\nb"), ("\n\n", ""), ("", "")] {
+            assert_eq!(strip_final_newline(text, LineEnding::Lf), expected, "{text:?}");
+        }
+        let text = "a\r\nb";
+        assert!(core::ptr::eq(&*strip_final_newline(text, LineEnding::CrLf), text));
+    }
+
+    #[test]
+    fn trims_lines_in_the_same_pass() {
+        let trim = Options { trim_trailing_whitespace: true, ..Options::new(LineEnding::Lf) };
+        assert_eq!(trim.apply("a \t\r\n b\n\t\nc  ").unwrap(), "a\n b\n\nc");
+        assert!(matches!(trim.apply("a\n b\n").unwrap(), Cow::Borrowed(_)));
+
+        let tidy = Options { max_blank_lines: Some(1), final_newline: FinalNewline::Ensure, ..trim };
+        assert_eq!(tidy.apply("a  \n \n\t\n\nb\n  \n \t").unwrap(), "a\n\nb\n");
+    }
+
+    #[test]
+    fn doubled_crs_before_lf() {
+        let text = "a\r\r\nb\r\r\r\nc\r\rd\r\r\n";
+        let policy = |cr_cr_lf, lone_cr| Options { cr_cr_lf, lone_cr, ..Options::new(LineEnding::Lf) };
+        assert_eq!(policy(CrCrLf::Two, LoneCr::Newline).apply(text).unwrap(), "a\n\nb\n\n\nc\n\nd\n\n");
+        assert_eq!(policy(CrCrLf::Two, LoneCr::Keep).apply(text).unwrap(), "a\r\nb\r\r\nc\r\rd\r\n");
+        assert_eq!(policy(CrCrLf::One, LoneCr::Newline).apply(text).unwrap(), "a\nb\nc\n\nd\n");
+        assert_eq!(policy(CrCrLf::One, LoneCr::Keep).apply(text).unwrap(), "a\nb\nc\r\rd\n");
+        assert_eq!(policy(CrCrLf::Error, LoneCr::Newline).apply("a\rb\r\r\n"), Err(LoneCarriageReturn { offset: 3 }));
+
+        let ending = |cr_cr_lf| Options { final_newline: FinalNewline::Ensure, ..policy(cr_cr_lf, LoneCr::Keep) };
+        assert_eq!(ending(CrCrLf::One).apply("a\r\r\n").unwrap(), "a\n");
+        assert_eq!(ending(CrCrLf::Two).apply("a\r\r\n").unwrap(), "a\r\n");
+        assert_eq!(ending(CrCrLf::Error).apply("a\r\r\n"), Err(LoneCarriageReturn { offset: 1 }));
+    }
+}
@@ -0,0 +1,179 @@
+//! Multi-threaded normalization of a single large stream.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Like [`normalize_copy`](crate::normalize_copy), spreading the work over several threads.
+///
+/// - A reader thread cuts the input into 1 MiB chunks, `threads` workers normalize them,
+///   and the calling thread writes the results back in order.
+/// - A chunk never ends in a CR unless the input does, so every chunk can be normalized
+///   independently and CRLF pairs are never split.
+/// - `threads == 0` uses [`thread::available_parallelism`].
+/// - Memory use is bounded to a few chunks per worker.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_copy_parallel, LineEnding};
+///
+/// let input = "line\r\n".repeat(500_000);
+/// let mut out = Vec::new();
+/// let stats = normalize_copy_parallel(&mut input.as_bytes(), &mut out, LineEnding::Lf, 4).unwrap();
+/// assert_eq!(out, "line\n".repeat(500_000).as_bytes());
+/// assert_eq!(stats.crlf, 500_000);
+/// ```
+pub fn normalize_copy_parallel<R, W>(reader: &mut R, writer: &mut W, ending: LineEnding, threads: usize) -> io::Result<Stats>
+where
+    R: Read + Send + ?Sized,
+    W: Write + ?Sized,
+{
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    thread::scope(|scope| {
+        let (chunk_tx, chunk_rx) = sync_channel::<(usize, Vec<u8>)>(threads * 2);
+        let (done_tx, done_rx) = sync_channel::<(usize, Vec<u8>, Stats)>(threads * 2);
+
+        let reading = scope.spawn(move || read_chunks(reader, |seq, chunk| chunk_tx.send((seq, chunk)).is_ok()));
+
+        // Workers own the only handles to the chunk receiver, so if they all stop, the reader does too.
+        let chunk_rx = Arc::new(Mutex::new(chunk_rx));
+        for _ in 0..threads {
+            let chunk_rx = Arc::clone(&chunk_rx);
+            let done_tx = done_tx.clone();
+            scope.spawn(move || loop {
+                let next = chunk_rx.lock().map_err(drop).and_then(|rx| rx.recv().map_err(drop));
+                let Ok((seq, chunk)) = next else { break };
+                let mut engine = Engine::new(ending);
+                let mut out = Vec::with_capacity(chunk.len() + chunk.len() / 8);
+                engine.normalize_into(&chunk, &mut out);
+                if done_tx.send((seq, out, engine.stats())).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(chunk_rx);
+        drop(done_tx);
+
+        let mut stats = Stats::default();
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let mut written = Ok(());
+        for (seq, out, chunk_stats) in done_rx.iter() {
+            pending.insert(seq, (out, chunk_stats));
+            while let Some((out, chunk_stats)) = pending.remove(&next) {
+                written = writer.write_all(&out);
+                stats += chunk_stats;
+                next += 1;
+                if written.is_err() {
+                    break;
+                }
+            }
+            if written.is_err() {
+                break;
+            }
+        }
+        // Unblocks workers and, through them, the reader if writing failed.
+        drop(done_rx);
+
+        let read = reading.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        written?;
+        read?;
+        writer.flush()?;
+        Ok(stats)
+    })
+}
+
+/// Reads full chunks, moving a trailing CR to the next chunk. Stops early if `send` returns `false`.
+fn read_chunks<R: Read + ?Sized>(reader: &mut R, mut send: impl FnMut(usize, Vec<u8>) -> bool) -> io::Result<()> {
+    let mut carry = None;
+    for seq in 0.. {
+        let mut chunk = vec![0; CHUNK_SIZE + 1];
+        let mut len = 0;
+        if let Some(cr) = carry.take() {
+            chunk[0] = cr;
+            len = 1;
+        }
+        while len < CHUNK_SIZE {
+            match reader.read(&mut chunk[len..CHUNK_SIZE]) {
+                Ok(0) => break,
+                Ok(read) => len += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let eof = len < CHUNK_SIZE;
+        if !eof && chunk[len - 1] == b'\r' {
+            len -= 1;
+            carry = Some(b'\r');
+        }
+        chunk.truncate(len);
+
+        if !chunk.is_empty() && !send(seq, chunk) {
+            return Ok(());
+        }
+        if eof {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize_copy;
+
+    #[test]
+    fn matches_sequential_output_across_chunk_boundaries() {
+        // Put a CRLF and a lone CR right at chunk boundaries.
+        let mut input = vec![b'x'; CHUNK_SIZE - 1];
+        input.extend_from_slice(b"\r\n");
+        input.extend(vec![b'y'; CHUNK_SIZE - 2]);
+        input.extend_from_slice(b"\r\rz\n");
+
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let mut sequential = Vec::new();
+            let expected = normalize_copy(&mut &input[..], &mut sequential, ending).unwrap();
+            let mut parallel = Vec::new();
+            let stats = normalize_copy_parallel(&mut &input[..], &mut parallel, ending, 3).unwrap();
+            assert!(parallel == sequential);
+            assert_eq!(stats, expected);
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        let mut out = Vec::new();
+        let stats = normalize_copy_parallel(&mut &b""[..], &mut out, LineEnding::Lf, 0).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(stats, Stats::default());
+    }
+
+    #[test]
+    fn write_error_stops_the_pipeline() {
+        struct Failing;
+        impl Write for Failing {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let input = vec![b'a'; CHUNK_SIZE * 8];
+        let err = normalize_copy_parallel(&mut &input[..], &mut Failing, LineEnding::Lf, 2).unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+    }
+}
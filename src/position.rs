@@ -0,0 +1,191 @@
+//! Language Server Protocol positions in a document and in its normalized form.
+
+use alloc::vec::Vec;
+
+use crate::search::memchr2_iter;
+use crate::LineEnding;
+
+/// A position as the Language Server Protocol counts it: a zero-based line, and a
+/// zero-based offset into that line in UTF-16 code units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    /// The line, where CR, LF and CRLF each end one.
+    pub line: u32,
+    /// UTF-16 code units from the start of the line.
+    pub character: u32,
+}
+
+impl Position {
+    /// The position `character` code units into `line`.
+    pub const fn new(line: u32, character: u32) -> Self {
+        Position { line, character }
+    }
+}
+
+/// Converts between positions and byte offsets in a document and in the same document
+/// normalized to a line ending, for language servers that normalize what clients send.
+///
+/// Every CR, LF and CRLF is replaced by exactly one line ending, so a line keeps its number
+/// and its contents; what moves is the byte offset where each line starts. Positions that
+/// point past the end of a line, which includes between the CR and LF of a CRLF, are
+/// clamped to the end of its contents, and positions past the last line to the end of the
+/// document, as the protocol asks. A position inside a surrogate pair moves to the start
+/// of its character.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{LineEnding, Position, PositionMapper};
+///
+/// let text = "fn main() {\r\n    println!(\"é\");\r\n}\r\n";
+/// let mapper = PositionMapper::new(text, LineEnding::Lf);
+/// let position = mapper.original_position(text.find('}').unwrap());
+/// assert_eq!(position, Position::new(2, 0));
+/// assert_eq!(mapper.normalized_offset(position), 32);
+/// assert_eq!(mapper.to_normalized(Position::new(0, 12)), Position::new(0, 11));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PositionMapper<'a> {
+    text: &'a str,
+    lines: Vec<Line>,
+}
+
+/// Where a line starts in both texts, and the byte length of its contents.
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    original: usize,
+    normalized: usize,
+    len: usize,
+}
+
+impl<'a> PositionMapper<'a> {
+    /// Maps positions between `text` and `ending.normalize(text)`.
+    pub fn new(text: &'a str, ending: LineEnding) -> Self {
+        let bytes = text.as_bytes();
+        let mut lines = Vec::new();
+        let (mut original, mut normalized) = (0, 0);
+        for found in memchr2_iter(b'\n', b'\r', bytes) {
+            if found < original {
+                // The LF of a CRLF, already ended.
+                continue;
+            }
+            let len = found - original;
+            lines.push(Line { original, normalized, len });
+            let crlf = bytes[found] == b'\r' && bytes.get(found + 1) == Some(&b'\n');
+            original = found + if crlf { 2 } else { 1 };
+            normalized += len + ending.as_bytes().len();
+        }
+        lines.push(Line { original, normalized, len: text.len() - original });
+        PositionMapper { text, lines }
+    }
+
+    /// The number of lines, one more than the line endings.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The position in the normalized text of the original `position`.
+    pub fn to_normalized(&self, position: Position) -> Position {
+        self.clamp(position)
+    }
+
+    /// The position in the original text of the normalized `position`.
+    pub fn to_original(&self, position: Position) -> Position {
+        self.clamp(position)
+    }
+
+    /// The byte offset of `position` in the original text.
+    pub fn original_offset(&self, position: Position) -> usize {
+        let (line, bytes, _) = self.locate(position);
+        line.original + bytes
+    }
+
+    /// The byte offset of `position` in the normalized text.
+    pub fn normalized_offset(&self, position: Position) -> usize {
+        let (line, bytes, _) = self.locate(position);
+        line.normalized + bytes
+    }
+
+    /// The position of byte `offset` of the original text.
+    pub fn original_position(&self, offset: usize) -> Position {
+        let index = self.lines.partition_point(|line| line.original <= offset) - 1;
+        self.position_in(index, offset - self.lines[index].original)
+    }
+
+    /// The position of byte `offset` of the normalized text.
+    pub fn normalized_position(&self, offset: usize) -> Position {
+        let index = self.lines.partition_point(|line| line.normalized <= offset) - 1;
+        self.position_in(index, offset - self.lines[index].normalized)
+    }
+
+    fn clamp(&self, position: Position) -> Position {
+        let (_, _, character) = self.locate(position);
+        Position { line: position.line.min(self.lines.len() as u32 - 1), character }
+    }
+
+    /// The line of `position`, and the bytes and code units it is into the contents.
+    fn locate(&self, position: Position) -> (Line, usize, u32) {
+        let Some(&line) = self.lines.get(position.line as usize) else {
+            let last = self.lines[self.lines.len() - 1];
+            return (last, last.len, self.contents(last).encode_utf16().count() as u32);
+        };
+        let (mut bytes, mut units) = (0, 0);
+        for c in self.contents(line).chars() {
+            let next = units + c.len_utf16() as u32;
+            if next > position.character {
+                break;
+            }
+            bytes += c.len_utf8();
+            units = next;
+        }
+        (line, bytes, units)
+    }
+
+    /// The position `bytes` into the line at `index`, past its end meaning its end.
+    fn position_in(&self, index: usize, bytes: usize) -> Position {
+        let line = self.lines[index];
+        let contents = self.contents(line);
+        let mut end = bytes.min(contents.len());
+        while !contents.is_char_boundary(end) {
+            end -= 1;
+        }
+        Position { line: index as u32, character: contents[..end].encode_utf16().count() as u32 }
+    }
+
+    fn contents(&self, line: Line) -> &'a str {
+        &self.text[line.original..line.original + line.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_move_with_line_endings() {
+        let text = "a\r\nb\rc\nd";
+        let mapper = PositionMapper::new(text, LineEnding::CrLf);
+        let normalized = LineEnding::CrLf.normalize(text);
+        assert_eq!(mapper.line_count(), 4);
+        for (line, c) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            let position = Position::new(line as u32, 0);
+            assert_eq!(&text[mapper.original_offset(position)..][..1], c);
+            assert_eq!(&normalized[mapper.normalized_offset(position)..][..1], c);
+            assert_eq!(mapper.normalized_position(mapper.normalized_offset(position)), position);
+        }
+        assert_eq!(mapper.original_position(2), Position::new(0, 1));
+        assert_eq!(mapper.to_original(Position::new(9, 9)), Position::new(3, 1));
+        assert_eq!(mapper.normalized_offset(Position::new(9, 9)), normalized.len());
+    }
+
+    #[test]
+    fn counts_utf16_code_units() {
+        let text = "😀é\na";
+        let mapper = PositionMapper::new(text, LineEnding::CrLf);
+        assert_eq!(mapper.original_position(4), Position::new(0, 2));
+        assert_eq!(mapper.original_position(6), Position::new(0, 3));
+        assert_eq!(mapper.original_offset(Position::new(0, 1)), 0);
+        assert_eq!(mapper.original_offset(Position::new(0, 3)), 6);
+        assert_eq!(mapper.normalized_offset(Position::new(1, 0)), 8);
+        assert_eq!(mapper.to_normalized(Position::new(0, 7)), Position::new(0, 3));
+    }
+}
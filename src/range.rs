@@ -0,0 +1,125 @@
+//! Normalization of a byte range inside a seekable source.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use crate::copy::copy_with;
+use crate::{LineEnding, Stats, StreamConfig};
+
+/// Outcome of [`normalize_range`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RangeReport {
+    /// Counters for the normalized region.
+    pub stats: Stats,
+    /// How much longer (positive) or shorter (negative) the rewritten region is.
+    pub delta: i64,
+}
+
+/// Normalizes only `range` of `source`, writing the rewritten region to `writer`.
+///
+/// - The bytes outside the range are read but never emitted; splice the output into
+///   place of the original range and shift what follows by [`RangeReport::delta`].
+/// - A CRLF straddling either edge of the range is left as it is, so the splice never
+///   splits or doubles a newline.
+/// - The range is clamped to the end of the source.
+///
+/// Example:
+/// ```
+/// use std::io::Cursor;
+/// use newline_normalizer::{normalize_range, LineEnding};
+///
+/// let mut source = Cursor::new(b"keep\r\n[a\nb]\r\nkeep".to_vec());
+/// let mut region = Vec::new();
+/// let report = normalize_range(&mut source, 6..11, &mut region, LineEnding::CrLf).unwrap();
+/// assert_eq!(region, b"[a\r\nb]");
+/// assert_eq!(report.delta, 1);
+/// ```
+pub fn normalize_range<S, W>(source: &mut S, range: Range<u64>, writer: &mut W, ending: LineEnding) -> io::Result<RangeReport>
+where
+    S: Read + Seek + ?Sized,
+    W: Write + ?Sized,
+{
+    let size = source.seek(SeekFrom::End(0))?;
+    let end = range.end.min(size);
+    let start = range.start.min(end);
+    let len = end - start;
+    if len == 0 {
+        return Ok(RangeReport::default());
+    }
+
+    let prev = if start > 0 { byte_at(source, start - 1)? } else { None };
+    let first = byte_at(source, start)?;
+    let last = byte_at(source, end - 1)?;
+    let next = byte_at(source, end)?;
+
+    let head = u64::from(prev == Some(b'\r') && first == Some(b'\n'));
+    let tail = u64::from(last == Some(b'\r') && next == Some(b'\n') && len > head);
+
+    if head == 1 {
+        writer.write_all(b"\n")?;
+    }
+    source.seek(SeekFrom::Start(start + head))?;
+    let mut stats = copy_with(
+        &mut source.take(len - head - tail),
+        writer,
+        ending,
+        StreamConfig::new(),
+        &mut |_| {},
+    )?;
+    if tail == 1 {
+        writer.write_all(b"\r")?;
+    }
+    stats.bytes_read += head + tail;
+    stats.bytes_written += head + tail;
+
+    Ok(RangeReport { stats, delta: stats.bytes_written as i64 - stats.bytes_read as i64 })
+}
+
+fn byte_at<S: Read + Seek + ?Sized>(source: &mut S, pos: u64) -> io::Result<Option<u8>> {
+    source.seek(SeekFrom::Start(pos))?;
+    let mut byte = [0];
+    match source.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn region(input: &[u8], range: Range<u64>, ending: LineEnding) -> (Vec<u8>, i64) {
+        let mut out = Vec::new();
+        let report = normalize_range(&mut Cursor::new(input), range, &mut out, ending).unwrap();
+        (out, report.delta)
+    }
+
+    #[test]
+    fn normalizes_inside_range_only() {
+        assert_eq!(region(b"a\r\nb\r\nc\r\n", 3..6, LineEnding::Lf), (b"b\n".to_vec(), -1));
+    }
+
+    #[test]
+    fn keeps_crlf_straddling_the_start() {
+        assert_eq!(region(b"a\r\nb\r", 2..5, LineEnding::Lf), (b"\nb\n".to_vec(), 0));
+    }
+
+    #[test]
+    fn keeps_crlf_straddling_the_end() {
+        assert_eq!(region(b"a\nb\r\nc", 0..4, LineEnding::Lf), (b"a\nb\r".to_vec(), 0));
+        assert_eq!(region(b"a\nb\r\nc", 0..4, LineEnding::CrLf), (b"a\r\nb\r".to_vec(), 1));
+    }
+
+    #[test]
+    fn single_cr_between_straddling_pairs() {
+        assert_eq!(region(b"\r\r\n", 1..2, LineEnding::Lf), (b"\r".to_vec(), 0));
+    }
+
+    #[test]
+    fn clamps_range_to_source() {
+        assert_eq!(region(b"a\rb", 2..100, LineEnding::CrLf), (b"b".to_vec(), 0));
+        assert_eq!(region(b"a\rb", 10..100, LineEnding::CrLf), (Vec::new(), 0));
+    }
+}
@@ -0,0 +1,165 @@
+//! `io::Read` adapter normalizing newlines on the fly.
+
+use std::io::{self, Read};
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats, StreamConfig};
+
+/// Wraps a reader and normalizes every newline read through it.
+///
+/// - Works on bytes, so the input does not have to be valid UTF-8.
+/// - A CRLF split between two reads from the inner reader produces one newline.
+///
+/// Example:
+/// ```
+/// use std::io::Read;
+/// use newline_normalizer::NormalizingReader;
+///
+/// let mut reader = NormalizingReader::unix("one\r\ntwo\rthree".as_bytes());
+/// let mut out = String::new();
+/// reader.read_to_string(&mut out).unwrap();
+/// assert_eq!(out, "one\ntwo\nthree");
+/// ```
+#[derive(Debug)]
+pub struct NormalizingReader<R> {
+    inner: R,
+    engine: Engine,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+}
+
+impl<R: Read> NormalizingReader<R> {
+    /// Creates a reader producing `ending`.
+    pub fn new(inner: R, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new())
+    }
+
+    /// Creates a reader producing `ending` that reads up to `capacity` bytes at a time.
+    pub fn with_capacity(capacity: usize, inner: R, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new().chunk_size(capacity))
+    }
+
+    /// Creates a reader producing `ending` whose buffering is bounded by `config`.
+    pub fn with_config(inner: R, ending: LineEnding, config: StreamConfig) -> Self {
+        let chunk_size = config.get_chunk_size();
+        NormalizingReader {
+            inner,
+            engine: Engine::new(ending),
+            input: vec![0; chunk_size],
+            output: Vec::with_capacity(chunk_size),
+            output_pos: 0,
+        }
+    }
+
+    /// Creates a reader producing Unix newlines (`\n`).
+    pub fn unix(inner: R) -> Self {
+        Self::new(inner, LineEnding::Lf)
+    }
+
+    /// Creates a reader producing DOS newlines (`\r\n`).
+    pub fn dos(inner: R) -> Self {
+        Self::new(inner, LineEnding::CrLf)
+    }
+}
+
+impl<R> NormalizingReader<R> {
+    /// The line ending this reader produces.
+    pub fn ending(&self) -> LineEnding {
+        self.engine.ending()
+    }
+
+    /// Counters for everything normalized so far; poll this to report progress.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this reader, discarding any normalized bytes not yet read.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for NormalizingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            let read = self.inner.read(&mut self.input)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            self.output.clear();
+            self.output_pos = 0;
+            self.engine.normalize_into(&self.input[..read], &mut self.output);
+        }
+
+        let available = &self.output[self.output_pos..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.output_pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Yields its data in fixed-size pieces to exercise chunk boundaries.
+    struct Chunked<'a>(&'a [u8], usize);
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.0.len().min(self.1).min(buf.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    fn read_all(reader: impl Read) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut reader = reader;
+        reader.read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn unix_every_chunk_size() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            let reader = NormalizingReader::unix(Chunked(input, size));
+            assert_eq!(read_all(reader), b"a\nb\nc\n\n\nd\n", "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn dos_every_chunk_size() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            let reader = NormalizingReader::dos(Chunked(input, size));
+            assert_eq!(read_all(reader), b"a\r\nb\r\nc\r\n\r\n\r\nd\r\n", "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn small_internal_buffer() {
+        let reader = NormalizingReader::with_capacity(1, &b"x\r\ny"[..], LineEnding::Lf);
+        assert_eq!(read_all(reader), b"x\ny");
+    }
+
+    #[test]
+    fn passes_through_invalid_utf8() {
+        let reader = NormalizingReader::dos(&b"\xff\n\xfe"[..]);
+        assert_eq!(read_all(reader), b"\xff\r\n\xfe");
+    }
+}
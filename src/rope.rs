@@ -0,0 +1,110 @@
+//! Normalization of a `ropey::Rope` by minimal edits, enabled by the `ropey` feature.
+
+use alloc::vec::Vec;
+
+use ropey::Rope;
+
+use crate::search::memchr2_iter;
+use crate::LineEnding;
+
+/// One edit at a char index: removing the char there, inserting before it, or both.
+struct Edit {
+    at: usize,
+    remove: bool,
+    insert: &'static str,
+}
+
+/// Normalizes the line endings of `rope` to `ending` in place, returning whether anything
+/// changed.
+///
+/// The rope is edited only where a line ending is wrong, one char at a time, so the rest of
+/// its structure is shared with earlier clones and an editor's undo history stays small.
+/// The chunks are scanned first and the edits applied afterwards, last one first.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_rope, LineEnding};
+/// use ropey::Rope;
+///
+/// let mut rope = Rope::from_str("one\r\ntwo\rthree\n");
+/// assert!(normalize_rope(&mut rope, LineEnding::Lf));
+/// assert_eq!(rope, "one\ntwo\nthree\n");
+/// assert!(!normalize_rope(&mut rope, LineEnding::Lf));
+/// ```
+pub fn normalize_rope(rope: &mut Rope, ending: LineEnding) -> bool {
+    let edits = edits(rope, ending);
+    for edit in edits.iter().rev() {
+        if edit.remove {
+            rope.remove(edit.at..edit.at + 1);
+        }
+        if !edit.insert.is_empty() {
+            rope.insert(edit.at, edit.insert);
+        }
+    }
+    !edits.is_empty()
+}
+
+fn edits(rope: &Rope, ending: LineEnding) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let mut chunk_start = 0;
+    for chunk in rope.chunks() {
+        let bytes = chunk.as_bytes();
+        // Char indices are counted up to each newline from the one before.
+        let (mut counted, mut chars) = (0, chunk_start);
+        for found in memchr2_iter(b'\n', b'\r', bytes) {
+            chars += chunk[counted..found].chars().count();
+            counted = found;
+            let cr_before = match found {
+                0 => chars > 0 && rope.char(chars - 1) == '\r',
+                _ => bytes[found - 1] == b'\r',
+            };
+            let lf_after = match bytes.get(found + 1) {
+                Some(&next) => next == b'\n',
+                None => rope.get_char(chars + 1) == Some('\n'),
+            };
+            let edit = match (ending, bytes[found]) {
+                (LineEnding::Lf, b'\r') if lf_after => Edit { at: chars, remove: true, insert: "" },
+                (LineEnding::Lf, b'\r') => Edit { at: chars, remove: true, insert: "\n" },
+                (LineEnding::CrLf, b'\n') if !cr_before => Edit { at: chars, remove: false, insert: "\r" },
+                (LineEnding::CrLf, b'\r') if !lf_after => Edit { at: chars + 1, remove: false, insert: "\n" },
+                _ => continue,
+            };
+            edits.push(edit);
+        }
+        chunk_start += chunk.chars().count();
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_string_normalizers() {
+        let inputs = ["", "plain", "\r", "\n", "\r\n", "a\nb\rc\r\nd", "\n\n\r\r", "x\r\r\n\ny", "é\r\nü\rß\n"];
+        for input in inputs {
+            for ending in [LineEnding::Lf, LineEnding::CrLf] {
+                let mut rope = Rope::from_str(input);
+                let changed = normalize_rope(&mut rope, ending);
+                assert_eq!(rope, ending.normalize(input), "{input:?} to {ending:?}");
+                assert_eq!(changed, rope != input);
+            }
+        }
+    }
+
+    #[test]
+    fn newlines_across_chunks() {
+        // Long enough for several chunks, with multi-byte chars shifting char indices.
+        let text = "ö\r\nline\r".repeat(2000) + "\n" + &"\nä\r".repeat(2000);
+        let mut rope = Rope::from_str(&text);
+        assert!(rope.chunks().count() > 1);
+        let before = rope.clone();
+        assert!(normalize_rope(&mut rope, LineEnding::CrLf));
+        assert_eq!(rope, LineEnding::CrLf.normalize(&text));
+        assert_eq!(before, text);
+
+        assert!(normalize_rope(&mut rope, LineEnding::Lf));
+        assert_eq!(rope, LineEnding::Lf.normalize(&text));
+    }
+}
@@ -0,0 +1,168 @@
+//! Byte searches, from `memchr` or, without the `memchr` feature, a SWAR fallback.
+//!
+//! `memchr` stops at three needles, so four-needle searches always use the fallback.
+
+#[cfg(feature = "memchr")]
+pub(crate) use memchr::{memchr, memchr2, memchr2_iter, memchr3_iter, memchr_iter};
+#[cfg(all(feature = "memchr", feature = "std"))]
+pub(crate) use memchr::memrchr;
+
+#[cfg(not(feature = "memchr"))]
+pub(crate) use swar::{memchr, memchr2, memchr2_iter, memchr3_iter, memchr_iter};
+#[cfg(all(not(feature = "memchr"), feature = "std"))]
+pub(crate) use swar::memrchr;
+pub(crate) use swar::memchr4_iter;
+
+/// Searches eight bytes at a time in a `u64`, with no dependencies and no `unsafe`.
+#[cfg_attr(feature = "memchr", allow(dead_code))]
+mod swar {
+    const LOW_BITS: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+    /// Sets the high bit of every byte of `word` that equals `byte`, and no other bit.
+    ///
+    /// Adding `LOW_BITS` never carries across bytes, so unlike the classic `haszero`
+    /// trick this has no false positives and the mask can be used for positions.
+    fn eq_mask(word: u64, byte: u8) -> u64 {
+        let x = word ^ (u64::from(byte) * 0x0101_0101_0101_0101);
+        !(((x & LOW_BITS) + LOW_BITS) | x | LOW_BITS)
+    }
+
+    fn mask<const N: usize>(word: u64, needles: [u8; N]) -> u64 {
+        needles.iter().fold(0, |mask, &needle| mask | eq_mask(word, needle))
+    }
+
+    /// Loads up to eight bytes little-endian, so byte `i` lands in bits `8 * i..`.
+    fn load(bytes: &[u8]) -> u64 {
+        let mut word = [0; 8];
+        word[..bytes.len()].copy_from_slice(bytes);
+        u64::from_le_bytes(word)
+    }
+
+    /// Keeps the mask bits of the first `len` bytes.
+    fn first_bytes(mask: u64, len: usize) -> u64 {
+        if len >= 8 {
+            mask
+        } else {
+            mask & ((1 << (len * 8)) - 1)
+        }
+    }
+
+    fn find<const N: usize>(needles: [u8; N], haystack: &[u8]) -> Option<usize> {
+        let mut chunks = haystack.chunks_exact(8);
+        let mut pos = 0;
+        for chunk in &mut chunks {
+            let found = mask(load(chunk), needles);
+            if found != 0 {
+                return Some(pos + found.trailing_zeros() as usize / 8);
+            }
+            pos += 8;
+        }
+        let rest = chunks.remainder();
+        let found = first_bytes(mask(load(rest), needles), rest.len());
+        (found != 0).then(|| pos + found.trailing_zeros() as usize / 8)
+    }
+
+    pub(crate) fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+        find([needle], haystack)
+    }
+
+    pub(crate) fn memchr2(needle1: u8, needle2: u8, haystack: &[u8]) -> Option<usize> {
+        find([needle1, needle2], haystack)
+    }
+
+    #[cfg(any(test, feature = "std"))]
+    pub(crate) fn memrchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+        let mut chunks = haystack.rchunks_exact(8);
+        let mut end = haystack.len();
+        for chunk in &mut chunks {
+            end -= 8;
+            let found = mask(load(chunk), [needle]);
+            if found != 0 {
+                return Some(end + 7 - found.leading_zeros() as usize / 8);
+            }
+        }
+        let rest = chunks.remainder();
+        let found = first_bytes(mask(load(rest), [needle]), rest.len());
+        (found != 0).then(|| 7 - found.leading_zeros() as usize / 8)
+    }
+
+    pub(crate) fn memchr_iter(needle: u8, haystack: &[u8]) -> Matches<'_, 1> {
+        Matches { haystack, pos: 0, needles: [needle] }
+    }
+
+    pub(crate) fn memchr2_iter(needle1: u8, needle2: u8, haystack: &[u8]) -> Matches<'_, 2> {
+        Matches { haystack, pos: 0, needles: [needle1, needle2] }
+    }
+
+    pub(crate) fn memchr3_iter(needle1: u8, needle2: u8, needle3: u8, haystack: &[u8]) -> Matches<'_, 3> {
+        Matches { haystack, pos: 0, needles: [needle1, needle2, needle3] }
+    }
+
+    pub(crate) fn memchr4_iter(needles: [u8; 4], haystack: &[u8]) -> Matches<'_, 4> {
+        Matches { haystack, pos: 0, needles }
+    }
+
+    /// Positions of the needles, in order.
+    pub(crate) struct Matches<'a, const N: usize> {
+        haystack: &'a [u8],
+        pos: usize,
+        needles: [u8; N],
+    }
+
+    impl<const N: usize> Iterator for Matches<'_, N> {
+        type Item = usize;
+
+        fn next(&mut self) -> Option<usize> {
+            let found = self.pos + find(self.needles, &self.haystack[self.pos..])?;
+            self.pos = found + 1;
+            Some(found)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::swar;
+
+    /// Inputs with needles at every position and offset within a word.
+    fn haystacks() -> Vec<Vec<u8>> {
+        let mut out = vec![Vec::new(), b"\r".to_vec(), b"\x8d\x8a\x0c\x0e\x7f\xff".to_vec()];
+        for len in 1..40 {
+            for at in 0..len {
+                let mut hay = vec![b'x'; len];
+                hay[at] = b'\r';
+                out.push(hay.clone());
+                hay[len - 1 - (at / 2)] = b'\n';
+                out.push(hay);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn matches_a_naive_search() {
+        for hay in haystacks() {
+            let naive: Vec<usize> = (0..hay.len()).filter(|&i| hay[i] == b'\r' || hay[i] == b'\n').collect();
+            assert_eq!(swar::memchr2_iter(b'\r', b'\n', &hay).collect::<Vec<_>>(), naive, "{hay:?}");
+            assert_eq!(swar::memchr2(b'\r', b'\n', &hay), naive.first().copied());
+
+            let naive: Vec<usize> = (0..hay.len()).filter(|&i| hay[i] == b'\r').collect();
+            assert_eq!(swar::memchr_iter(b'\r', &hay).collect::<Vec<_>>(), naive, "{hay:?}");
+            assert_eq!(swar::memchr(b'\r', &hay), naive.first().copied());
+            assert_eq!(swar::memrchr(b'\r', &hay), naive.last().copied(), "{hay:?}");
+
+            let naive: Vec<usize> = (0..hay.len()).filter(|&i| b"\r\n\x8d\x0e".contains(&hay[i])).collect();
+            assert_eq!(swar::memchr4_iter(*b"\r\n\x8d\x0e", &hay).collect::<Vec<_>>(), naive, "{hay:?}");
+            let naive = (0..hay.len()).filter(|&i| b"\r\nx".contains(&hay[i])).count();
+            assert_eq!(swar::memchr3_iter(b'\r', b'\n', b'x', &hay).count(), naive, "{hay:?}");
+        }
+    }
+
+    #[test]
+    fn high_bytes_are_not_matches() {
+        let hay = [0x80, 0x8d, 0xff, 0x0d | 0x80, 0x7f, 0x00, 0x01, 0x0c, 0x0e];
+        assert_eq!(swar::memchr2(b'\r', b'\n', &hay), None);
+        assert_eq!(swar::memchr(0, &hay), Some(5));
+        assert_eq!(swar::memrchr(0xff, &hay), Some(2));
+    }
+}
@@ -0,0 +1,381 @@
+//! Normalizing strings as they are deserialized or serialized, enabled by the `serde`
+//! feature.
+//!
+//! Each function fits `#[serde(deserialize_with = "...")]`; the ones in [`option`] are for
+//! `Option<String>` fields:
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Note {
+//!     #[serde(deserialize_with = "newline_normalizer::serde_helpers::unix")]
+//!     body: String,
+//!     #[serde(default, deserialize_with = "newline_normalizer::serde_helpers::option::unix")]
+//!     footer: Option<String>,
+//! }
+//!
+//! let note: Note = serde_json::from_str(r#"{"body": "a\r\nb", "footer": "c\r"}"#).unwrap();
+//! assert_eq!(note.body, "a\nb");
+//! assert_eq!(note.footer.as_deref(), Some("c\n"));
+//! ```
+//!
+//! On the way out, [`Normalized`] wraps a whole value so that every string in it is
+//! serialized with one line ending, whatever the data was built from. The line breaks
+//! the format itself writes, such as those of pretty-printed JSON or YAML, are the writer's
+//! business: write through a [`NormalizingWriter`](crate::NormalizingWriter) for those.
+
+use alloc::string::String;
+
+use serde::ser::{
+    SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct,
+    SerializeTupleVariant,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::text::normalize_owned;
+use crate::LineEnding;
+
+/// Deserializes a string with every CRLF and lone CR turned into an LF.
+pub fn unix<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    normalized(deserializer, LineEnding::Lf)
+}
+
+/// Deserializes a string with every LF and lone CR turned into a CRLF.
+pub fn dos<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    normalized(deserializer, LineEnding::CrLf)
+}
+
+/// The same for `Option<String>` fields; a missing field needs `#[serde(default)]`.
+pub mod option {
+    use super::*;
+
+    /// Deserializes an optional string with every CRLF and lone CR turned into an LF.
+    pub fn unix<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<String>::deserialize(deserializer)?.map(|text| normalize_owned(text, LineEnding::Lf)))
+    }
+
+    /// Deserializes an optional string with every LF and lone CR turned into a CRLF.
+    pub fn dos<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<String>::deserialize(deserializer)?.map(|text| normalize_owned(text, LineEnding::CrLf)))
+    }
+}
+
+fn normalized<'de, D: Deserializer<'de>>(deserializer: D, ending: LineEnding) -> Result<String, D::Error> {
+    Ok(normalize_owned(String::deserialize(deserializer)?, ending))
+}
+
+/// A value that serializes with the line endings of every string in it normalized, map keys
+/// included.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::serde_helpers::Normalized;
+/// use std::collections::BTreeMap;
+///
+/// let scripts = BTreeMap::from([("build.bat", vec!["@echo off\ncall make\n"])]);
+/// let json = serde_json::to_string(&Normalized::dos(&scripts)).unwrap();
+/// assert_eq!(json, r#"{"build.bat":["@echo off\r\ncall make\r\n"]}"#);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Normalized<'a, T: ?Sized> {
+    value: &'a T,
+    ending: LineEnding,
+}
+
+impl<'a, T: ?Sized> Normalized<'a, T> {
+    /// Serializes `value` with the strings in it normalized to `ending`.
+    pub fn new(value: &'a T, ending: LineEnding) -> Self {
+        Normalized { value, ending }
+    }
+
+    /// Serializes `value` with LF line endings in its strings.
+    pub fn unix(value: &'a T) -> Self {
+        Normalized::new(value, LineEnding::Lf)
+    }
+
+    /// Serializes `value` with CRLF line endings in its strings.
+    pub fn dos(value: &'a T) -> Self {
+        Normalized::new(value, LineEnding::CrLf)
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for Normalized<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(NormalizingSerializer { inner: serializer, ending: self.ending })
+    }
+}
+
+/// Passes everything on to `inner`, normalizing strings and wrapping nested values.
+struct NormalizingSerializer<S> {
+    inner: S,
+    ending: LineEnding,
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<S::Ok, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for NormalizingSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+    forward! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_str(&self.ending.normalize(v))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&Normalized::new(value, self.ending))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, &Normalized::new(value, self.ending))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, &Normalized::new(value, self.ending))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_seq(len)? })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_tuple(len)? })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_tuple_struct(name, len)? })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_tuple_variant(name, index, variant, len)? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_map(len)? })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_struct(name, len)? })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        Ok(Compound { ending: self.ending, inner: self.inner.serialize_struct_variant(name, index, variant, len)? })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// A sequence, tuple, map or struct of the inner serializer, whose elements are wrapped in
+/// [`Normalized`].
+struct Compound<C> {
+    inner: C,
+    ending: LineEnding,
+}
+
+impl<C: SerializeSeq> SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&Normalized::new(value, self.ending))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&Normalized::new(value, self.ending))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&Normalized::new(value, self.ending))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&Normalized::new(value, self.ending))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.inner.serialize_key(&Normalized::new(key, self.ending))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_value(&Normalized::new(value, self.ending))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStruct> SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &Normalized::new(value, self.ending))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &Normalized::new(value, self.ending))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    #[test]
+    fn strings_are_normalized() {
+        let text = |text: &'static str| -> StrDeserializer<'static, Error> { text.into_deserializer() };
+        assert_eq!(unix(text("a\r\nb\rc")).unwrap(), "a\nb\nc");
+        assert_eq!(dos(text("a\nb")).unwrap(), "a\r\nb");
+        assert_eq!(unix(text("")).unwrap(), "");
+    }
+
+    #[test]
+    fn optional_strings() {
+        #[derive(Deserialize)]
+        struct Fields {
+            #[serde(default, deserialize_with = "option::dos")]
+            text: Option<String>,
+        }
+        let fields: Fields = serde_json::from_str(r#"{"text": "a\nb"}"#).unwrap();
+        assert_eq!(fields.text.as_deref(), Some("a\r\nb"));
+        assert_eq!(serde_json::from_str::<Fields>(r#"{"text": null}"#).unwrap().text, None);
+        assert_eq!(serde_json::from_str::<Fields>("{}").unwrap().text, None);
+    }
+
+    #[test]
+    fn serializes_nested_strings_normalized() {
+        #[derive(serde::Serialize)]
+        enum Step {
+            Run { script: String },
+            Note(Option<String>),
+        }
+        let steps = (vec![Step::Run { script: "a\nb\r".into() }, Step::Note(Some("c\r\n".into()))], 'x');
+        let json = serde_json::to_string(&Normalized::dos(&steps)).unwrap();
+        assert_eq!(json, r#"[[{"Run":{"script":"a\r\nb\r\n"}},{"Note":"c\r\n"}],"x"]"#);
+
+        #[cfg(feature = "std")]
+        {
+            let mut out = alloc::vec::Vec::new();
+            let writer = crate::NormalizingWriter::dos(&mut out);
+            serde_json::to_writer_pretty(writer, &Normalized::dos(&["one\ntwo"])).unwrap();
+            assert_eq!(out, b"[\r\n  \"one\\r\\ntwo\"\r\n]");
+        }
+    }
+}
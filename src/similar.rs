@@ -0,0 +1,99 @@
+//! Patches through [`similar`](::similar), enabled by the `similar` feature.
+//!
+//! [`diff_ops`] turns an [`edit_script`] into `similar`'s byte-level [`DiffOp`]s without
+//! running a diff algorithm, so the result is always the minimal change and plugs into the
+//! rest of `similar`. [`unified_diff`] renders the change as a patch for `git apply` or
+//! `patch`.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+pub use ::similar::DiffOp;
+use ::similar::TextDiff;
+
+use crate::{edit_script, Edit, LineEnding};
+
+/// Returns the operations turning the bytes of `text` into their normalized form: `Equal`
+/// runs between the line endings that change, and a one-byte `Insert`, `Delete` or
+/// `Replace` for each of them.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::similar::{diff_ops, DiffOp};
+/// use newline_normalizer::LineEnding;
+///
+/// assert_eq!(
+///     diff_ops(b"a\nb", LineEnding::CrLf),
+///     [
+///         DiffOp::Equal { old_index: 0, new_index: 0, len: 1 },
+///         DiffOp::Insert { old_index: 1, new_index: 1, new_len: 1 },
+///         DiffOp::Equal { old_index: 1, new_index: 2, len: 2 },
+///     ]
+/// );
+/// ```
+pub fn diff_ops(text: &[u8], ending: LineEnding) -> Vec<DiffOp> {
+    let mut ops = Vec::new();
+    let (mut old, mut new) = (0, 0);
+    for edit in edit_script(text, ending) {
+        push_equal(&mut ops, &mut old, &mut new, edit.offset());
+        match edit {
+            Edit::Insert { .. } => {
+                ops.push(DiffOp::Insert { old_index: old, new_index: new, new_len: 1 });
+                new += 1;
+            }
+            Edit::Delete { .. } => {
+                ops.push(DiffOp::Delete { old_index: old, old_len: 1, new_index: new });
+                old += 1;
+            }
+            Edit::Replace { .. } => {
+                ops.push(DiffOp::Replace { old_index: old, old_len: 1, new_index: new, new_len: 1 });
+                old += 1;
+                new += 1;
+            }
+        }
+    }
+    push_equal(&mut ops, &mut old, &mut new, text.len());
+    ops
+}
+
+/// Pushes the unchanged bytes from `old` up to `at`, if any.
+fn push_equal(ops: &mut Vec<DiffOp>, old: &mut usize, new: &mut usize, at: usize) {
+    if at > *old {
+        ops.push(DiffOp::Equal { old_index: *old, new_index: *new, len: at - *old });
+        *new += at - *old;
+        *old = at;
+    }
+}
+
+/// Returns a unified diff normalizing `text` to `ending`, with `name` in the `a/` and `b/`
+/// headers, or an empty string when nothing changes.
+///
+/// Only the lines whose ending changes appear, with three lines of context. As in `similar`,
+/// a lone CR ends a line of the patch.
+pub fn unified_diff(text: &str, ending: LineEnding, name: &str) -> String {
+    let normalized = ending.normalize(text);
+    if normalized == text {
+        return String::new();
+    }
+    let (old, new) = (alloc::format!("a/{name}"), alloc::format!("b/{name}"));
+    TextDiff::from_lines(text, &normalized).unified_diff().header(&old, &new).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ops_and_patches() {
+        let text = "one\r\ntwo\n\rthree";
+        let ops = diff_ops(text.as_bytes(), LineEnding::Lf);
+        assert_eq!(ops.len(), 5);
+        let new_len: usize = ops.iter().map(|op| op.new_range().len()).sum();
+        assert_eq!(new_len, LineEnding::Lf.normalize(text).len());
+
+        let patch = unified_diff(text, LineEnding::Lf, "notes.txt");
+        assert!(patch.starts_with("--- a/notes.txt\n+++ b/notes.txt\n"), "{patch}");
+        assert!(patch.contains("-one\r\n+one\n two\n-\r+\n"), "{patch:?}");
+        assert_eq!(unified_diff("ok\n", LineEnding::Lf, "notes.txt"), "");
+    }
+}
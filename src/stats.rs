@@ -0,0 +1,38 @@
+//! Counters reported by the streaming and file APIs.
+
+/// What a streaming normalization saw and did.
+///
+/// Newline counts describe the input; `converted` is how many of them were rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Bytes consumed from the input.
+    pub bytes_read: u64,
+    /// Bytes produced as output.
+    pub bytes_written: u64,
+    /// Bare LF (`\n`) line endings in the input.
+    pub lf: u64,
+    /// CRLF (`\r\n`) line endings in the input.
+    pub crlf: u64,
+    /// Lone CR (`\r`) line endings in the input.
+    pub cr: u64,
+    /// Line endings that did not match the target and were rewritten.
+    pub converted: u64,
+}
+
+impl Stats {
+    /// Returns `true` if the output differs from the input.
+    pub fn changed(&self) -> bool {
+        self.converted > 0
+    }
+}
+
+impl core::ops::AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        self.bytes_read += other.bytes_read;
+        self.bytes_written += other.bytes_written;
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.cr += other.cr;
+        self.converted += other.converted;
+    }
+}
@@ -0,0 +1,180 @@
+//! Generators of adversarial newline mixtures, enabled by the `test-strategies` feature, for
+//! fuzzing and property-testing code that handles line endings.
+//!
+//! The text is built from [`PIECES`]: every newline this crate understands, runs such as
+//! `\r\r\n` and `\n\r` that are easy to pair up wrongly, the Unicode separators, and
+//! multi-byte letters. [`ChunkedMixture`] also splits it into chunks, most often right after
+//! a CR, so that a CRLF straddles two chunks.
+//!
+//! Each type implements both [`arbitrary::Arbitrary`], for `cargo fuzz`, and
+//! [`proptest::arbitrary::Arbitrary`]; [`newline_mixture`] and [`chunked_newline_mixture`]
+//! are the same generators as plain proptest strategies.
+//!
+//! ```
+//! use newline_normalizer::strategies::ChunkedMixture;
+//! use newline_normalizer::{LineEnding, StreamNormalizer};
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(input: ChunkedMixture)| {
+//!     let mut stream = StreamNormalizer::new(LineEnding::CrLf);
+//!     let mut out: String = input.chunks.iter().map(|chunk| stream.push(chunk)).collect();
+//!     out.push_str(stream.finish());
+//!     let text = input.text();
+//!     prop_assert_eq!(out, LineEnding::CrLf.normalize(&text));
+//! });
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+
+use crate::LineEnding;
+
+/// What mixtures are made of, each piece equally likely.
+pub const PIECES: &[&str] = &[
+    "\n", "\r\n", "\r", "\r\r\n", "\r\r", "\n\r", "\r\n\n", "\r\n\r", "\u{85}", "\u{2028}", "\u{2029}", "a", "line",
+    " ", "\t", "é", "日本", "\u{feff}",
+];
+
+/// The most pieces a mixture is made of.
+const MAX_PIECES: usize = 64;
+
+/// A string made of [`PIECES`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewlineMixture(pub String);
+
+/// A [`NewlineMixture`] split into chunks, for streaming normalizers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedMixture {
+    /// The chunks, none of them empty; a split inside a character is never made.
+    pub chunks: Vec<String>,
+}
+
+impl ChunkedMixture {
+    /// The whole text, all chunks joined.
+    pub fn text(&self) -> String {
+        self.chunks.concat()
+    }
+}
+
+/// Splits `text` after the characters for which `cut(after_cr)` says so.
+fn split<E>(text: &str, mut cut: impl FnMut(bool) -> Result<bool, E>) -> Result<Vec<String>, E> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for (pos, c) in text.char_indices() {
+        let end = pos + c.len_utf8();
+        if end < text.len() && cut(c == '\r')? {
+            chunks.push(String::from(&text[start..end]));
+            start = end;
+        }
+    }
+    if start < text.len() {
+        chunks.push(String::from(&text[start..]));
+    }
+    Ok(chunks)
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for NewlineMixture {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut text = String::new();
+        u.arbitrary_loop(None, Some(MAX_PIECES as u32), |u| {
+            text.push_str(u.choose(PIECES)?);
+            Ok(ControlFlow::Continue(()))
+        })?;
+        Ok(NewlineMixture(text))
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for ChunkedMixture {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let NewlineMixture(text) = u.arbitrary()?;
+        let chunks = split(&text, |after_cr| if after_cr { u.ratio(3u8, 4) } else { u.ratio(1u8, 8) })?;
+        Ok(ChunkedMixture { chunks })
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for LineEnding {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(if u.arbitrary()? { LineEnding::CrLf } else { LineEnding::Lf })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <bool as arbitrary::Arbitrary>::size_hint(depth)
+    }
+}
+
+/// Strings made of [`PIECES`].
+pub fn newline_mixture() -> impl Strategy<Value = String> {
+    proptest::collection::vec(proptest::sample::select(PIECES), 0..=MAX_PIECES).prop_map(|pieces| pieces.concat())
+}
+
+/// Mixtures split into chunks, most often right after a CR.
+pub fn chunked_newline_mixture() -> impl Strategy<Value = Vec<String>> {
+    newline_mixture()
+        .prop_flat_map(|text| {
+            let cuts = proptest::collection::vec(0..8u8, text.chars().count());
+            (Just(text), cuts)
+        })
+        .prop_map(|(text, cuts)| {
+            let mut cuts = cuts.into_iter();
+            let cut = |after_cr| Ok::<_, ()>(cuts.next().map_or(false, |n| if after_cr { n < 6 } else { n < 1 }));
+            split(&text, cut).unwrap_or_default()
+        })
+}
+
+impl proptest::arbitrary::Arbitrary for NewlineMixture {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        newline_mixture().prop_map(NewlineMixture).boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for ChunkedMixture {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        chunked_newline_mixture().prop_map(|chunks| ChunkedMixture { chunks }).boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for LineEnding {
+    type Parameters = ();
+    type Strategy = proptest::sample::Select<LineEnding>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        proptest::sample::select(&[LineEnding::Lf, LineEnding::CrLf][..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::{Arbitrary, Unstructured};
+    use proptest::prelude::*;
+
+    #[test]
+    fn arbitrary_builds_from_pieces() {
+        let data: Vec<u8> = (0..1024u32).map(|i| (i * 167 + 13) as u8).collect();
+        let mut u = Unstructured::new(&data);
+        let ChunkedMixture { chunks } = ChunkedMixture::arbitrary(&mut u).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| !chunk.is_empty()));
+        assert!(NewlineMixture::arbitrary(&mut Unstructured::new(&[])).unwrap().0.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn streaming_matches_whole_text(input: ChunkedMixture, ending: LineEnding) {
+            let mut stream = crate::StreamNormalizer::new(ending);
+            let mut out: String = input.chunks.iter().map(|chunk| stream.push(chunk)).collect();
+            out.push_str(stream.finish());
+            let text = input.text();
+            prop_assert_eq!(out, ending.normalize(&text));
+        }
+    }
+}
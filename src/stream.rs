@@ -0,0 +1,150 @@
+//! Normalization of text that arrives in chunks.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use crate::{LineEnding, ToDosNewlines, ToUnixNewlines};
+
+/// Normalizes a stream of `&str` chunks, carrying newline state across chunk boundaries.
+///
+/// - A CRLF split between two chunks produces exactly one newline.
+/// - Chunks that need no change are returned borrowed.
+/// - Call [`finish`](StreamNormalizer::finish) once the input ends and emit what it returns.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{LineEnding, StreamNormalizer};
+///
+/// let mut normalizer = StreamNormalizer::new(LineEnding::Lf);
+/// let mut out = String::new();
+/// for chunk in ["one\r", "\ntwo\r", "three"] {
+///     out.push_str(&normalizer.push(chunk));
+/// }
+/// out.push_str(normalizer.finish());
+/// assert_eq!(out, "one\ntwo\nthree");
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamNormalizer {
+    ending: LineEnding,
+    after_cr: bool,
+}
+
+impl StreamNormalizer {
+    /// Creates a normalizer producing `ending`.
+    pub fn new(ending: LineEnding) -> Self {
+        StreamNormalizer { ending, after_cr: false }
+    }
+
+    /// The line ending this normalizer produces.
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    /// Normalizes the next chunk of the stream.
+    ///
+    /// Returns a borrowed reference if the chunk can be emitted as-is.
+    pub fn push<'a>(&mut self, chunk: &'a str) -> Cow<'a, str> {
+        if chunk.is_empty() {
+            return Cow::Borrowed(chunk);
+        }
+
+        let after_cr = core::mem::replace(&mut self.after_cr, chunk.ends_with('\r'));
+        let continues_crlf = after_cr && chunk.starts_with('\n');
+
+        match self.ending {
+            // A trailing CR is emitted as LF right away, so only its LF partner needs skipping.
+            LineEnding::Lf => chunk[usize::from(continues_crlf)..].to_unix_newlines(),
+            // A trailing CR is passed through raw and completed by the next chunk or `finish`.
+            LineEnding::CrLf => {
+                let start = usize::from(continues_crlf);
+                let end = chunk.len() - usize::from(self.after_cr);
+                let body = chunk[start..end].to_dos_newlines();
+
+                if !after_cr || continues_crlf {
+                    if let Cow::Borrowed(_) = body {
+                        return Cow::Borrowed(chunk);
+                    }
+                }
+
+                let mut out = String::with_capacity(chunk.len() + 2);
+                if after_cr && !continues_crlf {
+                    out.push('\n');
+                }
+                out.push_str(&chunk[..start]);
+                out.push_str(&body);
+                out.push_str(&chunk[end..]);
+                Cow::Owned(out)
+            }
+        }
+    }
+
+    /// Ends the stream, returning any output still owed and resetting the boundary state.
+    ///
+    /// For DOS output this is the `\n` completing a CR that ended the last chunk.
+    pub fn finish(&mut self) -> &'static str {
+        let after_cr = core::mem::replace(&mut self.after_cr, false);
+        match self.ending {
+            LineEnding::CrLf if after_cr => "\n",
+            _ => "",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(ending: LineEnding, chunks: &[&str]) -> String {
+        let mut normalizer = StreamNormalizer::new(ending);
+        let mut out = String::new();
+        for chunk in chunks {
+            out.push_str(&normalizer.push(chunk));
+        }
+        out.push_str(normalizer.finish());
+        out
+    }
+
+    #[test]
+    fn unix_joins_crlf_split_across_chunks() {
+        assert_eq!(normalize(LineEnding::Lf, &["a\r", "\nb\r", "\r", "\n"]), "a\nb\n\n");
+    }
+
+    #[test]
+    fn unix_lone_cr_at_chunk_end() {
+        assert_eq!(normalize(LineEnding::Lf, &["a\r", "b\r"]), "a\nb\n");
+    }
+
+    #[test]
+    fn dos_joins_crlf_split_across_chunks() {
+        assert_eq!(normalize(LineEnding::CrLf, &["a\r", "\nb\n", "\r", "c"]), "a\r\nb\r\n\r\nc");
+    }
+
+    #[test]
+    fn dos_completes_trailing_cr_on_finish() {
+        assert_eq!(normalize(LineEnding::CrLf, &["a\r", "", "b\r"]), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn dos_lf_only_chunk_after_cr() {
+        assert_eq!(normalize(LineEnding::CrLf, &["\r", "\n", "\n"]), "\r\n\r\n");
+    }
+
+    #[test]
+    fn borrows_normalized_chunks() {
+        let mut normalizer = StreamNormalizer::new(LineEnding::CrLf);
+        assert!(matches!(normalizer.push("a\r\nb\r"), Cow::Borrowed("a\r\nb\r")));
+        assert!(matches!(normalizer.push("\nc"), Cow::Borrowed("\nc")));
+
+        let mut normalizer = StreamNormalizer::new(LineEnding::Lf);
+        assert!(matches!(normalizer.push("a\r"), Cow::Owned(_)));
+        assert!(matches!(normalizer.push("\nb"), Cow::Borrowed("b")));
+    }
+
+    #[test]
+    fn finish_resets_state() {
+        let mut normalizer = StreamNormalizer::new(LineEnding::Lf);
+        normalizer.push("a\r");
+        normalizer.finish();
+        assert_eq!(normalizer.push("\nb"), "\nb");
+    }
+}
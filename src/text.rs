@@ -0,0 +1,370 @@
+//! Strings that are known to have one kind of line ending.
+//!
+//! [`NormalizedString`], and its forms [`UnixText`] and [`DosText`], normalize whatever they
+//! are built from, so a field of either type needs no normalizing again. With the `serde`
+//! feature they serialize as plain strings and normalize when deserialized; with the `sqlx`
+//! or `diesel` feature they are text columns, normalized as they are read, so rows written
+//! before the type was adopted come out normalized too.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use crate::LineEnding;
+
+/// Normalizes `text`, keeping its buffer when nothing changes.
+pub(crate) fn normalize_owned(text: String, ending: LineEnding) -> String {
+    let normalized = match ending.normalize(&text) {
+        Cow::Owned(normalized) => Some(normalized),
+        Cow::Borrowed(_) => None,
+    };
+    normalized.unwrap_or(text)
+}
+
+/// The line ending of a [`NormalizedString`], [`Lf`] or [`CrLf`].
+pub trait Ending {
+    /// The line ending itself.
+    const ENDING: LineEnding;
+}
+
+/// LF line endings, those of [`UnixText`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lf;
+
+impl Ending for Lf {
+    const ENDING: LineEnding = LineEnding::Lf;
+}
+
+/// CRLF line endings, those of [`DosText`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CrLf;
+
+impl Ending for CrLf {
+    const ENDING: LineEnding = LineEnding::CrLf;
+}
+
+/// A string whose line endings are all `E`: a value of this type is proof that the text is
+/// normalized, so functions taking one need not normalize again.
+///
+/// [`new`](Self::new) normalizes whatever it is given, while
+/// [`from_normalized`](Self::from_normalized) only checks, without allocating, and hands
+/// the string back if it is not normalized.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{CrLf, NormalizedString};
+///
+/// fn send(body: &NormalizedString<CrLf>) -> usize {
+///     body.len()
+/// }
+///
+/// let body = NormalizedString::<CrLf>::new("a\nb");
+/// assert_eq!(send(&body), 4);
+/// assert!(NormalizedString::<CrLf>::from_normalized("a\r\nb").is_ok());
+/// assert_eq!(NormalizedString::<CrLf>::from_normalized("a\nb").unwrap_err().into_string(), "a\nb");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "diesel", derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow))]
+#[cfg_attr(feature = "diesel", diesel(sql_type = diesel::sql_types::Text))]
+pub struct NormalizedString<E>(String, PhantomData<E>);
+
+/// A string whose line endings are all LF: every CRLF and lone CR it is built from is
+/// turned into an LF.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::UnixText;
+///
+/// let text = UnixText::new("a\r\nb\rc");
+/// assert_eq!(text, "a\nb\nc");
+/// assert_eq!(text.lines().count(), 3);
+/// ```
+pub type UnixText = NormalizedString<Lf>;
+
+/// A string whose line endings are all CRLF: every LF and lone CR it is built from is
+/// turned into a CRLF.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::DosText;
+///
+/// let mut text = DosText::new("a\nb");
+/// text.push_str("\nc\r\n");
+/// assert_eq!(text, "a\r\nb\r\nc\r\n");
+/// ```
+pub type DosText = NormalizedString<CrLf>;
+
+impl<E: Ending> NormalizedString<E> {
+    /// Normalizes `text`, reusing its buffer when it already is normalized.
+    pub fn new(text: impl Into<String>) -> Self {
+        NormalizedString(normalize_owned(text.into(), E::ENDING), PhantomData)
+    }
+
+    /// Takes `text` if it already is normalized, or hands it back in the error.
+    pub fn from_normalized(text: impl Into<String>) -> Result<Self, NotNormalized> {
+        let text = text.into();
+        match E::ENDING.is_normalized(&text) {
+            true => Ok(NormalizedString(text, PhantomData)),
+            false => Err(NotNormalized { text, ending: E::ENDING }),
+        }
+    }
+
+    /// The line ending of the string.
+    pub fn ending(&self) -> LineEnding {
+        E::ENDING
+    }
+
+    /// Appends `text`, normalized.
+    ///
+    /// A CR ending `text` is converted at once, not held back to see whether the next push
+    /// starts with an LF: [`as_str`](Self::as_str) always returns everything pushed so far,
+    /// normalized, and a CR ending one push and an LF starting the next count as two line
+    /// endings, not one CRLF.
+    pub fn push_str(&mut self, text: &str) {
+        self.0.push_str(&E::ENDING.normalize(text));
+    }
+
+    /// Converts the string to the line ending `F`.
+    pub fn into_ending<F: Ending>(self) -> NormalizedString<F> {
+        NormalizedString::new(self.0)
+    }
+}
+
+impl<E> NormalizedString<E> {
+    /// The normalized string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Hands over the normalized string.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl<E> fmt::Debug for NormalizedString<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<E> Deref for NormalizedString<E> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<E> AsRef<str> for NormalizedString<E> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<E: Ending> From<String> for NormalizedString<E> {
+    fn from(text: String) -> Self {
+        NormalizedString::new(text)
+    }
+}
+
+impl<E: Ending> From<&str> for NormalizedString<E> {
+    fn from(text: &str) -> Self {
+        NormalizedString::new(text)
+    }
+}
+
+impl<E> From<NormalizedString<E>> for String {
+    fn from(text: NormalizedString<E>) -> String {
+        text.0
+    }
+}
+
+impl<E> PartialEq<str> for NormalizedString<E> {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl<E> PartialEq<&str> for NormalizedString<E> {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<E> fmt::Display for NormalizedString<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The error of [`NormalizedString::from_normalized`]: the text has other line endings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotNormalized {
+    text: String,
+    ending: LineEnding,
+}
+
+impl NotNormalized {
+    /// Hands back the text.
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl fmt::Display for NotNormalized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ending = match self.ending {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        };
+        write!(f, "text has line endings other than {ending}")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NotNormalized {}
+
+#[cfg(feature = "serde")]
+impl<E> serde::Serialize for NormalizedString<E> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, E: Ending> serde::Deserialize<'de> for NormalizedString<E> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(NormalizedString::new)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database, E> sqlx::Type<DB> for NormalizedString<E>
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'q, DB: sqlx::Database, E> sqlx::Encode<'q, DB> for NormalizedString<E>
+where
+    String: sqlx::Encode<'q, DB>,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer<'q>) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        self.0.encode_by_ref(buf)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: sqlx::Database, E: Ending> sqlx::Decode<'r, DB> for NormalizedString<E>
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        String::decode(value).map(NormalizedString::new)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl<DB: diesel::backend::Backend, E> diesel::serialize::ToSql<diesel::sql_types::Text, DB> for NormalizedString<E>
+where
+    str: diesel::serialize::ToSql<diesel::sql_types::Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut diesel::serialize::Output<'b, '_, DB>) -> diesel::serialize::Result {
+        self.0.as_str().to_sql(out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl<DB: diesel::backend::Backend, E: Ending> diesel::deserialize::FromSql<diesel::sql_types::Text, DB> for NormalizedString<E>
+where
+    String: diesel::deserialize::FromSql<diesel::sql_types::Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        String::from_sql(bytes).map(NormalizedString::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_normalized() {
+        assert_eq!(UnixText::from(String::from("a\r\n")).into_string(), "a\n");
+        assert_eq!(DosText::from("a\rb").as_str(), "a\r\nb");
+        assert_eq!(UnixText::default(), "");
+        let mut text = UnixText::new("a\r");
+        text.push_str("\nb");
+        assert_eq!(text, "a\n\nb");
+    }
+
+    #[test]
+    fn checked_and_converted() {
+        let text = DosText::from_normalized(String::from("a\r\nb")).unwrap();
+        assert_eq!(text.ending(), LineEnding::CrLf);
+        assert_eq!(text.clone().into_ending::<Lf>(), "a\nb");
+        let error = UnixText::from_normalized("a\r\n").unwrap_err();
+        assert_eq!(alloc::string::ToString::to_string(&error), "text has line endings other than LF");
+        assert_eq!(error.into_string(), "a\r\n");
+        assert_eq!(alloc::format!("{text:?}"), r#""a\r\nb""#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn plain_strings_in_serde() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Note {
+            body: DosText,
+        }
+        let note: Note = serde_json::from_str(r#"{"body": "a\nb"}"#).unwrap();
+        assert_eq!(note.body, "a\r\nb");
+        assert_eq!(serde_json::to_string(&note).unwrap(), r#"{"body":"a\r\nb"}"#);
+        assert!(serde_json::from_str::<Note>(r#"{"body": 1}"#).is_err());
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    async fn sqlx_columns_normalize_on_read() {
+        use sqlx::Connection;
+
+        let mut db = sqlx::SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE notes (body TEXT)").execute(&mut db).await.unwrap();
+        sqlx::query("INSERT INTO notes VALUES ('legacy' || char(13, 10))").execute(&mut db).await.unwrap();
+        sqlx::query("INSERT INTO notes VALUES (?)").bind(UnixText::new("new\r\n")).execute(&mut db).await.unwrap();
+        let bodies: Vec<(UnixText,)> = sqlx::query_as("SELECT body FROM notes").fetch_all(&mut db).await.unwrap();
+        assert_eq!(bodies, [(UnixText::new("legacy\n"),), (UnixText::new("new\n"),)]);
+        let raw: String = sqlx::query_scalar("SELECT body FROM notes WHERE rowid = 2").fetch_one(&mut db).await.unwrap();
+        assert_eq!(raw, "new\n");
+    }
+
+    #[cfg(feature = "diesel")]
+    #[test]
+    fn diesel_columns_normalize_on_read() {
+        use diesel::prelude::*;
+        use diesel::sql_types::Text;
+
+        #[derive(QueryableByName)]
+        struct Note {
+            #[diesel(sql_type = Text)]
+            body: DosText,
+        }
+
+        let mut db = SqliteConnection::establish(":memory:").unwrap();
+        diesel::sql_query("CREATE TABLE notes (body TEXT)").execute(&mut db).unwrap();
+        diesel::sql_query("INSERT INTO notes VALUES ('a' || char(10) || 'b')").execute(&mut db).unwrap();
+        diesel::sql_query("INSERT INTO notes VALUES (?)").bind::<Text, _>(DosText::new("c\n")).execute(&mut db).unwrap();
+        let notes: Vec<Note> = diesel::sql_query("SELECT body FROM notes").load(&mut db).unwrap();
+        assert_eq!(notes.iter().map(|note| note.body.as_str()).collect::<Vec<_>>(), ["a\r\nb", "c\r\n"]);
+    }
+}
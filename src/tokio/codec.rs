@@ -0,0 +1,261 @@
+//! `tokio-util` codec framing lines on any newline style.
+
+use std::{error, fmt, io};
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::LineEnding;
+
+/// Splits frames on CR, LF or CRLF and encodes lines with a configured ending.
+///
+/// Unlike `LinesCodec`, a lone CR ends a line, and a CRLF split across two reads is one
+/// terminator. Decoded lines have their terminator stripped unless
+/// [`keep_terminator`](Self::keep_terminator) is set, in which case they end with the
+/// configured ending instead.
+///
+/// Example:
+/// ```
+/// use bytes::BytesMut;
+/// use tokio_util::codec::Decoder;
+/// use newline_normalizer::tokio::NormalizedLinesCodec;
+///
+/// let mut codec = NormalizedLinesCodec::new();
+/// let mut buf = BytesMut::from("one\rtwo\r\nthree");
+/// assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some("one"));
+/// assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some("two"));
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// assert_eq!(codec.decode_eof(&mut buf).unwrap().as_deref(), Some("three"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NormalizedLinesCodec {
+    ending: LineEnding,
+    keep_terminator: bool,
+    max_length: usize,
+    next_index: usize,
+    after_cr: bool,
+    discarding: bool,
+}
+
+impl NormalizedLinesCodec {
+    /// Creates a codec that strips terminators on decode and writes `\n` on encode.
+    pub fn new() -> Self {
+        NormalizedLinesCodec {
+            ending: LineEnding::Lf,
+            keep_terminator: false,
+            max_length: usize::MAX,
+            next_index: 0,
+            after_cr: false,
+            discarding: false,
+        }
+    }
+
+    /// Sets the line ending written by the encoder and, with `keep_terminator`, by the decoder.
+    pub fn with_ending(mut self, ending: LineEnding) -> Self {
+        self.ending = ending;
+        self
+    }
+
+    /// Ends every decoded line with the configured line ending instead of stripping it.
+    pub fn keep_terminator(mut self, keep: bool) -> Self {
+        self.keep_terminator = keep;
+        self
+    }
+
+    /// Lines longer than `max_length` bytes fail with [`NormalizedLinesCodecError::MaxLineLengthExceeded`]
+    /// and are skipped up to their terminator.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// The line ending this codec writes.
+    pub fn ending(&self) -> LineEnding {
+        self.ending
+    }
+
+    fn line(&self, bytes: &[u8]) -> Result<String, NormalizedLinesCodecError> {
+        let line = std::str::from_utf8(bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut out = String::with_capacity(line.len() + 2);
+        out.push_str(line);
+        if self.keep_terminator {
+            out.push_str(self.ending.as_str());
+        }
+        Ok(out)
+    }
+}
+
+impl Default for NormalizedLinesCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for NormalizedLinesCodec {
+    type Item = String;
+    type Error = NormalizedLinesCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<String>, Self::Error> {
+        loop {
+            if self.after_cr {
+                match buf.first() {
+                    None => return Ok(None),
+                    Some(b'\n') => buf.advance(1),
+                    Some(_) => {}
+                }
+                self.after_cr = false;
+            }
+
+            let from = self.next_index.min(buf.len());
+            let Some(found) = crate::search::memchr2(b'\n', b'\r', &buf[from..]).map(|pos| pos + from) else {
+                if buf.len() > self.max_length && !self.discarding {
+                    self.discarding = true;
+                    buf.clear();
+                    self.next_index = 0;
+                    return Err(NormalizedLinesCodecError::MaxLineLengthExceeded);
+                }
+                if self.discarding {
+                    buf.clear();
+                }
+                self.next_index = buf.len();
+                return Ok(None);
+            };
+
+            self.next_index = 0;
+            let terminated = buf.split_to(found + 1);
+            if terminated[found] == b'\r' {
+                match buf.first() {
+                    Some(b'\n') => buf.advance(1),
+                    Some(_) => {}
+                    None => self.after_cr = true,
+                }
+            }
+
+            if std::mem::replace(&mut self.discarding, false) {
+                continue;
+            }
+            if found > self.max_length {
+                return Err(NormalizedLinesCodecError::MaxLineLengthExceeded);
+            }
+            return self.line(&terminated[..found]).map(Some);
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<String>, Self::Error> {
+        if let Some(line) = self.decode(buf)? {
+            return Ok(Some(line));
+        }
+        self.after_cr = false;
+        self.next_index = 0;
+        if buf.is_empty() || std::mem::replace(&mut self.discarding, false) {
+            buf.clear();
+            return Ok(None);
+        }
+        let rest = buf.split();
+        let line = std::str::from_utf8(&rest).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(line.to_owned()))
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for NormalizedLinesCodec {
+    type Error = NormalizedLinesCodecError;
+
+    /// Writes the line with its inner newlines normalized, followed by the configured ending.
+    fn encode(&mut self, line: T, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        let line = self.ending.normalize(line.as_ref());
+        buf.reserve(line.len() + 2);
+        buf.put_slice(line.as_bytes());
+        buf.put_slice(self.ending.as_bytes());
+        Ok(())
+    }
+}
+
+/// Error returned by [`NormalizedLinesCodec`].
+#[derive(Debug)]
+pub enum NormalizedLinesCodecError {
+    /// A line was longer than the configured maximum length.
+    MaxLineLengthExceeded,
+    /// An I/O error occurred, or a line was not valid UTF-8 (`InvalidData`).
+    Io(io::Error),
+}
+
+impl fmt::Display for NormalizedLinesCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizedLinesCodecError::MaxLineLengthExceeded => f.write_str("max line length exceeded"),
+            NormalizedLinesCodecError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for NormalizedLinesCodecError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            NormalizedLinesCodecError::MaxLineLengthExceeded => None,
+            NormalizedLinesCodecError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for NormalizedLinesCodecError {
+    fn from(e: io::Error) -> Self {
+        NormalizedLinesCodecError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(codec: &mut NormalizedLinesCodec, pieces: &[&str]) -> Vec<String> {
+        let mut buf = BytesMut::new();
+        let mut lines = Vec::new();
+        for piece in pieces {
+            buf.extend_from_slice(piece.as_bytes());
+            while let Some(line) = codec.decode(&mut buf).unwrap() {
+                lines.push(line);
+            }
+        }
+        while let Some(line) = codec.decode_eof(&mut buf).unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[test]
+    fn crlf_split_across_reads_is_one_terminator() {
+        let mut codec = NormalizedLinesCodec::new();
+        assert_eq!(decode_all(&mut codec, &["a\r", "\nb\r", "\r", "\nc"]), ["a", "b", "", "c"]);
+    }
+
+    #[test]
+    fn keeps_configured_terminator() {
+        let mut codec = NormalizedLinesCodec::new().with_ending(LineEnding::CrLf).keep_terminator(true);
+        assert_eq!(decode_all(&mut codec, &["a\nb\r", "c"]), ["a\r\n", "b\r\n", "c"]);
+    }
+
+    #[test]
+    fn skips_overlong_line() {
+        let mut codec = NormalizedLinesCodec::new().with_max_length(3);
+        let mut buf = BytesMut::from("abcdef");
+        assert!(matches!(codec.decode(&mut buf), Err(NormalizedLinesCodecError::MaxLineLengthExceeded)));
+        buf.extend_from_slice(b"gh\nok\n");
+        assert_eq!(codec.decode(&mut buf).unwrap().as_deref(), Some("ok"));
+    }
+
+    #[test]
+    fn encodes_with_configured_ending() {
+        let mut codec = NormalizedLinesCodec::new().with_ending(LineEnding::CrLf);
+        let mut buf = BytesMut::new();
+        codec.encode("a\nb", &mut buf).unwrap();
+        assert_eq!(&buf[..], b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        let mut codec = NormalizedLinesCodec::new();
+        let mut buf = BytesMut::from(&b"\xff\n"[..]);
+        assert!(matches!(codec.decode(&mut buf), Err(NormalizedLinesCodecError::Io(_))));
+    }
+}
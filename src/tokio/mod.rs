@@ -0,0 +1,296 @@
+//! Tokio `AsyncRead`/`AsyncWrite` adapters, enabled by the `tokio` feature.
+//!
+//! These mirror [`NormalizingReader`](crate::NormalizingReader) and
+//! [`NormalizingWriter`](crate::NormalizingWriter). Newline state is carried across polls,
+//! so a CRLF split between two reads or writes still produces a single newline.
+//!
+//! With the `tokio-util` feature, [`NormalizedLinesCodec`] frames lines for `FramedRead`/`FramedWrite`.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats, StreamConfig};
+
+#[cfg(feature = "tokio-util")]
+mod codec;
+
+#[cfg(feature = "tokio-util")]
+pub use codec::{NormalizedLinesCodec, NormalizedLinesCodecError};
+
+pin_project_lite::pin_project! {
+    /// Wraps an [`AsyncRead`] and normalizes every newline read through it.
+    ///
+    /// Example:
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use tokio::io::AsyncReadExt;
+    /// use newline_normalizer::tokio::NormalizingReader;
+    ///
+    /// let mut reader = NormalizingReader::unix("one\r\ntwo\r".as_bytes());
+    /// let mut out = String::new();
+    /// reader.read_to_string(&mut out).await.unwrap();
+    /// assert_eq!(out, "one\ntwo\n");
+    /// # }
+    /// ```
+    #[derive(Debug)]
+    pub struct NormalizingReader<R> {
+        #[pin]
+        inner: R,
+        engine: Engine,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        output_pos: usize,
+    }
+}
+
+impl<R: AsyncRead> NormalizingReader<R> {
+    /// Creates a reader producing `ending`.
+    pub fn new(inner: R, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new())
+    }
+
+    /// Creates a reader producing `ending` that reads up to `capacity` bytes at a time.
+    pub fn with_capacity(capacity: usize, inner: R, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new().chunk_size(capacity))
+    }
+
+    /// Creates a reader producing `ending` whose buffering is bounded by `config`.
+    pub fn with_config(inner: R, ending: LineEnding, config: StreamConfig) -> Self {
+        let chunk_size = config.get_chunk_size();
+        NormalizingReader {
+            inner,
+            engine: Engine::new(ending),
+            input: vec![0; chunk_size],
+            output: Vec::with_capacity(chunk_size),
+            output_pos: 0,
+        }
+    }
+
+    /// Creates a reader producing Unix newlines (`\n`).
+    pub fn unix(inner: R) -> Self {
+        Self::new(inner, LineEnding::Lf)
+    }
+
+    /// Creates a reader producing DOS newlines (`\r\n`).
+    pub fn dos(inner: R) -> Self {
+        Self::new(inner, LineEnding::CrLf)
+    }
+}
+
+impl<R> NormalizingReader<R> {
+    /// The line ending this reader produces.
+    pub fn ending(&self) -> LineEnding {
+        self.engine.ending()
+    }
+
+    /// Counters for everything normalized so far; poll this to report progress.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this reader, discarding any normalized bytes not yet read.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for NormalizingReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        while *this.output_pos == this.output.len() {
+            let mut input = ReadBuf::new(this.input);
+            ready!(this.inner.as_mut().poll_read(cx, &mut input))?;
+            if input.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            this.output.clear();
+            *this.output_pos = 0;
+            this.engine.normalize_into(input.filled(), this.output);
+        }
+
+        let available = &this.output[*this.output_pos..];
+        let len = available.len().min(buf.remaining());
+        buf.put_slice(&available[..len]);
+        *this.output_pos += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Wraps an [`AsyncWrite`] and normalizes every newline written through it.
+    ///
+    /// Each write accepts at most one chunk of [`StreamConfig`]. Accepted data is
+    /// buffered until the inner writer takes it, so call `flush` or
+    /// `shutdown` before dropping the writer.
+    ///
+    /// Example:
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use tokio::io::AsyncWriteExt;
+    /// use newline_normalizer::tokio::NormalizingWriter;
+    ///
+    /// let mut writer = NormalizingWriter::dos(Vec::new());
+    /// writer.write_all(b"one\r").await.unwrap();
+    /// writer.write_all(b"\ntwo\n").await.unwrap();
+    /// writer.flush().await.unwrap();
+    /// assert_eq!(writer.into_inner(), b"one\r\ntwo\r\n");
+    /// # }
+    /// ```
+    #[derive(Debug)]
+    pub struct NormalizingWriter<W> {
+        #[pin]
+        inner: W,
+        engine: Engine,
+        pending: Vec<u8>,
+        pending_pos: usize,
+        chunk_size: usize,
+    }
+}
+
+impl<W: AsyncWrite> NormalizingWriter<W> {
+    /// Creates a writer producing `ending`.
+    pub fn new(inner: W, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new())
+    }
+
+    /// Creates a writer producing `ending` whose buffering is bounded by `config`.
+    pub fn with_config(inner: W, ending: LineEnding, config: StreamConfig) -> Self {
+        NormalizingWriter {
+            inner,
+            engine: Engine::new(ending),
+            pending: Vec::new(),
+            pending_pos: 0,
+            chunk_size: config.get_chunk_size(),
+        }
+    }
+
+    /// Creates a writer producing Unix newlines (`\n`).
+    pub fn unix(inner: W) -> Self {
+        Self::new(inner, LineEnding::Lf)
+    }
+
+    /// Creates a writer producing DOS newlines (`\r\n`).
+    pub fn dos(inner: W) -> Self {
+        Self::new(inner, LineEnding::CrLf)
+    }
+
+    fn poll_drain(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        while *this.pending_pos < this.pending.len() {
+            let written = ready!(this.inner.as_mut().poll_write(cx, &this.pending[*this.pending_pos..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            *this.pending_pos += written;
+        }
+        this.pending.clear();
+        *this.pending_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W> NormalizingWriter<W> {
+    /// The line ending this writer produces.
+    pub fn ending(&self) -> LineEnding {
+        self.engine.ending()
+    }
+
+    /// Counters for everything normalized so far; poll this to report progress.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this writer, discarding any normalized bytes not yet written.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for NormalizingWriter<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+        let this = self.project();
+        let buf = &buf[..buf.len().min(*this.chunk_size)];
+        this.engine.normalize_into(buf, this.pending);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_drain(cx))?;
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// Yields its data in fixed-size pieces to exercise chunk boundaries.
+    struct Chunked<'a>(&'a [u8], usize);
+
+    impl AsyncRead for Chunked<'_> {
+        fn poll_read(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+            let len = self.0.len().min(self.1).min(buf.remaining());
+            buf.put_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[::tokio::test]
+    async fn reader_handles_crlf_split_across_reads() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            let mut out = Vec::new();
+            NormalizingReader::unix(Chunked(input, size)).read_to_end(&mut out).await.unwrap();
+            assert_eq!(out, b"a\nb\nc\n\n\nd\n", "chunk size {size}");
+        }
+    }
+
+    #[::tokio::test]
+    async fn writer_handles_crlf_split_across_writes() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            let mut writer = NormalizingWriter::dos(Vec::new());
+            for chunk in input.chunks(size) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            assert_eq!(writer.into_inner(), b"a\r\nb\r\nc\r\n\r\n\r\nd\r\n", "chunk size {size}");
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Tower middleware, enabled by the `tower` feature, normalizing text request bodies before
+//! they reach the handlers of axum, hyper or tonic services.
+//!
+//! [`NormalizeBodyLayer`] normalizes every `text/*` body, and those of the other content
+//! types it is given, such as `application/json`, as they stream in. Other bodies pass
+//! through untouched. Either way the service beneath receives a [`NormalizedBody`].
+//!
+//! Only raw newline bytes change: a `\r\n` escaped inside a JSON string is not a line ending
+//! of the body and is left as it is.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use ::bytes::{Buf, Bytes};
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, Request};
+use http_body::{Body, Frame, SizeHint};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::engine::Engine;
+use crate::LineEnding;
+
+/// A [`Layer`] normalizing the bodies of text requests to one line ending.
+///
+/// Example:
+/// ```
+/// # futures::executor::block_on(async {
+/// use bytes::Bytes;
+/// use http::Request;
+/// use http_body_util::{BodyExt, Full};
+/// use newline_normalizer::tower::{NormalizeBodyLayer, NormalizedBody};
+/// use newline_normalizer::LineEnding;
+/// use tower::{service_fn, ServiceBuilder, ServiceExt};
+///
+/// let service = ServiceBuilder::new()
+///     .layer(NormalizeBodyLayer::new(LineEnding::Lf).content_type("application/json"))
+///     .service(service_fn(|request: Request<NormalizedBody<Full<Bytes>>>| async move {
+///         let body = request.into_body().collect().await?.to_bytes();
+///         Ok::<_, std::convert::Infallible>(body)
+///     }));
+///
+/// let request = Request::post("/hook")
+///     .header("content-type", "text/plain; charset=utf-8")
+///     .body(Full::new(Bytes::from("one\r\ntwo\r\n")))
+///     .unwrap();
+/// assert_eq!(service.oneshot(request).await.unwrap(), "one\ntwo\n");
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct NormalizeBodyLayer {
+    config: Arc<Config>,
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    ending: LineEnding,
+    content_types: Vec<String>,
+}
+
+impl NormalizeBodyLayer {
+    /// Normalizes `text/*` request bodies to `ending`.
+    pub fn new(ending: LineEnding) -> Self {
+        NormalizeBodyLayer { config: Arc::new(Config { ending, content_types: Vec::new() }) }
+    }
+
+    /// Also normalizes bodies of `content_type`, a media type without parameters such as
+    /// `application/json`. Matching ignores ASCII case and any parameters of the request's
+    /// `Content-Type`.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).content_types.push(content_type.into());
+        self
+    }
+}
+
+impl<S> Layer<S> for NormalizeBodyLayer {
+    type Service = NormalizeBody<S>;
+
+    fn layer(&self, inner: S) -> NormalizeBody<S> {
+        NormalizeBody { inner, config: self.config.clone() }
+    }
+}
+
+impl Config {
+    fn applies_to(&self, headers: &HeaderMap) -> bool {
+        let Some(value) = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+            return false;
+        };
+        let essence = value.split(';').next().unwrap_or_default().trim();
+        let text = essence.get(..5).map_or(false, |prefix| prefix.eq_ignore_ascii_case("text/"));
+        text || self.content_types.iter().any(|wanted| wanted.eq_ignore_ascii_case(essence))
+    }
+}
+
+/// The [`Service`] made by [`NormalizeBodyLayer`].
+#[derive(Debug, Clone)]
+pub struct NormalizeBody<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S> NormalizeBody<S> {
+    /// Unwraps this middleware, returning the service beneath it.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, B> Service<Request<B>> for NormalizeBody<S>
+where
+    S: Service<Request<NormalizedBody<B>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> S::Future {
+        let normalize = self.config.applies_to(request.headers());
+        let (mut parts, body) = request.into_parts();
+        if normalize {
+            // The normalized body may be longer or shorter than announced.
+            parts.headers.remove(CONTENT_LENGTH);
+        }
+        let engine = normalize.then(|| Engine::new(self.config.ending));
+        self.inner.call(Request::from_parts(parts, NormalizedBody { inner: body, engine }))
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A request body, normalized as it streams in if its content type was selected.
+    ///
+    /// Data frames that need no change keep their buffer; trailers pass through.
+    #[derive(Debug)]
+    pub struct NormalizedBody<B> {
+        #[pin]
+        inner: B,
+        engine: Option<Engine>,
+    }
+}
+
+impl<B> NormalizedBody<B> {
+    /// Whether this body is being normalized.
+    pub fn is_normalized(&self) -> bool {
+        self.engine.is_some()
+    }
+
+    /// Unwraps this body, returning the one from the request.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> Body for NormalizedBody<B>
+where
+    B: Body,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        let mut this = self.project();
+        loop {
+            let frame = match ready!(this.inner.as_mut().poll_frame(cx)) {
+                None => return Poll::Ready(None),
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Some(Ok(frame)) => frame,
+            };
+            let mut data = match frame.into_data() {
+                Ok(data) => data,
+                Err(frame) => return Poll::Ready(Some(Ok(frame.map_data(|_| Bytes::new())))),
+            };
+            let data = data.copy_to_bytes(data.remaining());
+            let Some(engine) = this.engine.as_mut() else {
+                return Poll::Ready(Some(Ok(Frame::data(data))));
+            };
+
+            let out = engine.normalize_chunk(&data);
+            if out.is_empty() {
+                // The LF of a CRLF split across frames.
+                continue;
+            }
+            return Poll::Ready(Some(Ok(Frame::data(match out {
+                Cow::Borrowed(_) => data,
+                Cow::Owned(out) => Bytes::from(out),
+            }))));
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.engine {
+            None => self.inner.size_hint(),
+            Some(_) => SizeHint::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::stream;
+    use http_body_util::{BodyExt, Full, StreamBody};
+    use tower::{service_fn, ServiceExt};
+
+    fn echo(layer: NormalizeBodyLayer, request: Request<impl Body<Error = ()> + Send + 'static>) -> (bool, Bytes) {
+        let service = layer.layer(service_fn(|request: Request<NormalizedBody<_>>| async move {
+            let length = request.headers().contains_key(CONTENT_LENGTH);
+            Ok::<_, ()>((length, request.into_body().collect().await?.to_bytes()))
+        }));
+        block_on(service.oneshot(request)).unwrap()
+    }
+
+    fn request<B>(content_type: &str, body: B) -> Request<B> {
+        Request::post("/").header(CONTENT_TYPE, content_type).header(CONTENT_LENGTH, "6").body(body).unwrap()
+    }
+
+    #[test]
+    fn selects_bodies_by_content_type() {
+        let layer = NormalizeBodyLayer::new(LineEnding::CrLf).content_type("application/json");
+        let body = || Full::new(Bytes::from_static(b"a\nb\r\n")).map_err(|never| match never {});
+        assert_eq!(echo(layer.clone(), request("TEXT/csv", body())), (false, Bytes::from("a\r\nb\r\n")));
+        assert_eq!(echo(layer.clone(), request("application/json; charset=utf-8", body())).1, "a\r\nb\r\n");
+        assert_eq!(echo(layer, request("application/octet-stream", body())), (true, Bytes::from("a\nb\r\n")));
+    }
+
+    #[test]
+    fn carries_crlf_across_frames() {
+        let frames = [Ok(Frame::data(Bytes::from("one\r"))), Ok(Frame::data(Bytes::from("\ntwo\r")))];
+        let body = StreamBody::new(stream::iter(frames));
+        let (_, out) = echo(NormalizeBodyLayer::new(LineEnding::Lf), request("text/plain", body));
+        assert_eq!(out, "one\ntwo\n");
+
+        let frames = [Ok(Frame::data(Bytes::from("x\r"))), Ok(Frame::data(Bytes::from("\nab\n")))];
+        let body = StreamBody::new(stream::iter(frames));
+        let (_, out) = echo(NormalizeBodyLayer::new(LineEnding::CrLf), request("text/plain", body));
+        assert_eq!(out, "x\r\nab\r\n");
+    }
+}
@@ -0,0 +1,102 @@
+//! Instrumentation through `tracing`, compiled away without the `tracing` feature.
+//!
+//! Files and copies get a `debug` span each, closed by a `debug` event with the bytes read
+//! and written and the line endings converted. The string normalizers emit a `trace` event
+//! per call, noting whether the input was returned as it was (`fast_path`).
+
+/// Emits a `tracing` event at `$level`, such as `debug`, with the `tracing` feature.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!($($arg)+);
+    };
+}
+
+/// Enters a `tracing` span at `$level`, such as `DEBUG`, until the end of the enclosing block.
+#[cfg(feature = "std")]
+macro_rules! trace_span {
+    ($level:ident, $name:literal $(, $($field:tt)+)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::span!(::tracing::Level::$level, $name $(, $($field)+)?).entered();
+    };
+}
+
+/// Emits the event that closes a span, with the counters of `stats`.
+#[cfg(feature = "std")]
+macro_rules! trace_stats {
+    ($stats:expr, $message:literal) => {
+        #[cfg(feature = "tracing")]
+        {
+            let stats: &$crate::Stats = &$stats;
+            ::tracing::debug!(
+                bytes_read = stats.bytes_read,
+                bytes_written = stats.bytes_written,
+                converted = stats.converted,
+                changed = stats.changed(),
+                $message
+            );
+        }
+    };
+}
+
+pub(crate) use trace_event;
+#[cfg(feature = "std")]
+pub(crate) use {trace_span, trace_stats};
+
+#[cfg(all(test, feature = "std", feature = "tracing"))]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::{normalize_copy, ToUnixNewlines};
+
+    /// Records the name of every span and the fields of every event.
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push_str(&alloc::format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.0.lock().unwrap().push(span.metadata().name().to_string());
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn spans_and_events() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            normalize_copy(&mut &b"a\r\nb\r\n"[..], &mut Vec::new(), crate::LineEnding::Lf).unwrap();
+            "plain".to_unix_newlines();
+        });
+        let seen = recorder.0.lock().unwrap();
+        assert_eq!(seen[0], "normalize_copy");
+        assert!(seen[1].contains("bytes_read=6 bytes_written=4 converted=2 changed=true"), "{}", seen[1]);
+        assert!(seen[2].contains("fast_path=true"), "{}", seen[2]);
+    }
+}
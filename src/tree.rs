@@ -0,0 +1,273 @@
+//! Normalization of every text file below a directory.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::glob::path_matches;
+use crate::{looks_binary, normalize_copy, normalize_file_with_bom, BomPolicy, BomReader, FileReport, LineEnding, Stats};
+
+/// What [`normalize_tree`] normalizes, and how.
+///
+/// Globs use the `.editorconfig` syntax and are matched against paths relative to the root,
+/// with `/` between segments: a glob without a `/`, such as `*.rs`, matches file names in
+/// every directory, while `src/**/*.rs` or `/build` match from the root.
+#[derive(Debug, Clone)]
+pub struct TreeOptions {
+    ending: LineEnding,
+    bom: BomPolicy,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    binary: bool,
+    dry_run: bool,
+}
+
+impl TreeOptions {
+    /// Normalizes every file to `ending`, except those that look binary.
+    pub fn new(ending: LineEnding) -> Self {
+        TreeOptions {
+            ending,
+            bom: BomPolicy::Keep,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            binary: false,
+            dry_run: false,
+        }
+    }
+
+    /// Only normalizes files matching `glob`, or any of the globs given so far.
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.include.push(glob.into());
+        self
+    }
+
+    /// Skips files matching `glob`, and does not enter directories matching it.
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    /// What to do with a UTF-8 byte order mark; kept as it is by default.
+    pub fn bom(self, bom: BomPolicy) -> Self {
+        TreeOptions { bom, ..self }
+    }
+
+    /// Also normalizes files that [look binary](crate::looks_binary).
+    pub fn binary(self, binary: bool) -> Self {
+        TreeOptions { binary, ..self }
+    }
+
+    /// Reports what would change without writing any file.
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        TreeOptions { dry_run, ..self }
+    }
+
+    fn wants(&self, relative: &str, is_dir: bool) -> bool {
+        if self.exclude.iter().any(|glob| path_matches(glob, relative)) {
+            return false;
+        }
+        is_dir || self.include.is_empty() || self.include.iter().any(|glob| path_matches(glob, relative))
+    }
+}
+
+/// What happened to one file of the tree, as passed to the callback of
+/// [`normalize_tree_with_callback`].
+#[derive(Debug)]
+pub enum TreeFile {
+    /// The file was normalized, or with [`TreeOptions::dry_run`] would have been;
+    /// [`FileReport::changed`] tells whether it changed.
+    Normalized(FileReport),
+    /// The file looks binary and was left alone.
+    Binary,
+    /// The file could not be read or written.
+    Failed(io::Error),
+}
+
+/// The totals of a [`normalize_tree`] run.
+#[derive(Debug, Default)]
+pub struct TreeReport {
+    /// Files that were, or would be, changed.
+    pub changed: usize,
+    /// Files that needed no change.
+    pub unchanged: usize,
+    /// Files left alone because they look binary.
+    pub binary: usize,
+    /// Files and directories that could not be read or written, with the reason.
+    pub errors: Vec<(PathBuf, io::Error)>,
+    /// The counters of all normalized files together.
+    pub stats: Stats,
+}
+
+/// Normalizes the files below `root`, or `root` itself if it is a file.
+///
+/// Directories are visited in name order. `.git` directories are never entered and symbolic
+/// links below `root` are not followed; `root` itself is. A file that fails is recorded in the report and the walk goes on.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{normalize_tree, LineEnding, TreeOptions};
+///
+/// let options = TreeOptions::new(LineEnding::Lf).include("*.{rs,toml,md}").exclude("/target");
+/// let report = normalize_tree("crates", &options);
+/// println!("{} files changed, {} failed", report.changed, report.errors.len());
+/// ```
+pub fn normalize_tree<P: AsRef<Path>>(root: P, options: &TreeOptions) -> TreeReport {
+    normalize_tree_with_callback(root, options, |_, _| {})
+}
+
+/// Like [`normalize_tree`], calling `on_file(path, outcome)` after every file.
+pub fn normalize_tree_with_callback<P, F>(root: P, options: &TreeOptions, mut on_file: F) -> TreeReport
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, &TreeFile),
+{
+    let root = root.as_ref();
+    let mut report = TreeReport::default();
+    let mut visit = |path: &Path, outcome: TreeFile| {
+        on_file(path, &outcome);
+        match outcome {
+            TreeFile::Normalized(file) => {
+                report.stats += file.stats;
+                if file.changed {
+                    report.changed += 1;
+                } else {
+                    report.unchanged += 1;
+                }
+            }
+            TreeFile::Binary => report.binary += 1,
+            TreeFile::Failed(e) => report.errors.push((path.to_path_buf(), e)),
+        }
+    };
+
+    match fs::metadata(root) {
+        Ok(metadata) if metadata.is_dir() => walk(root, "", options, &mut visit),
+        Ok(_) => visit(root, normalize(root, options)),
+        Err(e) => visit(root, TreeFile::Failed(e)),
+    }
+    report
+}
+
+fn walk(dir: &Path, relative: &str, options: &TreeOptions, visit: &mut dyn FnMut(&Path, TreeFile)) {
+    let entries = match fs::read_dir(dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>()) {
+        Ok(mut entries) => {
+            entries.sort_by_key(|entry| entry.file_name());
+            entries
+        }
+        Err(e) => return visit(dir, TreeFile::Failed(e)),
+    };
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let relative = if relative.is_empty() { name.to_string() } else { format!("{relative}/{name}") };
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => {
+                if name != ".git" && options.wants(&relative, true) {
+                    walk(&path, &relative, options, visit);
+                }
+            }
+            Ok(kind) if kind.is_file() => {
+                if options.wants(&relative, false) {
+                    visit(&path, normalize(&path, options));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => visit(&path, TreeFile::Failed(e)),
+        }
+    }
+}
+
+fn normalize(path: &Path, options: &TreeOptions) -> TreeFile {
+    match looks_binary(path) {
+        Ok(true) if !options.binary => return TreeFile::Binary,
+        Ok(_) => {}
+        Err(e) => return TreeFile::Failed(e),
+    }
+    let result = if options.dry_run {
+        fs::File::open(path).and_then(|file| {
+            let mut source = BomReader::new(file, options.bom);
+            let stats = normalize_copy(&mut source, &mut io::sink(), options.ending)?;
+            Ok(FileReport { stats, changed: stats.changed() || source.changed() })
+        })
+    } else {
+        normalize_file_with_bom(path, options.ending, options.bom)
+    };
+    result.map_or_else(TreeFile::Failed, TreeFile::Normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("newline_normalizer-tree-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn filters_and_skips_binaries() {
+        let dir = tree(
+            "filters",
+            &[
+                ("a.txt", b"a\r\n"),
+                ("src/b.txt", b"b\n"),
+                ("src/c.rs", b"c\r\n"),
+                ("target/d.txt", b"d\r\n"),
+                ("logo.png", b"\r\n"),
+                (".git/e.txt", b"e\r\n"),
+            ],
+        );
+        let options = TreeOptions::new(LineEnding::Lf).include("*.txt").include("*.png").exclude("/target");
+        let mut seen = Vec::new();
+        let report = normalize_tree_with_callback(&dir, &options, |path, _| seen.push(path.strip_prefix(&dir).unwrap().to_path_buf()));
+        assert_eq!(seen, ["a.txt", "logo.png", "src/b.txt"].map(PathBuf::from));
+        assert_eq!((report.changed, report.unchanged, report.binary), (1, 1, 1));
+        assert_eq!(fs::read(dir.join("a.txt")).unwrap(), b"a\n");
+        assert_eq!(fs::read(dir.join("target/d.txt")).unwrap(), b"d\r\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dry_runs_and_errors() {
+        let dir = tree("dry-run", &[("a.txt", b"a\n"), ("b.txt", b"b\r\n")]);
+        let report = normalize_tree(&dir, &TreeOptions::new(LineEnding::CrLf).dry_run(true));
+        assert_eq!((report.changed, report.unchanged, report.stats.converted), (1, 1, 1));
+        assert_eq!(fs::read(dir.join("a.txt")).unwrap(), b"a\n");
+
+        let report = normalize_tree(dir.join("b.txt"), &TreeOptions::new(LineEnding::Lf));
+        assert_eq!(report.changed, 1);
+        let report = normalize_tree(dir.join("missing"), &TreeOptions::new(LineEnding::Lf));
+        assert_eq!(report.errors.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn root_symlink_to_a_file_keeps_the_link() {
+        let dir = tree("symlink", &[("a.txt", b"a\r\n")]);
+        let link = dir.join("link.txt");
+        std::os::unix::fs::symlink(dir.join("a.txt"), &link).unwrap();
+        assert_eq!(normalize_tree(&link, &TreeOptions::new(LineEnding::Lf)).changed, 1);
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(dir.join("a.txt")).unwrap(), b"a\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn root_symlink_to_a_directory_is_walked() {
+        let dir = tree("symlink-dir", &[("sub/a.txt", b"a\r\n")]);
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(dir.join("sub"), &link).unwrap();
+        let report = normalize_tree(&link, &TreeOptions::new(LineEnding::Lf));
+        assert_eq!((report.changed, report.errors.len()), (1, 0));
+        assert_eq!(fs::read(dir.join("sub/a.txt")).unwrap(), b"a\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+//! Normalization that also treats the Unicode line separators as newlines.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::search::{memchr3_iter, memchr4_iter};
+use crate::LineEnding;
+
+/// First byte of NEL (U+0085) in UTF-8.
+pub(crate) const NEL_LEAD: u8 = 0xC2;
+/// First byte of LINE SEPARATOR (U+2028) and PARAGRAPH SEPARATOR (U+2029) in UTF-8.
+pub(crate) const SEPARATOR_LEAD: u8 = 0xE2;
+
+/// Normalizes CR, LF, CRLF, NEL, LS and PS in `text` to `ending`.
+///
+/// The lead bytes of the separators are searched for in the same pass as CR and LF, and
+/// only confirmed where they occur, so text without separators costs one scan.
+pub(crate) fn normalize(text: &str, ending: LineEnding) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    match ending {
+        // LFs are already in the target form and need not be visited.
+        LineEnding::Lf => rewrite(text, ending, memchr3_iter(b'\r', NEL_LEAD, SEPARATOR_LEAD, bytes)),
+        LineEnding::CrLf => rewrite(text, ending, memchr4_iter([b'\n', b'\r', NEL_LEAD, SEPARATOR_LEAD], bytes)),
+    }
+}
+
+/// Rewrites the newlines among `candidates`, the positions of possible newline starts.
+fn rewrite(text: &str, ending: LineEnding, candidates: impl Iterator<Item = usize>) -> Cow<'_, str> {
+    let bytes = text.as_bytes();
+    let mut out: Option<Vec<u8>> = None;
+    let mut copied = 0;
+    let mut skip_to = 0;
+
+    for found in candidates {
+        if found < skip_to {
+            continue;
+        }
+        let Some((len, kept)) = newline_at(bytes, found, ending) else {
+            continue;
+        };
+        skip_to = found + len;
+        if kept {
+            continue;
+        }
+        let out = out.get_or_insert_with(|| Vec::with_capacity(text.len() + text.len() / 8));
+        out.extend_from_slice(&bytes[copied..found]);
+        out.extend_from_slice(ending.as_bytes());
+        copied = found + len;
+    }
+
+    match out {
+        Some(mut out) => {
+            out.extend_from_slice(&bytes[copied..]);
+            // Whole UTF-8 sequences were replaced by ASCII, so the result is still UTF-8.
+            Cow::Owned(unsafe { String::from_utf8_unchecked(out) })
+        }
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// The length of the newline starting at `pos`, and whether it already is `ending`.
+fn newline_at(bytes: &[u8], pos: usize, ending: LineEnding) -> Option<(usize, bool)> {
+    let next = |offset: usize| bytes.get(pos + offset).copied();
+    match bytes[pos] {
+        b'\n' => Some((1, ending == LineEnding::Lf)),
+        b'\r' if next(1) == Some(b'\n') => Some((2, ending == LineEnding::CrLf)),
+        b'\r' => Some((1, false)),
+        NEL_LEAD if next(1) == Some(0x85) => Some((2, false)),
+        SEPARATOR_LEAD if next(1) == Some(0x80) && matches!(next(2), Some(0xA8 | 0xA9)) => Some((3, false)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_every_separator() {
+        let text = "a\u{85}b\u{2028}c\u{2029}d\r\ne\rf\n";
+        assert_eq!(normalize(text, LineEnding::Lf), "a\nb\nc\nd\ne\nf\n");
+        assert_eq!(normalize(text, LineEnding::CrLf), "a\r\nb\r\nc\r\nd\r\ne\r\nf\r\n");
+    }
+
+    #[test]
+    fn other_characters_sharing_lead_bytes_are_kept() {
+        // U+00A9, U+2014, U+20AC and U+2027 start with the same bytes as the separators.
+        let text = "\u{a9}\u{2014}\u{20ac}\u{2027}\u{2030}";
+        assert!(matches!(normalize(text, LineEnding::Lf), Cow::Borrowed(_)));
+        assert!(matches!(normalize(text, LineEnding::CrLf), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn normalized_text_is_borrowed() {
+        assert!(matches!(normalize("a\nb\n", LineEnding::Lf), Cow::Borrowed(_)));
+        assert!(matches!(normalize("a\r\nb\r\n", LineEnding::CrLf), Cow::Borrowed(_)));
+        assert_eq!(normalize("\u{2028}", LineEnding::Lf), "\n");
+        assert_eq!(normalize("é\u{85}", LineEnding::CrLf), "é\r\n");
+    }
+}
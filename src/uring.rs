@@ -0,0 +1,179 @@
+//! File normalization on Linux `io_uring`, enabled by the `tokio-uring` feature.
+//!
+//! Same behavior as [`normalize_file`](crate::normalize_file), but the next chunk is
+//! already being read while the current one is written, keeping the disk busy. The
+//! functions must run inside [`tokio_uring::start`].
+
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+
+use tokio_uring::fs::File;
+
+use crate::engine::Engine;
+use crate::file::{create_temp_file, resolve_symlink};
+use crate::{FileReport, LineEnding, Stats};
+
+const BUFFER_SIZE: usize = 256 * 1024;
+
+/// Normalizes the newlines of a file, atomically replacing it with the result.
+///
+/// Example:
+/// ```no_run
+/// use newline_normalizer::{uring, LineEnding};
+///
+/// tokio_uring::start(async {
+///     let report = uring::normalize_file("notes.txt", LineEnding::Lf).await?;
+///     println!("rewrote {} line endings", report.stats.converted);
+///     Ok::<(), std::io::Error>(())
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub async fn normalize_file<P: AsRef<Path>>(path: P, ending: LineEnding) -> io::Result<FileReport> {
+    normalize_file_with_progress(path, ending, |_, _| {}).await
+}
+
+/// Like [`normalize_file`], calling `progress(bytes_processed, bytes_total)` after every chunk.
+pub async fn normalize_file_with_progress<P, F>(path: P, ending: LineEnding, mut progress: F) -> io::Result<FileReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(u64, u64),
+{
+    let path = &*resolve_symlink(path.as_ref())?;
+    let source = Rc::new(File::open(path).await?);
+    let metadata = fs::metadata(path)?;
+    let total = metadata.len();
+
+    let (temp_path, temp) = create_temp_file(path)?;
+    let temp = File::from_std(temp);
+    let result = async {
+        let stats = pipeline(&source, &temp, ending, &mut |done| progress(done, total)).await?;
+        if !stats.changed() {
+            return Ok(FileReport { stats, changed: false });
+        }
+        temp.sync_all().await?;
+        fs::set_permissions(&temp_path, metadata.permissions())?;
+        tokio_uring::fs::rename(&temp_path, path).await?;
+        Ok(FileReport { stats, changed: true })
+    }
+    .await;
+
+    if !matches!(result, Ok(FileReport { changed: true, .. })) {
+        let _ = tokio_uring::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// Streams `source` into `dest`, reading chunk `n + 1` while chunk `n` is written.
+async fn pipeline(source: &Rc<File>, dest: &File, ending: LineEnding, progress: &mut dyn FnMut(u64)) -> io::Result<Stats> {
+    let mut engine = Engine::new(ending);
+    let mut output = Vec::with_capacity(BUFFER_SIZE);
+    let mut spare = Vec::with_capacity(BUFFER_SIZE);
+    let mut reading = read_ahead(source, Vec::with_capacity(BUFFER_SIZE), 0);
+    let mut read_pos = 0;
+    let mut write_pos = 0;
+
+    loop {
+        let input = reading.await?;
+        if input.is_empty() {
+            break;
+        }
+        read_pos += input.len() as u64;
+        reading = read_ahead(source, mem::take(&mut spare), read_pos);
+
+        output.clear();
+        engine.normalize_into(&input, &mut output);
+        let (written, buf) = dest.write_all_at(output, write_pos).await;
+        written?;
+        write_pos += buf.len() as u64;
+        output = buf;
+        spare = input;
+        progress(read_pos);
+    }
+    Ok(engine.stats())
+}
+
+/// Starts reading up to `buf.capacity()` bytes at `pos` in the background.
+///
+/// The returned future yields `buf` holding the bytes read, empty at end of file.
+fn read_ahead(file: &Rc<File>, mut buf: Vec<u8>, pos: u64) -> impl Future<Output = io::Result<Vec<u8>>> {
+    let file = Rc::clone(file);
+    buf.clear();
+    let task = tokio_uring::spawn(async move {
+        let (read, buf) = file.read_at(buf, pos).await;
+        read.map(|_| buf)
+    });
+    async move { task.await.map_err(|e| io::Error::new(io::ErrorKind::Other, e))? }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("newline_normalizer-uring-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rewrites_multi_chunk_file() {
+        let dir = temp_dir("rewrite");
+        let path = dir.join("input.txt");
+        // A CRLF straddles the first chunk boundary.
+        let input = "x".repeat(BUFFER_SIZE - 1) + &"line\r\n".repeat(BUFFER_SIZE / 2) + "end\r";
+        fs::write(&path, &input).unwrap();
+
+        let mut calls = Vec::new();
+        let report = tokio_uring::start(normalize_file_with_progress(&path, LineEnding::Lf, |done, total| {
+            calls.push((done, total))
+        }))
+        .unwrap();
+        assert!(report.changed);
+        assert_eq!(report.stats.crlf, BUFFER_SIZE as u64 / 2);
+        let expected = "x".repeat(BUFFER_SIZE - 1) + &"line\n".repeat(BUFFER_SIZE / 2) + "end\n";
+        assert!(fs::read_to_string(&path).unwrap() == expected);
+        assert_eq!(calls.last(), Some(&(input.len() as u64, input.len() as u64)));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_normalized_file_untouched() {
+        let dir = temp_dir("untouched");
+        let path = dir.join("input.txt");
+        fs::write(&path, "a\nb\n").unwrap();
+
+        let report = tokio_uring::start(normalize_file(&path, LineEnding::Lf)).unwrap();
+        assert!(!report.changed);
+        assert_eq!(report.stats.lf, 2);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn follows_symlinks() {
+        let dir = temp_dir("symlink");
+        let (target, link) = (dir.join("target.txt"), dir.join("link.txt"));
+        fs::write(&target, "a\r\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(tokio_uring::start(normalize_file(&link, LineEnding::Lf)).unwrap().changed);
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"a\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let dir = temp_dir("missing");
+        assert!(tokio_uring::start(normalize_file(dir.join("nope.txt"), LineEnding::Lf)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
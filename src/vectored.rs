@@ -0,0 +1,221 @@
+//! Streaming to a writer with vectored writes instead of an output buffer.
+
+use std::io::{self, IoSlice, Read, Write};
+
+use crate::{LineEnding, Stats, StreamConfig};
+
+/// Most slices handed to one `write_vectored` call, matching the common `IOV_MAX`.
+const MAX_SLICES: usize = 1024;
+
+/// Like [`normalize_copy`](crate::normalize_copy), but hands the writer the unchanged
+/// spans of each chunk and the rewritten line endings as separate slices of one
+/// `write_vectored` call, so the normalized output is never copied into a buffer.
+///
+/// - Input that needs no rewriting goes out as one slice per chunk.
+/// - This pays off with writers that implement `write_vectored` natively, such as
+///   `TcpStream`, `UnixStream` and `File`. The default implementation writes one slice
+///   per call; wrap such writers in a `BufWriter`, or use `normalize_copy`.
+///
+/// Example:
+/// ```
+/// use newline_normalizer::{normalize_copy_vectored, LineEnding};
+///
+/// let mut out = Vec::new();
+/// let stats = normalize_copy_vectored(&mut "a\r\nb\rc".as_bytes(), &mut out, LineEnding::Lf).unwrap();
+/// assert_eq!(out, b"a\nb\nc");
+/// assert_eq!((stats.bytes_read, stats.bytes_written, stats.converted), (6, 5, 2));
+/// ```
+pub fn normalize_copy_vectored<R, W>(reader: &mut R, writer: &mut W, ending: LineEnding) -> io::Result<Stats>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    normalize_copy_vectored_with_config(reader, writer, ending, StreamConfig::new())
+}
+
+/// Like [`normalize_copy_vectored`], reading chunks of the size set in `config`.
+pub fn normalize_copy_vectored_with_config<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    ending: LineEnding,
+    config: StreamConfig,
+) -> io::Result<Stats>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut splitter = Splitter { ending, after_cr: false, stats: Stats::default() };
+    let mut input = vec![0; config.get_chunk_size()];
+
+    loop {
+        let read = match reader.read(&mut input) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let mut spans = Vec::new();
+        splitter.split(&input[..read], &mut spans);
+        write_all_spans(writer, &mut spans)?;
+    }
+
+    writer.flush()?;
+    Ok(splitter.stats)
+}
+
+/// Cuts chunks into the spans making up their normalized form.
+struct Splitter {
+    ending: LineEnding,
+    after_cr: bool,
+    stats: Stats,
+}
+
+impl Splitter {
+    /// Appends the spans of the normalized `chunk` to `spans`.
+    ///
+    /// A CR ending the chunk is rewritten right away; an LF starting the next chunk is
+    /// then dropped as its partner.
+    fn split<'a>(&mut self, chunk: &'a [u8], spans: &mut Vec<&'a [u8]>) {
+        let ending = self.ending.as_bytes();
+        let mut start = 0;
+        if core::mem::replace(&mut self.after_cr, false) && chunk.first() == Some(&b'\n') {
+            // The CR ending the previous chunk was counted alone; it was half of a CRLF.
+            self.stats.cr -= 1;
+            self.stats.crlf += 1;
+            if self.ending == LineEnding::CrLf {
+                self.stats.converted -= 1;
+            }
+            start = 1;
+        }
+
+        let from = start;
+        let mut skip_to = start;
+        for found in crate::search::memchr2_iter(b'\n', b'\r', &chunk[from..]).map(|found| found + from) {
+            if found < skip_to {
+                continue;
+            }
+            if chunk[found] == b'\n' {
+                self.stats.lf += 1;
+                if self.ending == LineEnding::CrLf {
+                    self.stats.converted += 1;
+                    spans.extend_from_slice(&[&chunk[start..found], ending]);
+                    start = found + 1;
+                }
+                continue;
+            }
+            match chunk.get(found + 1) {
+                Some(b'\n') => {
+                    self.stats.crlf += 1;
+                    skip_to = found + 2;
+                    if self.ending == LineEnding::Lf {
+                        // Dropping the CR leaves the LF at the start of the next span.
+                        self.stats.converted += 1;
+                        spans.push(&chunk[start..found]);
+                        start = found + 1;
+                    }
+                }
+                next => {
+                    self.stats.cr += 1;
+                    self.stats.converted += 1;
+                    self.after_cr = next.is_none();
+                    spans.extend_from_slice(&[&chunk[start..found], ending]);
+                    start = found + 1;
+                }
+            }
+        }
+        spans.push(&chunk[start..]);
+        spans.retain(|span| !span.is_empty());
+
+        self.stats.bytes_read += chunk.len() as u64;
+        self.stats.bytes_written += spans.iter().map(|span| span.len() as u64).sum::<u64>();
+    }
+}
+
+/// Writes every span, retrying with the rest after partial writes.
+fn write_all_spans<W: Write + ?Sized>(writer: &mut W, spans: &mut [&[u8]]) -> io::Result<()> {
+    let mut first = 0;
+    let mut slices = Vec::with_capacity(spans.len().min(MAX_SLICES));
+    while first < spans.len() {
+        slices.clear();
+        slices.extend(spans[first..].iter().take(MAX_SLICES).map(|span| IoSlice::new(span)));
+        let mut written = match writer.write_vectored(&slices) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(written) => written,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        while first < spans.len() && written >= spans[first].len() {
+            written -= spans[first].len();
+            first += 1;
+        }
+        if written > 0 {
+            spans[first] = &spans[first][written..];
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts at most `limit` bytes per call, counting the calls.
+    struct Trickle {
+        out: Vec<u8>,
+        limit: usize,
+        calls: usize,
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_vectored(&[IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            self.calls += 1;
+            let before = self.out.len();
+            for buf in bufs {
+                let room = self.limit - (self.out.len() - before);
+                self.out.extend_from_slice(&buf[..buf.len().min(room)]);
+            }
+            Ok(self.out.len() - before)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn matches_normalize_copy() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r\r\n\ne\r";
+        for ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let mut expected = Vec::new();
+            let expected_stats = crate::normalize_copy(&mut &input[..], &mut expected, ending).unwrap();
+            for chunk_size in 1..=input.len() {
+                let config = StreamConfig::new().chunk_size(chunk_size);
+                let mut out = Vec::new();
+                let stats = normalize_copy_vectored_with_config(&mut &input[..], &mut out, ending, config).unwrap();
+                assert_eq!(out, expected, "{ending:?} {chunk_size}");
+                assert_eq!(stats, expected_stats, "{ending:?} {chunk_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn resumes_after_partial_writes() {
+        let input = "line\n".repeat(50);
+        let mut writer = Trickle { out: Vec::new(), limit: 7, calls: 0 };
+        normalize_copy_vectored(&mut input.as_bytes(), &mut writer, LineEnding::CrLf).unwrap();
+        assert_eq!(writer.out, "line\r\n".repeat(50).as_bytes());
+    }
+
+    #[test]
+    fn normalized_input_is_written_in_one_call() {
+        let input = "line\n".repeat(1000);
+        let mut writer = Trickle { out: Vec::new(), limit: usize::MAX, calls: 0 };
+        normalize_copy_vectored(&mut input.as_bytes(), &mut writer, LineEnding::Lf).unwrap();
+        assert_eq!(writer.out, input.as_bytes());
+        assert_eq!(writer.calls, 1);
+    }
+}
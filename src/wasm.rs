@@ -0,0 +1,96 @@
+//! JavaScript bindings, enabled by the `wasm` feature, for web editors and Node.
+//!
+//! `bindings/wasm` builds these into an npm package with `wasm-pack`:
+//!
+//! ```js
+//! import { toUnix, toDos, StreamNormalizer } from "newline-normalizer-wasm";
+//!
+//! toUnix("a\r\nb"); // "a\nb"
+//! const stream = new StreamNormalizer("crlf");
+//! let out = stream.push("one\n") + stream.push("two\r") + stream.finish();
+//! ```
+//!
+//! Strings cross into WebAssembly as UTF-8 and back, which costs a copy each way.
+
+use alloc::string::String;
+
+use wasm_bindgen::prelude::*;
+
+use crate::LineEnding;
+
+/// Returns `text` with every CRLF and lone CR turned into an LF.
+#[wasm_bindgen(js_name = toUnix)]
+pub fn to_unix(text: &str) -> String {
+    LineEnding::Lf.normalize(text).into_owned()
+}
+
+/// Returns `text` with every LF and lone CR turned into a CRLF.
+#[wasm_bindgen(js_name = toDos)]
+pub fn to_dos(text: &str) -> String {
+    LineEnding::CrLf.normalize(text).into_owned()
+}
+
+/// Returns `text` normalized to `ending`, `"lf"` or `"crlf"`; throws for any other.
+#[wasm_bindgen]
+pub fn normalize(text: &str, ending: &str) -> Result<String, JsError> {
+    Ok(parse(ending)?.normalize(text).into_owned())
+}
+
+/// Normalizes text arriving in chunks, such as from a `ReadableStream`, handling a CRLF
+/// split between two chunks. Call `finish()` once the input ends and append what it returns.
+#[wasm_bindgen]
+pub struct StreamNormalizer {
+    inner: crate::StreamNormalizer,
+}
+
+#[wasm_bindgen]
+impl StreamNormalizer {
+    /// Normalizes to `ending`, `"lf"` or `"crlf"`; throws for any other.
+    #[wasm_bindgen(constructor)]
+    pub fn new(ending: &str) -> Result<StreamNormalizer, JsError> {
+        Ok(StreamNormalizer { inner: crate::StreamNormalizer::new(parse(ending)?) })
+    }
+
+    /// Returns the normalized form of the next chunk.
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.inner.push(chunk).into_owned()
+    }
+
+    /// Returns what is left once the input ends.
+    pub fn finish(&mut self) -> String {
+        self.inner.finish().into()
+    }
+}
+
+fn parse(ending: &str) -> Result<LineEnding, JsError> {
+    ending_named(ending).ok_or_else(|| JsError::new("line ending must be \"lf\" or \"crlf\""))
+}
+
+fn ending_named(ending: &str) -> Option<LineEnding> {
+    match ending {
+        "lf" => Some(LineEnding::Lf),
+        "crlf" => Some(LineEnding::CrLf),
+        _ => None,
+    }
+}
+
+// `JsError` needs a JavaScript host, so the tests keep to what has none.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_in_and_out() {
+        assert_eq!(to_unix("a\r\nb\rc"), "a\nb\nc");
+        assert_eq!(to_dos("a\nb\r\n"), "a\r\nb\r\n");
+        assert_eq!(ending_named("crlf"), Some(LineEnding::CrLf));
+        assert_eq!(ending_named("CRLF"), None);
+    }
+
+    #[test]
+    fn streams_carry_a_split_crlf() {
+        let mut stream = StreamNormalizer { inner: crate::StreamNormalizer::new(LineEnding::CrLf) };
+        let out = stream.push("one\r") + &stream.push("\ntwo\n") + &stream.push("three\r") + &stream.finish();
+        assert_eq!(out, "one\r\ntwo\r\nthree\r\n");
+    }
+}
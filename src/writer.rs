@@ -0,0 +1,148 @@
+//! `io::Write` adapter normalizing newlines before they reach the inner writer.
+
+use std::io::{self, Write};
+
+use crate::engine::Engine;
+use crate::{LineEnding, Stats, StreamConfig};
+
+/// Wraps a writer and normalizes every newline written through it.
+///
+/// - A CRLF split between two `write` calls produces one newline.
+/// - Nothing is held back, so no final flush of pending newline state is needed.
+/// - Each `write` accepts at most one chunk of [`StreamConfig`], bounding the scratch buffer.
+/// - If the inner writer fails, the data of that `write` call may be partially written.
+///
+/// Example:
+/// ```
+/// use std::io::Write;
+/// use newline_normalizer::NormalizingWriter;
+///
+/// let mut writer = NormalizingWriter::unix(Vec::new());
+/// writer.write_all(b"one\r").unwrap();
+/// writer.write_all(b"\ntwo\r\n").unwrap();
+/// assert_eq!(writer.into_inner(), b"one\ntwo\n");
+/// ```
+#[derive(Debug)]
+pub struct NormalizingWriter<W> {
+    inner: W,
+    engine: Engine,
+    scratch: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl<W: Write> NormalizingWriter<W> {
+    /// Creates a writer producing `ending`.
+    pub fn new(inner: W, ending: LineEnding) -> Self {
+        Self::with_config(inner, ending, StreamConfig::new())
+    }
+
+    /// Creates a writer producing `ending` whose buffering is bounded by `config`.
+    pub fn with_config(inner: W, ending: LineEnding, config: StreamConfig) -> Self {
+        NormalizingWriter {
+            inner,
+            engine: Engine::new(ending),
+            scratch: Vec::new(),
+            chunk_size: config.get_chunk_size(),
+        }
+    }
+
+    /// Creates a writer producing Unix newlines (`\n`).
+    pub fn unix(inner: W) -> Self {
+        Self::new(inner, LineEnding::Lf)
+    }
+
+    /// Creates a writer producing DOS newlines (`\r\n`).
+    pub fn dos(inner: W) -> Self {
+        Self::new(inner, LineEnding::CrLf)
+    }
+}
+
+impl<W> NormalizingWriter<W> {
+    /// The line ending this writer produces.
+    pub fn ending(&self) -> LineEnding {
+        self.engine.ending()
+    }
+
+    /// Counters for everything normalized so far; poll this to report progress.
+    pub fn stats(&self) -> Stats {
+        self.engine.stats()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this writer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for NormalizingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = &buf[..buf.len().min(self.chunk_size)];
+        self.scratch.clear();
+        self.engine.normalize_into(buf, &mut self.scratch);
+        self.inner.write_all(&self.scratch)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_in_pieces(ending: LineEnding, input: &[u8], size: usize) -> Vec<u8> {
+        let mut writer = NormalizingWriter::new(Vec::new(), ending);
+        for piece in input.chunks(size) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn unix_every_write_size() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            assert_eq!(write_in_pieces(LineEnding::Lf, input, size), b"a\nb\nc\n\n\nd\n");
+        }
+    }
+
+    #[test]
+    fn dos_every_write_size() {
+        let input = b"a\r\nb\rc\n\r\r\nd\r";
+        for size in 1..=input.len() {
+            assert_eq!(
+                write_in_pieces(LineEnding::CrLf, input, size),
+                b"a\r\nb\r\nc\r\n\r\n\r\nd\r\n"
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_one_chunk_per_write() {
+        let config = StreamConfig::new().chunk_size(2);
+        let mut writer = NormalizingWriter::with_config(Vec::new(), LineEnding::CrLf, config);
+        assert_eq!(writer.write(b"a\nb\n").unwrap(), 2);
+        writer.write_all(b"b\nc").unwrap();
+        assert_eq!(writer.get_ref(), b"a\r\nb\r\nc");
+    }
+
+    #[test]
+    fn reports_whole_buffer_written() {
+        let mut writer = NormalizingWriter::dos(Vec::new());
+        assert_eq!(writer.write(b"a\nb").unwrap(), 3);
+        assert_eq!(writer.get_ref(), b"a\r\nb");
+    }
+}